@@ -1,123 +1,896 @@
 use iced::{
-    alignment, theme, widget::{button, column, container, row, text, text_input},
-    Command, Element, Length,
+    alignment, clipboard, keyboard, theme, widget::{button, column, container, image, row, scrollable, text, text_input},
+    futures::{channel::mpsc, stream, StreamExt},
+    Command, ContentFit, Element, Font, Length, Subscription,
 };
 use reqwest;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::config::Config;
+use min_desk::listing::format_file_size;
+use min_desk::page::{
+    download, extract_links, extract_meta_refresh, extract_readable, extract_text_from_html,
+    fetch_image, fetch_page_with_progress, remove_text, ContentBlock, DownloadOffer, Fetched,
+    HttpClient, Link, Page, Proxies, ResponseInfo,
+};
+use regex::Regex;
+
+use crate::config::{Bookmark, BrowserConfig, Config, TextFilter};
+use crate::widgets;
 
 #[derive(Debug, Clone)]
 pub enum Message {
     Navigate(String),
     UpdateUrl(String),
+    UpdateStartQuery(String),
+    FocusUrlBar,
+    /// Ctrl+Shift+V: open the URL on the clipboard.
+    PasteAndGo,
+    Pasted(Option<String>),
     GoBack,
     GoForward,
     Refresh,
-    LoadComplete(Result<String, String>),
+    LoadProgress(LoadId, usize),
+    LoadComplete(LoadId, Result<Page, String>),
+    /// A meta refresh on the tab's page, from and to, came due.
+    MetaRefresh(u64, String, String),
+    ImageLoaded(u64, String, Result<image::Handle, String>),
+    /// The page was scrolled to this offset.
+    Scrolled(scrollable::AbsoluteOffset),
+    ToggleReaderMode,
+    ToggleLinks,
+    ToggleMonospace,
+    /// Adds the active page to the bookmarks, or removes it.
+    ToggleBookmark,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    SavePage(SaveFormat),
+    PageSaved(u64, Result<PathBuf, String>),
+    /// The load turned out to be a file, offered for download.
+    DownloadOffered(LoadId, DownloadOffer),
+    /// Downloads the active tab's offered file.
+    ConfirmDownload,
+    DismissDownload,
+    DownloadComplete(u64, Result<PathBuf, String>),
+    NewTab,
+    CloseTab(usize),
+    SwitchTab(usize),
+    Tick,
     Close,
 }
 
+/// Which tab a page load is for, and which of its navigations, so the
+/// results of a load the tab has since moved on from can be dropped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadId {
+    tab: u64,
+    navigation: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SaveFormat {
+    Html,
+    Text,
+}
+
 #[derive(Debug, Clone)]
 struct WebPage {
     url: String,
     content: String,
 }
 
-pub struct Browser {
-    config: Config,
+/// Fetch state of an inline image, keyed by its absolute URL.
+#[derive(Debug, Clone)]
+enum ImageState {
+    Loading,
+    Loaded(image::Handle),
+    Failed,
+}
+
+/// A page kept for instant back/forward.
+struct CachedPage {
+    url: String,
+    page: Page,
+    fetched: Instant,
+}
+
+/// URL of the start page, which is drawn by the browser rather than fetched.
+const START_PAGE: &str = "about:blank";
+
+/// Recently visited pages listed on the start page.
+const MAX_RECENT_PAGES: usize = 8;
+
+/// A page listed under "Recently visited".
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct RecentPage {
+    title: String,
+    url: String,
+}
+
+/// What gets saved of the open tabs so they can be reopened next run.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Session {
+    tabs: Vec<SessionTab>,
+    active_tab: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct SessionTab {
+    history: Vec<String>,
+    history_index: usize,
+}
+
+/// A single page: its own URL, content, history and loading state.
+struct Tab {
+    /// Stable identifier used to route fetch results back to this tab,
+    /// since indices shift as tabs are closed.
+    id: u64,
     current_url: String,
     url_input: String,
-    content: String,
+    /// Contents of the page's `<title>`, if it had one.
+    page_title: Option<String>,
+    content: Vec<ContentBlock>,
+    /// Raw HTML of the current page, kept so it can be saved offline.
+    html: Option<String>,
+    reader_blocks: Vec<ContentBlock>,
+    links: Vec<Link>,
+    images: HashMap<String, ImageState>,
+    /// Response details of the current page.
+    response: Option<ResponseInfo>,
+    reader_mode: bool,
     loading: bool,
+    /// Bumped by every navigation, so only the latest load's results are
+    /// shown.
+    navigation: u64,
+    /// Seconds spent on the current load, advanced by the app-wide tick.
+    loading_elapsed: u64,
+    /// Bytes of the page being loaded received so far.
+    loading_received: usize,
+    /// Meta refresh redirects followed in a row, reset by any other load.
+    meta_redirects: usize,
     error: Option<String>,
+    notice: Option<String>,
+    /// A file a link led to, waiting for the user to download it.
+    offer: Option<DownloadOffer>,
     history: Vec<String>,
     history_index: usize,
+    /// History and position of the page on screen while another loads, to
+    /// return to if the load turns out to be a download.
+    before_load: Option<(Vec<String>, usize)>,
+    /// Where the page is scrolled to.
+    scroll_offset: scrollable::AbsoluteOffset,
+    /// Scroll positions of pages left via Back, Forward or a link, by
+    /// history index.
+    history_scroll: HashMap<usize, scrollable::AbsoluteOffset>,
+    /// Position to restore once the page being loaded arrives. Fresh pages
+    /// start at the top.
+    restore_scroll: Option<scrollable::AbsoluteOffset>,
 }
 
-impl Browser {
-    pub fn new(config: Config) -> Self {
-        let homepage = config.applications.browser.homepage.clone();
+impl Tab {
+    /// A tab showing the start page.
+    fn new(id: u64) -> Self {
         Self {
-            config,
-            current_url: homepage.clone(),
-            url_input: homepage.clone(),
-            content: String::from("Welcome to MinDesk Browser\n\nEnter a URL above to start browsing."),
+            id,
+            current_url: START_PAGE.to_string(),
+            url_input: String::new(),
+            page_title: None,
+            content: Vec::new(),
+            html: None,
+            reader_blocks: Vec::new(),
+            links: Vec::new(),
+            images: HashMap::new(),
+            response: None,
+            reader_mode: false,
             loading: false,
+            navigation: 0,
+            loading_elapsed: 0,
+            loading_received: 0,
+            meta_redirects: 0,
             error: None,
-            history: vec![homepage],
+            notice: None,
+            offer: None,
+            history: vec![START_PAGE.to_string()],
             history_index: 0,
+            before_load: None,
+            scroll_offset: scrollable::AbsoluteOffset::default(),
+            history_scroll: HashMap::new(),
+            restore_scroll: None,
+        }
+    }
+
+    /// Label for the tab strip: the page's title or first heading, or
+    /// failing both its URL.
+    fn title(&self) -> String {
+        if self.is_start_page() {
+            return "New Tab".to_string();
+        }
+        let title = self
+            .page_title
+            .clone()
+            .or_else(|| {
+                self.content.iter().find_map(|block| match block {
+                    ContentBlock::Heading(_, heading) => Some(heading.clone()),
+                    _ => None,
+                })
+            })
+            .unwrap_or_else(|| {
+                self.current_url
+                    .trim_start_matches("https://")
+                    .trim_start_matches("http://")
+                    .to_string()
+            });
+
+        if title.chars().count() > TAB_TITLE_LENGTH {
+            format!("{}…", title.chars().take(TAB_TITLE_LENGTH).collect::<String>())
+        } else {
+            title
+        }
+    }
+
+    /// Moves through history by `offset`, returning the URL to load.
+    fn go(&mut self, offset: isize) -> Option<String> {
+        let index = self.history_index.checked_add_signed(offset)?;
+        let url = self.history.get(index)?.clone();
+        self.mark_position();
+        self.history_scroll.insert(self.history_index, self.scroll_offset);
+        self.restore_scroll = self.history_scroll.get(&index).copied();
+        self.history_index = index;
+        self.current_url = url.clone();
+        self.url_input = url.clone();
+        self.start_loading();
+        Some(url)
+    }
+
+    /// Notes where the tab is in its history before a navigation changes
+    /// it. A navigation that interrupts another keeps the earlier mark,
+    /// since the page on screen is still the one from before both.
+    fn mark_position(&mut self) {
+        if !self.loading || self.before_load.is_none() {
+            self.before_load = Some((self.history.clone(), self.history_index));
+        }
+    }
+
+    /// Undoes the navigation that led to a download offer, so the URL bar
+    /// and history match the page still on screen.
+    fn return_from_download(&mut self) {
+        let Some((history, index)) = self.before_load.take() else {
+            return;
+        };
+        self.history = history;
+        self.history_index = index;
+        self.current_url = self.history[index].clone();
+        self.url_input = if self.is_start_page() {
+            String::new()
+        } else {
+            self.current_url.clone()
+        };
+    }
+
+    fn is_start_page(&self) -> bool {
+        self.current_url == START_PAGE
+    }
+
+    /// Clears the page away for the start page, once `current_url` is set
+    /// to it.
+    fn show_start_page(&mut self) {
+        self.loading = false;
+        self.before_load = None;
+        self.navigation += 1;
+        self.url_input.clear();
+        self.page_title = None;
+        self.content.clear();
+        self.html = None;
+        self.reader_blocks.clear();
+        self.links.clear();
+        self.images.clear();
+        self.response = None;
+        self.error = None;
+        self.notice = None;
+        self.offer = None;
+        self.scroll_offset = scrollable::AbsoluteOffset::default();
+        self.restore_scroll = None;
+    }
+
+    /// Identifies the load of the tab's current navigation.
+    fn load_id(&self) -> LoadId {
+        LoadId {
+            tab: self.id,
+            navigation: self.navigation,
+        }
+    }
+
+    fn can_go_back(&self) -> bool {
+        self.history_index > 0
+    }
+
+    fn can_go_forward(&self) -> bool {
+        self.history_index + 1 < self.history.len()
+    }
+
+    fn start_loading(&mut self) {
+        self.loading = true;
+        self.navigation += 1;
+        self.loading_elapsed = 0;
+        self.loading_received = 0;
+        self.meta_redirects = 0;
+        self.notice = None;
+        self.offer = None;
+    }
+
+    /// Marks the page's images as loading, returning the URLs to fetch.
+    /// Images past `MAX_IMAGES_PER_PAGE` are left as alt text.
+    fn start_image_loads(&mut self) -> Vec<String> {
+        let sources: Vec<String> = self
+            .content
+            .iter()
+            .chain(&self.reader_blocks)
+            .filter_map(|block| match block {
+                ContentBlock::Image { src, .. } => Some(src.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let mut pending = Vec::new();
+        for src in sources {
+            if self.images.len() >= MAX_IMAGES_PER_PAGE {
+                break;
+            }
+            if !self.images.contains_key(&src) {
+                self.images.insert(src.clone(), ImageState::Loading);
+                pending.push(src);
+            }
         }
+        pending
+    }
+}
+
+pub struct Browser {
+    config: Config,
+    tabs: Vec<Tab>,
+    active_tab: usize,
+    next_tab_id: u64,
+    /// Multiplier applied to the content text size.
+    zoom: f32,
+    show_links: bool,
+    downloads: Vec<PathBuf>,
+    /// Recently loaded pages, least recently used first.
+    page_cache: VecDeque<CachedPage>,
+    /// `text_filters` from the config, by host, compiled once per config.
+    text_filters: Vec<(String, Regex)>,
+    /// Shared by every request so connections are reused, and rebuilt only
+    /// when the proxies change. The error if a proxy is invalid.
+    client: Result<Arc<HttpClient>, String>,
+    /// Pages loaded lately in any tab, most recent first.
+    recent: Vec<RecentPage>,
+    /// What's typed in the start page's search box.
+    start_query: String,
+}
+
+impl Browser {
+    pub fn new(config: Config) -> (Self, Command<Message>) {
+        let zoom = config.applications.browser.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        let text_filters = compile_text_filters(&config.applications.browser.text_filters);
+        let client = HttpClient::new(proxies(&config.applications.browser)).map(Arc::new);
+        let mut browser = Self {
+            config,
+            text_filters,
+            client,
+            tabs: vec![Tab::new(0)],
+            active_tab: 0,
+            next_tab_id: 1,
+            zoom,
+            show_links: false,
+            downloads: Vec::new(),
+            page_cache: VecDeque::new(),
+            recent: load_recent_pages().unwrap_or_else(|e| {
+                log::warn!("Ignoring browsing history: {}", e);
+                Vec::new()
+            }),
+            start_query: String::new(),
+        };
+
+        let command = if browser.config.applications.browser.restore_session {
+            browser.restore_session()
+        } else {
+            Command::none()
+        };
+        (browser, command)
+    }
+
+    /// Reopens the tabs saved by the last run and starts loading them. A
+    /// missing or unreadable session leaves the single home tab in place.
+    fn restore_session(&mut self) -> Command<Message> {
+        let session = match load_session() {
+            Ok(Some(session)) => session,
+            Ok(None) => return Command::none(),
+            Err(e) => {
+                log::warn!("Ignoring browser session: {}", e);
+                return Command::none();
+            }
+        };
+
+        let tabs: Vec<Tab> = session
+            .tabs
+            .into_iter()
+            .filter(|saved| !saved.history.is_empty())
+            .enumerate()
+            .map(|(id, saved)| {
+                let mut tab = Tab::new(id as u64);
+                tab.history_index = saved.history_index.min(saved.history.len() - 1);
+                tab.history = saved.history;
+                tab.current_url = tab.history[tab.history_index].clone();
+                if tab.is_start_page() {
+                    tab.show_start_page();
+                } else {
+                    tab.url_input = tab.current_url.clone();
+                    tab.start_loading();
+                }
+                tab
+            })
+            .collect();
+
+        if tabs.is_empty() {
+            return Command::none();
+        }
+
+        let client = self.client.clone();
+        let loads: Vec<_> = tabs
+            .iter()
+            .filter(|tab| !tab.is_start_page())
+            .map(|tab| load(tab.load_id(), tab.current_url.clone(), client.clone()))
+            .collect();
+        self.next_tab_id = tabs.len() as u64;
+        self.active_tab = session.active_tab.min(tabs.len() - 1);
+        self.tabs = tabs;
+        Command::batch(loads)
+    }
+
+    fn save_session(&self) {
+        if !self.config.applications.browser.restore_session {
+            return;
+        }
+
+        let session = Session {
+            tabs: self
+                .tabs
+                .iter()
+                .map(|tab| SessionTab {
+                    history: tab.history.clone(),
+                    history_index: tab.history_index,
+                })
+                .collect(),
+            active_tab: self.active_tab,
+        };
+        if let Err(e) = write_session(&session) {
+            log::warn!("Failed to save browser session: {}", e);
+        }
+    }
+
+    /// Replaces the config after settings are saved.
+    pub fn set_config(&mut self, config: Config) {
+        if config.applications.browser.text_filters != self.config.applications.browser.text_filters {
+            self.text_filters = compile_text_filters(&config.applications.browser.text_filters);
+        }
+        let proxies = proxies(&config.applications.browser);
+        if self.client.as_ref().map(|client| client.proxies()) != Ok(&proxies) {
+            self.client = HttpClient::new(proxies).map(Arc::new);
+        }
+        self.config = config;
+    }
+
+    /// The text filters for the site at `url`.
+    fn text_filters_for(&self, url: &str) -> Vec<Regex> {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_lowercase));
+        let Some(host) = host else {
+            return Vec::new();
+        };
+        self.text_filters
+            .iter()
+            .filter(|(filter_host, _)| {
+                host == *filter_host
+                    || host.strip_suffix(filter_host.as_str()).is_some_and(|sub| sub.ends_with('.'))
+            })
+            .map(|(_, pattern)| pattern.clone())
+            .collect()
+    }
+
+    /// Host of the active tab's page, if its URL has one.
+    pub fn current_host(&self) -> Option<String> {
+        reqwest::Url::parse(&self.tab().current_url)
+            .ok()?
+            .host_str()
+            .map(str::to_string)
     }
 
     pub fn update(&mut self, message: Message) -> Command<Message> {
+        // Saved as tabs and history change rather than on exit, which a
+        // crash or killed session would skip
+        let changes_session = matches!(
+            message,
+            Message::Navigate(_)
+                | Message::Pasted(_)
+                | Message::GoBack
+                | Message::GoForward
+                | Message::NewTab
+                | Message::CloseTab(_)
+                | Message::SwitchTab(_)
+                | Message::DownloadOffered(..)
+        );
+
+        let command = self.handle(message);
+        if changes_session {
+            self.save_session();
+        }
+        command
+    }
+
+    fn handle(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::UpdateUrl(url) => {
-                self.url_input = url;
+                self.tab_mut().url_input = url;
+                Command::none()
+            }
+            Message::UpdateStartQuery(query) => {
+                self.start_query = query;
                 Command::none()
             }
+            Message::FocusUrlBar => Command::batch(vec![
+                text_input::focus(url_input_id()),
+                text_input::select_all(url_input_id()),
+            ]),
+            Message::PasteAndGo => clipboard::read(Message::Pasted),
+            Message::Pasted(contents) => {
+                let Some(contents) = contents.map(|contents| contents.trim().to_string()) else {
+                    return Command::none();
+                };
+                if contents.is_empty() {
+                    return Command::none();
+                }
+
+                // Anything else is left in the bar to be edited or searched
+                if !contents.contains(char::is_whitespace) && looks_like_url(&contents) {
+                    return self.handle(Message::Navigate(contents));
+                }
+                self.tab_mut().url_input = contents;
+                text_input::focus(url_input_id())
+            }
             Message::Navigate(url) => {
-                let url = if !url.starts_with("http://") && !url.starts_with("https://") {
-                    format!("https://{}", url)
+                // Nothing typed, or about:blank, goes back to the start page
+                let url = if url.trim().is_empty() || url.trim() == START_PAGE {
+                    START_PAGE.to_string()
                 } else {
-                    url
+                    resolve_input(&url, &self.config.applications.browser)
                 };
+                self.start_query.clear();
+                let client = self.client.clone();
 
-                self.current_url = url.clone();
-                self.url_input = url.clone();
-                self.loading = true;
-                self.error = None;
+                let tab = self.tab_mut();
+                tab.mark_position();
+                tab.current_url = url.clone();
+                tab.url_input = url.clone();
+                tab.start_loading();
+                tab.error = None;
 
                 // Update history
-                if self.history_index < self.history.len() - 1 {
-                    self.history.truncate(self.history_index + 1);
-                }
-                self.history.push(url.clone());
-                self.history_index = self.history.len() - 1;
-
-                Command::perform(fetch_page(url), Message::LoadComplete)
-            }
-            Message::GoBack => {
-                if self.history_index > 0 {
-                    self.history_index -= 1;
-                    let url = self.history[self.history_index].clone();
-                    self.current_url = url.clone();
-                    self.url_input = url.clone();
-                    self.loading = true;
-                    Command::perform(fetch_page(url), Message::LoadComplete)
-                } else {
-                    Command::none()
+                let index = tab.history_index;
+                tab.history_scroll.insert(index, tab.scroll_offset);
+                tab.history_scroll.retain(|&entry, _| entry <= index);
+                tab.restore_scroll = None;
+                if tab.history_index < tab.history.len() - 1 {
+                    tab.history.truncate(tab.history_index + 1);
                 }
-            }
-            Message::GoForward => {
-                if self.history_index < self.history.len() - 1 {
-                    self.history_index += 1;
-                    let url = self.history[self.history_index].clone();
-                    self.current_url = url.clone();
-                    self.url_input = url.clone();
-                    self.loading = true;
-                    Command::perform(fetch_page(url), Message::LoadComplete)
-                } else {
-                    Command::none()
+                tab.history.push(url.clone());
+                tab.history_index = tab.history.len() - 1;
+
+                if tab.is_start_page() {
+                    tab.show_start_page();
+                    return text_input::focus(start_search_id());
                 }
+                load(tab.load_id(), url, client)
             }
+            Message::GoBack => self.go(-1),
+            Message::GoForward => self.go(1),
             Message::Refresh => {
-                self.loading = true;
-                self.error = None;
-                Command::perform(fetch_page(self.current_url.clone()), Message::LoadComplete)
+                if self.tab().is_start_page() {
+                    return Command::none();
+                }
+                let url = self.tab().current_url.clone();
+                self.page_cache.retain(|page| page.url != url);
+                let client = self.client.clone();
+
+                let tab = self.tab_mut();
+                tab.mark_position();
+                tab.start_loading();
+                tab.restore_scroll = Some(tab.scroll_offset);
+                tab.error = None;
+                load(tab.load_id(), url, client)
+            }
+            Message::Scrolled(offset) => {
+                self.tab_mut().scroll_offset = offset;
+                Command::none()
+            }
+            Message::LoadProgress(load, received) => {
+                if let Some(tab) = self.tab_for_load(load) {
+                    tab.loading_received = received;
+                }
+                Command::none()
             }
-            Message::LoadComplete(result) => {
-                self.loading = false;
+            Message::LoadComplete(load, result) => {
+                // The tab may have been closed, or navigated elsewhere, while
+                // loading
+                let Some(url) = self.tab_for_load(load).map(|tab| tab.current_url.clone()) else {
+                    return Command::none();
+                };
+                let text_filters = self.text_filters_for(&url);
+                if let Ok(page) = &result {
+                    self.cache_page(url, page.clone());
+                }
+
+                let load_images = self.config.applications.browser.load_images;
+                let client = self.client.clone();
+                let id = load.tab;
+                let active = self.tab().id == id;
+                let Some(tab) = self.tab_for_load(load) else {
+                    return Command::none();
+                };
+
+                tab.loading = false;
+                tab.before_load = None;
+                tab.images.clear();
+                let mut commands = Vec::new();
+                let mut visited = None;
                 match result {
-                    Ok(content) => {
-                        self.content = content;
-                        self.error = None;
+                    Ok(Page { html, info }) => {
+                        let redirects = tab.meta_redirects;
+                        let refresh = extract_meta_refresh(&html, &tab.current_url).filter(|refresh| {
+                            refresh.delay <= MAX_META_REFRESH_DELAY && redirects < MAX_META_REDIRECTS
+                        });
+                        if let Some(refresh) = refresh {
+                            tab.notice = Some(format!("Redirecting to {}…", refresh.url));
+                            let from = tab.current_url.clone();
+                            commands.push(Command::perform(
+                                tokio::time::sleep(Duration::from_secs(refresh.delay)),
+                                move |_| Message::MetaRefresh(id, from, refresh.url),
+                            ));
+                        }
+                        tab.response = info;
+                        let page = extract_text_from_html(&html);
+                        tab.page_title = page.title;
+                        tab.content = page.blocks;
+                        tab.reader_blocks = extract_readable(&html);
+                        remove_text(&mut tab.content, &text_filters);
+                        remove_text(&mut tab.reader_blocks, &text_filters);
+                        resolve_image_sources(&mut tab.content, &tab.current_url);
+                        resolve_image_sources(&mut tab.reader_blocks, &tab.current_url);
+                        tab.links = extract_links(&html, &tab.current_url);
+                        tab.html = Some(html);
+                        tab.error = None;
+                        visited = Some(RecentPage {
+                            title: tab.page_title.clone().unwrap_or_else(|| tab.current_url.clone()),
+                            url: tab.current_url.clone(),
+                        });
                     }
                     Err(error) => {
-                        self.error = Some(error);
-                        self.content = String::new();
+                        tab.error = Some(error);
+                        tab.response = None;
+                        tab.page_title = None;
+                        tab.content.clear();
+                        tab.html = None;
+                        tab.reader_blocks.clear();
+                        tab.links.clear();
                     }
                 }
+
+                tab.scroll_offset = tab.restore_scroll.take().unwrap_or_default();
+                // Background tabs are scrolled when switched to
+                if active {
+                    commands.push(scrollable::scroll_to(content_scroll_id(), tab.scroll_offset));
+                }
+
+                if load_images {
+                    commands.extend(
+                        tab.start_image_loads()
+                            .into_iter()
+                            .map(|url| load_image(id, url, client.clone())),
+                    );
+                }
+                if let Some(page) = visited {
+                    self.remember_visit(page);
+                }
+                Command::batch(commands)
+            }
+            Message::MetaRefresh(id, from, to) => {
+                let client = self.client.clone();
+                // Dropped if the user has moved on in the meantime
+                let Some(tab) = self.tab_by_id(id) else {
+                    return Command::none();
+                };
+                if tab.loading || tab.current_url != from {
+                    return Command::none();
+                }
+
+                let redirects = tab.meta_redirects + 1;
+                tab.mark_position();
+                tab.start_loading();
+                tab.meta_redirects = redirects;
+                tab.error = None;
+                // Replace rather than add to history, so Back doesn't land
+                // on the redirect again
+                tab.history[tab.history_index] = to.clone();
+                tab.restore_scroll = None;
+                tab.current_url = to.clone();
+                tab.url_input = to.clone();
+                load(tab.load_id(), to, client)
+            }
+            Message::ImageLoaded(id, url, result) => {
+                // Results for a page that has since been replaced are dropped
+                if let Some(state) = self.tab_by_id(id).and_then(|tab| tab.images.get_mut(&url)) {
+                    *state = match result {
+                        Ok(handle) => ImageState::Loaded(handle),
+                        Err(e) => {
+                            log::debug!("Failed to load image {}: {}", url, e);
+                            ImageState::Failed
+                        }
+                    };
+                }
+                Command::none()
+            }
+            Message::ToggleReaderMode => {
+                let tab = self.tab_mut();
+                tab.reader_mode = !tab.reader_mode;
+                Command::none()
+            }
+            Message::ToggleLinks => {
+                self.show_links = !self.show_links;
+                Command::none()
+            }
+            Message::ToggleMonospace => {
+                let monospace = !self.config.applications.browser.monospace;
+                self.config.applications.browser.monospace = monospace;
+                if let Err(e) = Config::persist(|config| config.applications.browser.monospace = monospace) {
+                    self.tab_mut().notice = Some(format!("Failed to save monospace setting: {}", e));
+                }
+                Command::none()
+            }
+            Message::ToggleBookmark => {
+                let url = self.tab().current_url.clone();
+                let mut bookmarks = self.config.applications.browser.bookmarks.clone();
+                if let Some(index) = bookmarks.iter().position(|bookmark| bookmark.url == url) {
+                    bookmarks.remove(index);
+                } else {
+                    bookmarks.push(Bookmark {
+                        title: self.tab().title(),
+                        url,
+                    });
+                }
+                let saved = bookmarks.clone();
+                match Config::persist(|config| config.applications.browser.bookmarks = saved) {
+                    Ok(()) => self.config.applications.browser.bookmarks = bookmarks,
+                    Err(e) => self.tab_mut().notice = Some(format!("Failed to save bookmark: {}", e)),
+                }
+                Command::none()
+            }
+            Message::ZoomIn => {
+                self.set_zoom(self.zoom + ZOOM_STEP);
+                Command::none()
+            }
+            Message::ZoomOut => {
+                self.set_zoom(self.zoom - ZOOM_STEP);
+                Command::none()
+            }
+            Message::ZoomReset => {
+                self.set_zoom(1.0);
+                Command::none()
+            }
+            Message::SavePage(format) => {
+                let directory = self.save_directory();
+                let tab = self.tab();
+                let contents = match (format, &tab.html) {
+                    (SaveFormat::Html, Some(html)) => html.clone(),
+                    _ => blocks_to_text(&tab.content),
+                };
+                let extension = if format == SaveFormat::Html && tab.html.is_some() {
+                    "html"
+                } else {
+                    "txt"
+                };
+                let path = directory.join(format!("{}.{}", page_file_name(&tab.current_url), extension));
+
+                let id = tab.id;
+                Command::perform(save_page(path, contents), move |result| Message::PageSaved(id, result))
+            }
+            Message::PageSaved(id, result) => {
+                if let Some(tab) = self.tab_by_id(id) {
+                    match result {
+                        Ok(path) => tab.notice = Some(format!("Saved to {}", path.display())),
+                        Err(e) => tab.notice = Some(format!("Failed to save page: {}", e)),
+                    }
+                }
+                Command::none()
+            }
+            Message::DownloadOffered(load, offer) => {
+                // The previous page stays on screen, so its URL goes back
+                // in the bar
+                if let Some(tab) = self.tab_for_load(load) {
+                    tab.loading = false;
+                    tab.return_from_download();
+                    tab.offer = Some(offer);
+                }
+                Command::none()
+            }
+            Message::ConfirmDownload => {
+                let client = self.client.clone();
+                let tab = self.tab_mut();
+                let Some(offer) = tab.offer.take() else {
+                    return Command::none();
+                };
+                tab.notice = Some(format!("Downloading {}…", offer_file_name(&offer)));
+
+                let id = tab.id;
+                Command::perform(
+                    async move { download(offer, client?).await },
+                    move |result| Message::DownloadComplete(id, result),
+                )
+            }
+            Message::DismissDownload => {
+                self.tab_mut().offer = None;
+                Command::none()
+            }
+            Message::DownloadComplete(id, result) => {
+                let notice = match &result {
+                    Ok(path) => format!("Downloaded to {}", path.display()),
+                    Err(e) => e.clone(),
+                };
+                if let Some(tab) = self.tab_by_id(id) {
+                    tab.notice = Some(notice);
+                }
+                if let Ok(path) = result {
+                    self.downloads.push(path);
+                }
+                Command::none()
+            }
+            Message::NewTab => {
+                let id = self.next_tab_id;
+                self.next_tab_id += 1;
+                self.tabs.push(Tab::new(id));
+                self.active_tab = self.tabs.len() - 1;
+                self.start_query.clear();
+                text_input::focus(start_search_id())
+            }
+            Message::CloseTab(index) => {
+                if index < self.tabs.len() {
+                    self.tabs.remove(index);
+                }
+
+                // Always keep one tab open
+                if self.tabs.is_empty() {
+                    return self.handle(Message::NewTab);
+                }
+                if self.active_tab > index || self.active_tab >= self.tabs.len() {
+                    self.active_tab = self.active_tab.saturating_sub(1);
+                }
+                scrollable::scroll_to(content_scroll_id(), self.tab().scroll_offset)
+            }
+            Message::SwitchTab(index) => {
+                if index >= self.tabs.len() {
+                    return Command::none();
+                }
+                // Tabs share one scrollable, so put back this tab's position
+                self.active_tab = index;
+                scrollable::scroll_to(content_scroll_id(), self.tab().scroll_offset)
+            }
+            Message::Tick => {
+                for tab in self.tabs.iter_mut().filter(|tab| tab.loading) {
+                    tab.loading_elapsed += 1;
+                }
                 Command::none()
             }
             Message::Close => {
@@ -126,19 +899,204 @@ impl Browser {
         }
     }
 
+    /// Whether any tab is still waiting for a page.
+    pub fn is_loading(&self) -> bool {
+        self.tabs.iter().any(|tab| tab.loading)
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        keyboard::on_key_press(|key, modifiers| {
+            if !modifiers.command() {
+                return None;
+            }
+            match key.as_ref() {
+                keyboard::Key::Character("+" | "=") => Some(Message::ZoomIn),
+                keyboard::Key::Character("-") => Some(Message::ZoomOut),
+                keyboard::Key::Character("0") => Some(Message::ZoomReset),
+                keyboard::Key::Character("t") => Some(Message::NewTab),
+                keyboard::Key::Character("l") => Some(Message::FocusUrlBar),
+                keyboard::Key::Character("v" | "V") if modifiers.shift() => Some(Message::PasteAndGo),
+                _ => None,
+            }
+        })
+    }
+
+    /// Moves the active tab through its history, showing a cached copy of
+    /// the page when there is one instead of fetching it again.
+    fn go(&mut self, offset: isize) -> Command<Message> {
+        let client = self.client.clone();
+        let tab = self.tab_mut();
+        let Some(url) = tab.go(offset) else {
+            return Command::none();
+        };
+        let id = tab.load_id();
+        if tab.is_start_page() {
+            tab.show_start_page();
+            return Command::none();
+        }
+
+        match self.cached_page(&url) {
+            Some(page) => self.handle(Message::LoadComplete(id, Ok(page))),
+            None => load(id, url, client),
+        }
+    }
+
+    /// Moves `page` to the top of the recently visited list and saves it.
+    fn remember_visit(&mut self, page: RecentPage) {
+        self.recent.retain(|recent| recent.url != page.url);
+        self.recent.insert(0, page);
+        self.recent.truncate(MAX_RECENT_PAGES);
+        if let Err(e) = write_recent_pages(&self.recent) {
+            log::warn!("Failed to save browsing history: {}", e);
+        }
+    }
+
+    fn cache_page(&mut self, url: String, page: Page) {
+        // Showing a cached copy doesn't make it any fresher
+        let fetched = match self.page_cache.iter().position(|cached| cached.url == url) {
+            Some(index) => self
+                .page_cache
+                .remove(index)
+                .filter(|cached| cached.page.html == page.html)
+                .map_or_else(Instant::now, |cached| cached.fetched),
+            None => Instant::now(),
+        };
+
+        if self.page_cache.len() >= PAGE_CACHE_SIZE {
+            self.page_cache.pop_front();
+        }
+        self.page_cache.push_back(CachedPage { url, page, fetched });
+    }
+
+    /// A fresh cached copy of `url`, marking it as recently used.
+    fn cached_page(&mut self, url: &str) -> Option<Page> {
+        self.page_cache.retain(|cached| cached.fetched.elapsed() < PAGE_CACHE_TTL);
+        let index = self.page_cache.iter().position(|cached| cached.url == url)?;
+        let cached = self.page_cache.remove(index)?;
+        let page = cached.page.clone();
+        self.page_cache.push_back(cached);
+        Some(page)
+    }
+
+    fn tab(&self) -> &Tab {
+        &self.tabs[self.active_tab]
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active_tab]
+    }
+
+    fn tab_by_id(&mut self, id: u64) -> Option<&mut Tab> {
+        self.tabs.iter_mut().find(|tab| tab.id == id)
+    }
+
+    /// The tab `load` is for, if it's still open and hasn't navigated
+    /// since.
+    fn tab_for_load(&mut self, load: LoadId) -> Option<&mut Tab> {
+        self.tab_by_id(load.tab).filter(|tab| tab.navigation == load.navigation)
+    }
+
+    fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.config.applications.browser.zoom = self.zoom;
+
+        let zoom = self.zoom;
+        if let Err(e) = Config::persist(|config| config.applications.browser.zoom = zoom) {
+            self.tab_mut().notice = Some(format!("Failed to save zoom level: {}", e));
+        }
+    }
+
+    fn content_font(&self) -> Font {
+        if self.config.applications.browser.monospace {
+            Font::MONOSPACE
+        } else {
+            Font::DEFAULT
+        }
+    }
+
+    /// The tab's images, or `None` when inline images are turned off.
+    fn images<'a>(&self, tab: &'a Tab) -> Option<&'a HashMap<String, ImageState>> {
+        self.config.applications.browser.load_images.then_some(&tab.images)
+    }
+
+    /// Saved pages go to the file manager's default directory.
+    fn save_directory(&self) -> PathBuf {
+        let dir = PathBuf::from(&self.config.applications.file_manager.default_path);
+        if dir.is_dir() {
+            dir
+        } else {
+            dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
+        }
+    }
+
     pub fn view(&self) -> Element<Message> {
         let header = self.create_header();
+        let tab_strip = self.create_tab_strip();
         let navigation = self.create_navigation();
         let content = self.create_content();
 
-        let main_content = column![
+        let mut main_content = column![
             header,
+            tab_strip,
             navigation,
-            content,
         ]
         .spacing(5)
         .padding(10);
 
+        if let Some(info) = &self.tab().response {
+            main_content = main_content.push(
+                text(response_summary(info, &self.tab().current_url))
+                    .size(11)
+                    .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6)))
+            );
+        }
+
+        if let Some(notice) = &self.tab().notice {
+            main_content = main_content.push(
+                text(notice)
+                    .size(12)
+                    .style(theme::Text::Color(iced::Color::from_rgb(0.4, 0.8, 0.4)))
+            );
+        }
+
+        if let Some(offer) = &self.tab().offer {
+            main_content = main_content.push(
+                row![
+                    text(offer_summary(offer)).size(12),
+                    button(text("Download").size(12))
+                        .on_press(Message::ConfirmDownload)
+                        .padding(5),
+                    button(text("Cancel").size(12))
+                        .on_press(Message::DismissDownload)
+                        .padding(5),
+                ]
+                .spacing(10)
+                .align_items(alignment::Alignment::Center)
+            );
+        }
+
+        if !self.downloads.is_empty() {
+            let mut downloads = row![text("Downloads:").size(12)].spacing(10);
+            for path in self.downloads.iter().rev().take(5) {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                downloads = downloads.push(
+                    text(format!("⬇ {}", name))
+                        .size(12)
+                        .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6)))
+                );
+            }
+            main_content = main_content.push(downloads);
+        }
+
+        if self.show_links {
+            main_content = main_content.push(self.create_links_panel());
+        }
+
+        let main_content = main_content.push(content);
+
         container(main_content)
             .width(Length::Fill)
             .height(Length::Fill)
@@ -173,9 +1131,19 @@ impl Browser {
                 }
             });
 
+        let save_html_btn = button(text("💾 HTML").size(14))
+            .on_press_maybe(self.tab().html.is_some().then_some(Message::SavePage(SaveFormat::Html)))
+            .padding(5);
+
+        let save_text_btn = button(text("💾 Text").size(14))
+            .on_press(Message::SavePage(SaveFormat::Text))
+            .padding(5);
+
         row![
             text("🌐 Browser").size(18),
             row![].width(Length::Fill),
+            save_html_btn,
+            save_text_btn,
             close_btn,
         ]
         .spacing(10)
@@ -183,13 +1151,60 @@ impl Browser {
         .into()
     }
 
+    fn create_tab_strip(&self) -> Element<Message> {
+        let mut strip = row![].spacing(4).align_items(alignment::Alignment::Center);
+
+        for (index, tab) in self.tabs.iter().enumerate() {
+            let is_active = index == self.active_tab;
+            let label = if tab.loading {
+                format!("⏳ {}", tab.title())
+            } else {
+                tab.title()
+            };
+
+            let switch_btn = button(text(label).size(12))
+                .on_press(Message::SwitchTab(index))
+                .padding([5, 8])
+                .style(move |theme: &iced::Theme, status| {
+                    let palette = theme.extended_palette();
+                    button::Appearance {
+                        background: Some(iced::Background::Color(
+                            if is_active {
+                                iced::Color { a: 0.3, ..theme.palette().primary }
+                            } else if matches!(status, button::Status::Hovered) {
+                                palette.background.weak.color
+                            } else {
+                                iced::Color::TRANSPARENT
+                            }
+                        )),
+                        border: iced::Border::with_radius(4),
+                        text_color: palette.background.base.text,
+                        ..Default::default()
+                    }
+                });
+
+            let close_btn = button(text("✕").size(10))
+                .on_press(Message::CloseTab(index))
+                .padding(5);
+
+            strip = strip.push(row![switch_btn, close_btn].spacing(1));
+        }
+
+        let new_tab_btn = button(text("+").size(14))
+            .on_press(Message::NewTab)
+            .padding([3, 8]);
+
+        strip.push(new_tab_btn).into()
+    }
+
     fn create_navigation(&self) -> Element<Message> {
+        let tab = self.tab();
+        let can_go_back = tab.can_go_back();
         let back_btn = button(text("◀").size(16))
-            .on_press(Message::GoBack)
+            .on_press_maybe(can_go_back.then_some(Message::GoBack))
             .padding(8)
-            .style(|theme: &iced::Theme, status| {
+            .style(move |theme: &iced::Theme, status| {
                 let palette = theme.extended_palette();
-                let can_go_back = true; // You might want to check history here
                 button::Appearance {
                     background: Some(iced::Background::Color(
                         if matches!(status, button::Status::Hovered) && can_go_back {
@@ -208,12 +1223,12 @@ impl Browser {
                 }
             });
 
+        let can_go_forward = tab.can_go_forward();
         let forward_btn = button(text("▶").size(16))
-            .on_press(Message::GoForward)
+            .on_press_maybe(can_go_forward.then_some(Message::GoForward))
             .padding(8)
-            .style(|theme: &iced::Theme, status| {
+            .style(move |theme: &iced::Theme, status| {
                 let palette = theme.extended_palette();
-                let can_go_forward = true; // You might want to check history here
                 button::Appearance {
                     background: Some(iced::Background::Color(
                         if matches!(status, button::Status::Hovered) && can_go_forward {
@@ -236,26 +1251,120 @@ impl Browser {
             .on_press(Message::Refresh)
             .padding(8);
 
+        let home_btn = button(text("🏠").size(16))
+            .on_press(Message::Navigate(self.config.applications.browser.homepage.clone()))
+            .padding(8);
+
+        let bookmarked = self
+            .config
+            .applications
+            .browser
+            .bookmarks
+            .iter()
+            .any(|bookmark| bookmark.url == tab.current_url);
+        let bookmark_btn = button(text(if bookmarked { "★" } else { "☆" }).size(16))
+            .on_press_maybe((!tab.is_start_page()).then_some(Message::ToggleBookmark))
+            .padding(8);
+
+        let zoom_out_btn = button(text("−").size(16))
+            .on_press(Message::ZoomOut)
+            .padding(8);
+
+        let zoom_reset_btn = button(text(format!("{:.0}%", self.zoom * 100.0)).size(12))
+            .on_press(Message::ZoomReset)
+            .padding(8);
+
+        let zoom_in_btn = button(text("+").size(16))
+            .on_press(Message::ZoomIn)
+            .padding(8);
+
+        let reader_active = tab.reader_mode;
+        let reader_btn = button(text("📖").size(16))
+            .on_press(Message::ToggleReaderMode)
+            .padding(8)
+            .style(move |theme: &iced::Theme, status| {
+                let palette = theme.extended_palette();
+                button::Appearance {
+                    background: Some(iced::Background::Color(
+                        if reader_active {
+                            iced::Color { a: 0.3, ..theme.palette().primary }
+                        } else if matches!(status, button::Status::Hovered) {
+                            palette.background.weak.color
+                        } else {
+                            iced::Color::TRANSPARENT
+                        }
+                    )),
+                    border: iced::Border::with_radius(4),
+                    text_color: palette.background.base.text,
+                    ..Default::default()
+                }
+            });
+
+        let monospace_active = self.config.applications.browser.monospace;
+        let monospace_btn = button(text("⌨").size(16))
+            .on_press(Message::ToggleMonospace)
+            .padding(8)
+            .style(move |theme: &iced::Theme, status| {
+                let palette = theme.extended_palette();
+                button::Appearance {
+                    background: Some(iced::Background::Color(
+                        if monospace_active {
+                            iced::Color { a: 0.3, ..theme.palette().primary }
+                        } else if matches!(status, button::Status::Hovered) {
+                            palette.background.weak.color
+                        } else {
+                            iced::Color::TRANSPARENT
+                        }
+                    )),
+                    border: iced::Border::with_radius(4),
+                    text_color: palette.background.base.text,
+                    ..Default::default()
+                }
+            });
+
+        let links_active = self.show_links;
+        let links_btn = button(text("🔗").size(16))
+            .on_press(Message::ToggleLinks)
+            .padding(8)
+            .style(move |theme: &iced::Theme, status| {
+                let palette = theme.extended_palette();
+                button::Appearance {
+                    background: Some(iced::Background::Color(
+                        if links_active {
+                            iced::Color { a: 0.3, ..theme.palette().primary }
+                        } else if matches!(status, button::Status::Hovered) {
+                            palette.background.weak.color
+                        } else {
+                            iced::Color::TRANSPARENT
+                        }
+                    )),
+                    border: iced::Border::with_radius(4),
+                    text_color: palette.background.base.text,
+                    ..Default::default()
+                }
+            });
+
         let url_input = text_input(
             "Enter URL...",
-            &self.url_input,
+            &tab.url_input,
         )
+        .id(url_input_id())
         .on_input(Message::UpdateUrl)
-        .on_submit(Message::Navigate(self.url_input.clone()))
+        .on_submit(Message::Navigate(tab.url_input.clone()))
         .padding(8)
         .size(14)
         .width(Length::Fill);
 
         let go_btn = button(text("Go").size(14))
-            .on_press(Message::Navigate(self.url_input.clone()))
+            .on_press(Message::Navigate(tab.url_input.clone()))
             .padding(8)
             .style(|theme: &iced::Theme, _| {
                 button::Appearance {
                     background: Some(iced::Background::Color(
-                        iced::Color::from_rgb(0.2, 0.4, 0.8)
+                        theme.palette().primary
                     )),
                     border: iced::Border::with_radius(4),
-                    text_color: iced::Color::WHITE,
+                    text_color: theme.extended_palette().primary.base.text,
                     ..Default::default()
                 }
             });
@@ -265,8 +1374,16 @@ impl Browser {
                 back_btn,
                 forward_btn,
                 refresh_btn,
+                home_btn,
+                reader_btn,
+                monospace_btn,
+                links_btn,
                 url_input,
                 go_btn,
+                bookmark_btn,
+                zoom_out_btn,
+                zoom_reset_btn,
+                zoom_in_btn,
             ]
             .spacing(5)
             .align_items(alignment::Alignment::Center)
@@ -283,12 +1400,114 @@ impl Browser {
         .into()
     }
 
+    fn create_links_panel(&self) -> Element<Message> {
+        let links = &self.tab().links;
+        let mut list = column![
+            text(format!("Links on this page ({})", links.len())).size(14),
+        ]
+        .spacing(2);
+
+        for link in links {
+            let label = if link.text.is_empty() { &link.url } else { &link.text };
+            let link_btn = button(
+                column![
+                    text(label).size(13),
+                    text(&link.url)
+                        .size(11)
+                        .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
+                ]
+            )
+            .on_press(Message::Navigate(link.url.clone()))
+            .width(Length::Fill)
+            .padding(5)
+            .style(|theme: &iced::Theme, status| {
+                let palette = theme.extended_palette();
+                button::Appearance {
+                    background: Some(iced::Background::Color(
+                        if matches!(status, button::Status::Hovered) {
+                            palette.background.strong.color
+                        } else {
+                            iced::Color::TRANSPARENT
+                        }
+                    )),
+                    border: iced::Border::with_radius(4),
+                    text_color: palette.background.base.text,
+                    ..Default::default()
+                }
+            });
+            list = list.push(link_btn);
+        }
+
+        container(iced::widget::scrollable(list).width(Length::Fill))
+            .width(Length::Fill)
+            .max_height(LINKS_PANEL_HEIGHT)
+            .padding(10)
+            .style(|theme: &iced::Theme| {
+                let palette = theme.extended_palette();
+                container::Appearance {
+                    background: Some(iced::Background::Color(palette.background.weak.color)),
+                    border: iced::Border::with_radius(4),
+                    ..Default::default()
+                }
+            })
+            .into()
+    }
+
+    /// The page a new tab opens on: a search box, the bookmarks and the
+    /// most recently visited pages.
+    fn create_start_page(&self) -> Element<Message> {
+        let search = text_input("Search or enter address", &self.start_query)
+            .id(start_search_id())
+            .on_input(Message::UpdateStartQuery)
+            .on_submit(Message::Navigate(self.start_query.clone()))
+            .padding(14)
+            .size(20)
+            .width(560);
+
+        let mut page = column![search].spacing(24).align_items(alignment::Alignment::Center);
+
+        let bookmarks = &self.config.applications.browser.bookmarks;
+        if !bookmarks.is_empty() {
+            let entries = bookmarks.iter().map(|bookmark| (&bookmark.title, &bookmark.url));
+            page = page.push(start_page_section("Bookmarks", entries));
+        }
+        if !self.recent.is_empty() {
+            let entries = self.recent.iter().map(|recent| (&recent.title, &recent.url));
+            page = page.push(start_page_section("Recently visited", entries));
+        }
+        if bookmarks.is_empty() && self.recent.is_empty() {
+            page = page.push(
+                text("Pages you bookmark with ☆ and visit will be listed here.")
+                    .size(13)
+                    .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
+            );
+        }
+
+        container(iced::widget::scrollable(
+            container(page).width(Length::Fill).center_x().padding(40),
+        ))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
     fn create_content(&self) -> Element<Message> {
-        if self.loading {
+        let tab = self.tab();
+
+        if tab.loading {
             return container(
                 column![
-                    text("Loading...").size(16),
-                    text(&self.current_url)
+                    row![
+                        widgets::spinner().size(16),
+                        text(if tab.loading_received > 0 {
+                            format!("Loading... {}", format_file_size(tab.loading_received as u64))
+                        } else {
+                            format!("Loading... {}s", tab.loading_elapsed)
+                        })
+                        .size(16),
+                    ]
+                    .spacing(8),
+                    text(&tab.current_url)
                         .size(12)
                         .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6)))
                 ]
@@ -302,7 +1521,11 @@ impl Browser {
             .into();
         }
 
-        if let Some(error) = &self.error {
+        if tab.is_start_page() {
+            return self.create_start_page();
+        }
+
+        if let Some(error) = &tab.error {
             return container(
                 column![
                     text("Error loading page").size(18)
@@ -325,19 +1548,40 @@ impl Browser {
         }
 
         // Display content in a simple text format (minimal mode)
-        let content_display = if self.config.applications.browser.minimal_mode {
+        let content_display = if tab.reader_mode && !tab.reader_blocks.is_empty() {
+            container(
+                iced::widget::scrollable(
+                    container(render_blocks(
+                        &tab.reader_blocks,
+                        self.zoom,
+                        self.content_font(),
+                        self.images(tab),
+                    ))
+                        .padding(20)
+                        .max_width(800)
+                )
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .id(content_scroll_id())
+                .on_scroll(|viewport| Message::Scrolled(viewport.absolute_offset()))
+            )
+            .center_x()
+        } else if self.config.applications.browser.minimal_mode {
             // In minimal mode, show plain text version
             container(
                 iced::widget::scrollable(
-                    container(
-                        text(&self.content)
-                            .size(14)
-                            .style(theme::Text::Color(iced::Color::from_rgb(0.9, 0.9, 0.9)))
-                    )
-                    .padding(20)
+                    container(render_blocks(
+                        &tab.content,
+                        self.zoom,
+                        self.content_font(),
+                        self.images(tab),
+                    ))
+                        .padding(20)
                 )
                 .width(Length::Fill)
                 .height(Length::Fill)
+                .id(content_scroll_id())
+                .on_scroll(|viewport| Message::Scrolled(viewport.absolute_offset()))
             )
         } else {
             // Normal mode would show rendered HTML (not implemented in minimal version)
@@ -369,72 +1613,460 @@ impl Browser {
     }
 }
 
-async fn fetch_page(url: String) -> Result<String, String> {
-    // Create a client with minimal settings for Alpine compatibility
-    let client = reqwest::Client::builder()
-        .user_agent("MinDesk/1.0")
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+/// Compiles the `text_filters` setting, with plain text matched literally.
+/// Invalid regular expressions are logged and left out.
+fn compile_text_filters(filters: &[TextFilter]) -> Vec<(String, Regex)> {
+    filters
+        .iter()
+        .filter(|filter| !filter.remove.is_empty())
+        .filter_map(|filter| {
+            let pattern = if filter.regex {
+                filter.remove.clone()
+            } else {
+                regex::escape(&filter.remove)
+            };
+            match Regex::new(&pattern) {
+                Ok(pattern) => Some((filter.host.trim().to_lowercase(), pattern)),
+                Err(e) => {
+                    log::warn!("Skipping text filter {:?} for {}: {}", filter.remove, filter.host, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
 
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch page: {}", e))?;
+/// Turns what was typed in the URL bar into a URL. `!bang query` searches
+/// with that bang's template and anything else that doesn't look like a URL
+/// goes to the search engine, bang and all if the bang is unknown.
+fn resolve_input(input: &str, browser: &BrowserConfig) -> String {
+    let input = input.trim();
+
+    let bang = input
+        .strip_prefix('!')
+        .and_then(|rest| rest.split_once(char::is_whitespace))
+        .and_then(|(bang, query)| Some((browser.bangs.get(bang)?, query.trim())));
+    if let Some((template, query)) = bang {
+        return fill_search_template(template, query);
+    }
 
-    if !response.status().is_success() {
-        return Err(format!("HTTP {}: {}",
-            response.status().as_u16(),
-            response.status().canonical_reason().unwrap_or("Unknown error")
-        ));
+    if looks_like_url(input) {
+        normalize_url(input)
+    } else {
+        fill_search_template(&browser.search_engine, input)
     }
+}
 
-    let html = response
-        .text()
+fn looks_like_url(input: &str) -> bool {
+    input.contains("://")
+        || input.starts_with('/')
+        || (!input.contains(char::is_whitespace)
+            && (input.contains('.') || input.contains(':') || input.starts_with("localhost")))
+}
+
+/// Puts the percent-encoded `query` in place of `{}` in `template`.
+fn fill_search_template(template: &str, query: &str) -> String {
+    let encoded: String = query
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect();
+    template.replace("{}", &encoded)
+}
+
+/// Turns what was typed in the URL bar into a URL: absolute paths become
+/// `file://` URLs and bare hosts get `https://`.
+fn normalize_url(input: &str) -> String {
+    if ["http://", "https://", "file://"].iter().any(|scheme| input.starts_with(scheme)) {
+        input.to_string()
+    } else if input.starts_with('/') {
+        reqwest::Url::from_file_path(input)
+            .map(|url| url.to_string())
+            .unwrap_or_else(|_| format!("file://{}", input))
+    } else {
+        format!("https://{}", input)
+    }
+}
+
+/// One-line description of a response, e.g. `200 OK · text/html · 48 KB`,
+/// with the final URL appended when the request was redirected.
+fn response_summary(info: &ResponseInfo, requested_url: &str) -> String {
+    let status = match reqwest::StatusCode::from_u16(info.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+    {
+        Some(reason) => format!("{} {}", info.status, reason),
+        None => info.status.to_string(),
+    };
+    let content_type = info.content_type.split(';').next().unwrap_or("").trim();
+
+    let mut parts = vec![status];
+    if !content_type.is_empty() {
+        parts.push(content_type.to_string());
+    }
+    parts.push(format_file_size(info.size as u64));
+    // Compare normalized, so "example.com" and "example.com/" aren't a redirect
+    let redirected = reqwest::Url::parse(requested_url)
+        .map_or(true, |requested| requested.as_str() != info.final_url);
+    if redirected {
+        parts.push(format!("→ {}", info.final_url));
+    }
+    parts.join(" · ")
+}
+
+/// The last part of an offered file's URL, which is roughly what it will
+/// be saved as.
+fn offer_file_name(offer: &DownloadOffer) -> String {
+    reqwest::Url::parse(&offer.url)
+        .ok()
+        .and_then(|url| Some(url.path_segments()?.next_back()?.to_string()))
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| offer.url.clone())
+}
+
+/// "Download debian.iso (application/x-iso9660-image, 2.0 GB)?"
+fn offer_summary(offer: &DownloadOffer) -> String {
+    let content_type = offer.content_type.split(';').next().unwrap_or("").trim();
+    let mut details = Vec::new();
+    if !content_type.is_empty() {
+        details.push(content_type.to_string());
+    }
+    if let Some(size) = offer.size {
+        details.push(format_file_size(size));
+    }
+
+    let name = offer_file_name(offer);
+    if details.is_empty() {
+        format!("Download {}?", name)
+    } else {
+        format!("Download {} ({})?", name, details.join(", "))
+    }
+}
+
+/// Renders blocks back to plain text for saving.
+fn blocks_to_text(blocks: &[ContentBlock]) -> String {
+    let mut output = String::new();
+    for block in blocks {
+        match block {
+            ContentBlock::Heading(_, heading) => output.push_str(&format!("{}\n\n", heading)),
+            ContentBlock::Paragraph(paragraph) => output.push_str(&format!("{}\n\n", paragraph)),
+            ContentBlock::ListItem(item) => output.push_str(&format!("• {}\n", item)),
+            ContentBlock::Image { alt, .. } if !alt.is_empty() => {
+                output.push_str(&format!("[{}]\n\n", alt))
+            }
+            ContentBlock::Image { .. } => {}
+        }
+    }
+    output.trim_end().to_string() + "\n"
+}
+
+/// Turns a URL into a filesystem-safe file stem.
+fn page_file_name(url: &str) -> String {
+    let stripped = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_end_matches('/');
+    let name: String = stripped
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .take(100)
+        .collect();
+    if name.is_empty() {
+        "page".to_string()
+    } else {
+        name
+    }
+}
+
+async fn save_page(path: PathBuf, contents: String) -> Result<PathBuf, String> {
+    tokio::fs::write(&path, contents)
         .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+const ZOOM_STEP: f32 = 0.1;
+
+/// Characters of a page title shown in the tab strip.
+const TAB_TITLE_LENGTH: usize = 20;
 
-    // Extract text content from HTML (very basic extraction)
-    Ok(extract_text_from_html(&html))
+const LINKS_PANEL_HEIGHT: f32 = 200.0;
+
+/// Pages kept for back/forward, and how long before they are refetched.
+const PAGE_CACHE_SIZE: usize = 20;
+const PAGE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Images fetched per page when inline images are on.
+const MAX_IMAGES_PER_PAGE: usize = 20;
+
+/// Tallest an inline image is drawn before zoom; bigger ones are
+/// scaled down to fit.
+const MAX_IMAGE_HEIGHT: f32 = 400.0;
+
+/// Longest meta refresh delay, in seconds, that is followed automatically,
+/// and how many may be followed in a row before stopping on a page.
+const MAX_META_REFRESH_DELAY: u64 = 5;
+const MAX_META_REDIRECTS: usize = 5;
+
+fn session_file() -> Option<PathBuf> {
+    Config::state_dir().map(|dir| dir.join("browser-session.json"))
+}
+
+fn load_session() -> Result<Option<Session>, String> {
+    let Some(path) = session_file().filter(|path| path.exists()) else {
+        return Ok(None);
+    };
+    let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write_session(session: &Session) -> Result<(), String> {
+    let path = session_file().ok_or("No config directory available")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let contents = serde_json::to_string(session).map_err(|e| e.to_string())?;
+    fs::write(&path, contents).map_err(|e| e.to_string())
+}
+
+/// A titled list of links for the start page.
+fn start_page_section<'a>(
+    title: &'a str,
+    entries: impl Iterator<Item = (&'a String, &'a String)>,
+) -> Element<'a, Message> {
+    let mut list = column![text(title).size(16)].spacing(2).width(560);
+    for (label, url) in entries {
+        let link_btn = button(
+            column![
+                text(label).size(14),
+                text(url)
+                    .size(11)
+                    .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
+            ]
+        )
+        .on_press(Message::Navigate(url.clone()))
+        .width(Length::Fill)
+        .padding(6)
+        .style(|theme: &iced::Theme, status| {
+            let palette = theme.extended_palette();
+            button::Appearance {
+                background: Some(iced::Background::Color(
+                    if matches!(status, button::Status::Hovered) {
+                        palette.background.weak.color
+                    } else {
+                        iced::Color::TRANSPARENT
+                    }
+                )),
+                border: iced::Border::with_radius(4),
+                text_color: palette.background.base.text,
+                ..Default::default()
+            }
+        });
+        list = list.push(link_btn);
+    }
+    list.into()
 }
 
-fn extract_text_from_html(html: &str) -> String {
-    use html5ever::parse_document;
-    use html5ever::tendril::TendrilSink;
-    use markup5ever_rcdom::{Handle, NodeData, RcDom};
+fn recent_pages_file() -> Option<PathBuf> {
+    Config::state_dir().map(|dir| dir.join("browser-history.json"))
+}
 
-    let dom = parse_document(RcDom::default(), Default::default())
-        .from_utf8()
-        .read_from(&mut html.as_bytes())
-        .unwrap();
+fn load_recent_pages() -> Result<Vec<RecentPage>, String> {
+    let Some(path) = recent_pages_file().filter(|path| path.exists()) else {
+        return Ok(Vec::new());
+    };
+    let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write_recent_pages(pages: &[RecentPage]) -> Result<(), String> {
+    let path = recent_pages_file().ok_or("No config directory available")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let contents = serde_json::to_string(pages).map_err(|e| e.to_string())?;
+    fs::write(&path, contents).map_err(|e| e.to_string())
+}
+
+fn url_input_id() -> text_input::Id {
+    text_input::Id::new("url-input")
+}
+
+fn start_search_id() -> text_input::Id {
+    text_input::Id::new("start-search")
+}
 
-    let mut text = String::new();
-    extract_text_from_node(&dom.document, &mut text);
-    text.trim().to_string()
+fn content_scroll_id() -> scrollable::Id {
+    scrollable::Id::new("page-content")
 }
 
-fn extract_text_from_node(handle: &Handle, output: &mut String) {
-    match handle.data {
-        NodeData::Text { ref contents } => {
-            let text = contents.borrow();
-            if !text.trim().is_empty() {
-                output.push_str(&text);
-                output.push('\n');
+/// Words longer than this get break opportunities so they wrap instead of
+/// running off the edge.
+const MAX_UNBROKEN_WORD: usize = 40;
+
+/// Inserts zero-width spaces into words longer than `MAX_UNBROKEN_WORD`,
+/// since text only wraps at word boundaries. Whitespace, including
+/// indentation, is left as it is.
+fn break_long_words(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut run = 0;
+    for ch in text.chars() {
+        if ch.is_whitespace() {
+            run = 0;
+        } else {
+            if run == MAX_UNBROKEN_WORD {
+                output.push('\u{200B}');
+                run = 0;
             }
+            run += 1;
         }
-        NodeData::Element { ref name, .. } => {
-            // Skip script and style elements
-            if name.local.as_ref() != "script" && name.local.as_ref() != "style" {
-                for child in handle.children.borrow().iter() {
-                    extract_text_from_node(child, output);
+        output.push(ch);
+    }
+    output
+}
+
+/// Lays out extracted blocks, sizing headings up and bulleting list items.
+/// Images are drawn from `images` when given, falling back to their alt
+/// text, and left out entirely when `images` is `None`.
+fn render_blocks<'a>(
+    blocks: &'a [ContentBlock],
+    zoom: f32,
+    font: Font,
+    images: Option<&'a HashMap<String, ImageState>>,
+) -> iced::widget::Column<'a, Message> {
+    let body_size = 14.0 * zoom;
+    let caption = |caption: String| -> Element<'a, Message> {
+        text(caption)
+            .size(12.0 * zoom)
+            .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6)))
+            .into()
+    };
+
+    let mut column = column![].spacing(10.0 * zoom);
+    for block in blocks {
+        let element: Element<'a, Message> = match block {
+            ContentBlock::Heading(level, heading) => text(break_long_words(heading))
+                .font(font)
+                .size(match level {
+                    1 => 24.0,
+                    2 => 20.0,
+                    3 => 17.0,
+                    _ => 15.0,
+                } * zoom)
+                .style(theme::Text::Color(iced::Color::WHITE))
+                .into(),
+            ContentBlock::Paragraph(paragraph) => text(break_long_words(paragraph))
+                .font(font)
+                .size(body_size)
+                .style(theme::Text::Color(iced::Color::from_rgb(0.9, 0.9, 0.9)))
+                .into(),
+            ContentBlock::ListItem(item) => text(format!("  • {}", break_long_words(item)))
+                .font(font)
+                .size(body_size)
+                .style(theme::Text::Color(iced::Color::from_rgb(0.9, 0.9, 0.9)))
+                .into(),
+            ContentBlock::Image { src, alt } => {
+                let Some(images) = images else {
+                    continue;
+                };
+                match images.get(src) {
+                    Some(ImageState::Loaded(handle)) => container(
+                        image(handle.clone()).content_fit(ContentFit::ScaleDown),
+                    )
+                    .max_height(MAX_IMAGE_HEIGHT * zoom)
+                    .into(),
+                    Some(ImageState::Loading) => caption(format!("🖼 {}", alt)),
+                    _ if alt.is_empty() => continue,
+                    _ => caption(format!("[{}]", alt)),
                 }
             }
-        }
-        _ => {
-            for child in handle.children.borrow().iter() {
-                extract_text_from_node(child, output);
+        };
+        column = column.push(element);
+    }
+    column
+}
+
+/// Rewrites image sources to absolute URLs. Images that can't be fetched,
+/// such as `data:` URIs, are replaced by their alt text.
+fn resolve_image_sources(blocks: &mut Vec<ContentBlock>, base_url: &str) {
+    let base = reqwest::Url::parse(base_url).ok();
+    for block in blocks.iter_mut() {
+        let ContentBlock::Image { src, alt } = block else {
+            continue;
+        };
+        let url = base
+            .as_ref()
+            .and_then(|base| base.join(src).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"));
+
+        match url {
+            Some(url) => *src = url.to_string(),
+            None => {
+                let alt = std::mem::take(alt);
+                *block = ContentBlock::Paragraph(alt);
             }
         }
     }
+    blocks.retain(|block| !matches!(block, ContentBlock::Paragraph(paragraph) if paragraph.is_empty()));
+}
+
+/// Proxies from the config, or the environment where it sets none.
+fn proxies(browser: &BrowserConfig) -> Proxies {
+    Proxies {
+        http: browser.http_proxy.clone(),
+        https: browser.https_proxy.clone(),
+    }
+    .or_from_env()
+}
+
+fn load_image(tab_id: u64, url: String, client: Result<Arc<HttpClient>, String>) -> Command<Message> {
+    let fetch = {
+        let url = url.clone();
+        async move { fetch_image(url, client?).await }
+    };
+    Command::perform(fetch, move |result| {
+        Message::ImageLoaded(tab_id, url, result.map(image::Handle::from_memory))
+    })
+}
+
+/// How much more of a page must arrive before progress is reported again.
+const PROGRESS_STEP: usize = 16 * 1024;
+
+/// Fetches `url` on behalf of the tab with the given id, reporting the
+/// bytes received as `LoadProgress` along the way.
+fn load(id: LoadId, url: String, client: Result<Arc<HttpClient>, String>) -> Command<Message> {
+    let (sender, receiver) = mpsc::unbounded();
+    let progress = receiver.map(move |received| Message::LoadProgress(id, received));
+
+    let fetch = stream::once(async move {
+        let mut reported = 0;
+        let result = match client {
+            Ok(client) => {
+                fetch_page_with_progress(url, client, |received| {
+                    if received - reported >= PROGRESS_STEP {
+                        reported = received;
+                        let _ = sender.unbounded_send(received);
+                    }
+                })
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(Fetched::Page(page)) => Message::LoadComplete(id, Ok(page)),
+            Ok(Fetched::Offer(offer)) => Message::DownloadOffered(id, offer),
+            Err(e) => Message::LoadComplete(id, Err(e)),
+        }
+    });
+
+    Command::run(stream::select(progress, fetch), |message| message)
 }
@@ -1,6 +1,13 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Explicit config file chosen at startup. Once set, it replaces the
+/// per-user file for both loading and saving, so each profile keeps its
+/// own settings.
+static EXPLICIT_PATH: OnceLock<PathBuf> = OnceLock::new();
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
@@ -16,6 +23,17 @@ pub struct DesktopConfig {
     pub font_name: String,
     pub font_size: u16,
     pub theme: String,
+    /// Hex accent color (`#rgb` or `#rrggbb`); empty uses the theme default.
+    #[serde(default)]
+    pub accent_color: String,
+    /// Row spacing of the file and package lists: `"compact"`,
+    /// `"comfortable"` or `"spacious"`.
+    #[serde(default = "default_density")]
+    pub density: String,
+}
+
+fn default_density() -> String {
+    "comfortable".to_string()
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -23,6 +41,17 @@ pub struct ApplicationsConfig {
     pub file_manager: AppConfig,
     pub package_manager: PackageManagerConfig,
     pub browser: BrowserConfig,
+    /// Other programs shown on the desktop next to the built-in apps.
+    #[serde(default)]
+    pub external: Vec<ExternalApp>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExternalApp {
+    pub name: String,
+    pub icon: String,
+    /// Command line to run, passed to `sh -c`.
+    pub exec: String,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -30,6 +59,86 @@ pub struct AppConfig {
     pub enabled: bool,
     pub icon: String,
     pub default_path: String,
+    /// Start in the last visited directory instead of `default_path`.
+    #[serde(default)]
+    pub restore_last_path: bool,
+    /// Folders pinned to the sidebar.
+    #[serde(default)]
+    pub favorites: Vec<PathBuf>,
+    /// Column the listing is sorted by at startup: `"name"`, `"size"` or
+    /// `"modified"`.
+    #[serde(default = "default_sort")]
+    pub default_sort: String,
+    /// `"asc"` or `"desc"`.
+    #[serde(default = "default_sort_order")]
+    pub default_sort_order: String,
+    /// Programs offered by "Open With…".
+    #[serde(default = "default_open_with")]
+    pub open_with: Vec<OpenWithApp>,
+    /// Toolbar buttons, in order. See `TOOLBAR_ACTIONS` for the names.
+    #[serde(default = "default_toolbar")]
+    pub toolbar: Vec<String>,
+}
+
+/// Names accepted in the file manager's `toolbar` setting.
+pub const TOOLBAR_ACTIONS: &[&str] = &[
+    "up",
+    "refresh",
+    "new_folder",
+    "new_file",
+    "pin",
+    "copy",
+    "cut",
+    "paste",
+    "delete",
+    "dual_pane",
+    "copy_to",
+    "move_to",
+    "copy_to_other",
+    "move_to_other",
+    "copy_path",
+    "copy_name",
+    "batch_rename",
+    "edit",
+    "open_with",
+    "calculate_size",
+    "extract",
+    "terminal",
+];
+
+/// Every toolbar button except the terminal, which has to be asked for.
+fn default_toolbar() -> Vec<String> {
+    TOOLBAR_ACTIONS
+        .iter()
+        .filter(|action| **action != "terminal")
+        .map(|action| action.to_string())
+        .collect()
+}
+
+/// A program a file can be opened with.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OpenWithApp {
+    pub name: String,
+    /// Command line run through `sh`, with the file's path appended.
+    pub command: String,
+}
+
+fn default_open_with() -> Vec<OpenWithApp> {
+    [("Text editor", "xterm -e vi"), ("Image viewer", "feh")]
+        .into_iter()
+        .map(|(name, command)| OpenWithApp {
+            name: name.to_string(),
+            command: command.to_string(),
+        })
+        .collect()
+}
+
+fn default_sort() -> String {
+    "name".to_string()
+}
+
+fn default_sort_order() -> String {
+    "asc".to_string()
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -37,6 +146,18 @@ pub struct PackageManagerConfig {
     pub enabled: bool,
     pub icon: String,
     pub backend: String,
+    /// `"sudo"` or `"pkexec"`; pkexec shows a polkit dialog instead of
+    /// requiring passwordless sudo.
+    #[serde(default = "default_privilege_helper")]
+    pub privilege_helper: String,
+    /// Show a desktop notification when an install, removal or cache
+    /// update finishes.
+    #[serde(default)]
+    pub notifications: bool,
+}
+
+fn default_privilege_helper() -> String {
+    "sudo".to_string()
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -45,17 +166,132 @@ pub struct BrowserConfig {
     pub icon: String,
     pub homepage: String,
     pub minimal_mode: bool,
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+    /// Reopen the tabs and history from the previous run.
+    #[serde(default)]
+    pub restore_session: bool,
+    /// Fetch and show `<img>` images inline. Off by default to save
+    /// bandwidth; alt text is shown instead.
+    #[serde(default)]
+    pub load_images: bool,
+    /// Show page text in a monospace font, which suits code and other
+    /// preformatted pages.
+    #[serde(default)]
+    pub monospace: bool,
+    /// Where text that isn't a URL is searched, with `{}` replaced by the
+    /// query.
+    #[serde(default = "default_search_engine")]
+    pub search_engine: String,
+    /// Search URL templates by prefix, so `!w rust` searches with the `w`
+    /// template. Setting this replaces the defaults.
+    #[serde(default = "default_bangs")]
+    pub bangs: BTreeMap<String, String>,
+    /// Proxy for `http://` pages, which may include `user:pass@`. Falls
+    /// back to the `http_proxy` environment variable when unset.
+    #[serde(default)]
+    pub http_proxy: Option<String>,
+    /// Proxy for `https://` pages. Falls back to `https_proxy`.
+    #[serde(default)]
+    pub https_proxy: Option<String>,
+    /// Text removed from pages on particular sites.
+    #[serde(default)]
+    pub text_filters: Vec<TextFilter>,
+    /// Pages listed on the start page, added with the ☆ button.
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Bookmark {
+    pub title: String,
+    pub url: String,
+}
+
+/// Text to cut out of the pages of one site, such as a cookie banner.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TextFilter {
+    /// Host the rule applies to, subdomains included.
+    pub host: String,
+    /// Text to remove, or a regular expression if `regex` is set.
+    pub remove: String,
+    #[serde(default)]
+    pub regex: bool,
+}
+
+fn default_search_engine() -> String {
+    "https://html.duckduckgo.com/html/?q={}".to_string()
+}
+
+fn default_bangs() -> BTreeMap<String, String> {
+    [
+        ("w", "https://en.wikipedia.org/w/index.php?search={}"),
+        ("gh", "https://github.com/search?q={}"),
+        ("crates", "https://crates.io/search?q={}"),
+    ]
+    .into_iter()
+    .map(|(bang, template)| (bang.to_string(), template.to_string()))
+    .collect()
+}
+
+fn default_zoom() -> f32 {
+    1.0
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SystemConfig {
+    /// Display DPI; the UI is scaled by `dpi / 96`.
     pub dpi: u32,
     pub vsync: bool,
+    /// Whether a compositor is running; the window is only made
+    /// transparent when it is.
     pub compositor: bool,
+    /// Reopen the window at the size and position it last had, rather
+    /// than the default size.
+    #[serde(default = "default_remember_window")]
+    pub remember_window: bool,
+}
+
+fn default_remember_window() -> bool {
+    true
 }
 
 impl Config {
-    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+    /// Directory holding the user config and any saved app state.
+    pub fn state_dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("min-desk"))
+    }
+
+    /// Per-user config file, which is also where `save` writes. An explicit
+    /// path passed to `load` takes its place.
+    pub fn user_config_path() -> Option<PathBuf> {
+        if let Some(path) = EXPLICIT_PATH.get() {
+            return Some(path.clone());
+        }
+        Self::state_dir().map(|dir| dir.join("config.json"))
+    }
+
+    /// Loads the config from `path` if given, failing if it can't be read.
+    /// Otherwise searches the usual locations, falling back to the default.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(path) = path {
+            let contents = fs::read_to_string(path)
+                .map_err(|e| format!("Cannot read config {}: {}", path.display(), e))?;
+            let config: Config = serde_json::from_str(&contents)
+                .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+            let _ = EXPLICIT_PATH.set(path.to_path_buf());
+            return Ok(config);
+        }
+
+        // Settings saved from within the app take priority
+        if let Some(user_path) = Self::user_config_path() {
+            if user_path.exists() {
+                let contents = fs::read_to_string(&user_path)?;
+                let config: Config = serde_json::from_str(&contents)?;
+                return Ok(config);
+            }
+        }
+
         let config_path = "/etc/min-desk/config.json";
 
         // Try system config first
@@ -75,6 +311,49 @@ impl Config {
         // Return default config
         Ok(Config::default())
     }
+
+    /// Whether any of the locations `load` searches holds a config file.
+    pub fn exists() -> bool {
+        Self::user_config_path().map_or(false, |path| path.exists())
+            || Path::new("/etc/min-desk/config.json").exists()
+            || Path::new("config.json").exists()
+    }
+
+    /// Writes the default config to the per-user file unless one is already
+    /// there. Returns the file's path and whether it was created.
+    pub fn init_user_config() -> Result<(PathBuf, bool), Box<dyn std::error::Error>> {
+        let path = Self::state_dir()
+            .ok_or("No config directory available")?
+            .join("config.json");
+        if path.exists() {
+            return Ok((path, false));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(&Config::default())?)?;
+        Ok((path, true))
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::user_config_path().ok_or("No config directory available")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Applies `edit` to the config currently on disk and saves it. Each app
+    /// holds its own copy of the config, so re-loading first keeps one app
+    /// from clobbering settings another app saved. If the file on disk can't
+    /// be read or parsed nothing is written, so a bad edit by hand isn't
+    /// replaced with the defaults.
+    pub fn persist(edit: impl FnOnce(&mut Config)) -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = Self::load(None)?;
+        edit(&mut config);
+        config.save()
+    }
 }
 
 impl Default for Config {
@@ -85,24 +364,45 @@ impl Default for Config {
                 font_name: "Inter".to_string(),
                 font_size: 12,
                 theme: "dark".to_string(),
+                accent_color: String::new(),
+                density: default_density(),
             },
             applications: ApplicationsConfig {
                 file_manager: AppConfig {
                     enabled: true,
                     icon: "📁".to_string(),
                     default_path: "/home".to_string(),
+                    restore_last_path: false,
+                    favorites: Vec::new(),
+                    default_sort: default_sort(),
+                    default_sort_order: default_sort_order(),
+                    open_with: default_open_with(),
+                    toolbar: default_toolbar(),
                 },
                 package_manager: PackageManagerConfig {
                     enabled: true,
                     icon: "📦".to_string(),
                     backend: "apk".to_string(),
+                    privilege_helper: default_privilege_helper(),
+                    notifications: false,
                 },
                 browser: BrowserConfig {
                     enabled: true,
                     icon: "🌐".to_string(),
                     homepage: "https://start.duckduckgo.com".to_string(),
                     minimal_mode: true,
+                    zoom: default_zoom(),
+                    restore_session: false,
+                    load_images: false,
+                    monospace: false,
+                    search_engine: default_search_engine(),
+                    bangs: default_bangs(),
+                    http_proxy: None,
+                    https_proxy: None,
+                    text_filters: Vec::new(),
+                    bookmarks: Vec::new(),
                 },
+                external: Vec::new(),
             },
             packages_to_install: vec![
                 "firefox-esr".to_string(),
@@ -115,6 +415,7 @@ impl Default for Config {
                 dpi: 96,
                 vsync: true,
                 compositor: false,
+                remember_window: default_remember_window(),
             },
         }
     }
@@ -1,13 +1,24 @@
 use iced::{
-    alignment, theme, widget::{button, column, container, row, scrollable, text, text_input},
-    Command, Element, Length,
+    alignment, clipboard, event, keyboard, subscription, theme,
+    widget::{button, column, container, progress_bar, row, scrollable, text, text_editor, text_input},
+    futures::{channel::mpsc, stream, SinkExt, StreamExt},
+    Command, Element, Event, Length, Subscription,
 };
+use notify::Watcher;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use walkdir::WalkDir;
 
 use crate::config::Config;
+use crate::widgets::Density;
+use min_desk::fs_ops;
+use min_desk::listing::{format_file_size, fuzzy_match, icon_for, read_directory, FileItem};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -20,18 +31,125 @@ pub enum Message {
     CancelCreate,
     SelectItem(usize),
     OpenItem(PathBuf),
+    ModifiersChanged(keyboard::Modifiers),
+    MoveSelection(isize),
+    OpenSelected,
+    DeleteSelected,
+    DeleteSelectedPermanently,
+    ConfirmDelete,
+    CancelDelete,
+    OperationProgress(u64, Progress),
+    /// A background operation finished, with any errors it ran into.
+    OperationFinished(u64, Vec<String>),
+    CancelOperation,
+    RunScript,
+    EditScript,
+    CancelRun,
+    /// Asks which program to open this file with.
+    OpenWith(PathBuf),
+    OpenWithInput(String),
+    OpenWithSubmit,
+    OpenWithCancel,
+    /// Opens the batch rename bar for the selected items.
+    BatchRename,
+    BatchRenameInput(String),
+    BatchRenameConfirm,
+    BatchRenameCancel,
+    /// A program started with this file, or failed to, from this command.
+    OpenedWith(PathBuf, String, Result<(), String>),
+    FilterChanged(String),
+    FocusFilter,
+    OpenGoTo,
+    GoToInput(String),
+    GoToComplete,
+    GoToSubmit,
+    GoToCancel,
+    CopySelected,
+    CutSelected,
+    Paste,
+    FilesDropped(Vec<PathBuf>),
+    ResolveCollision(CollisionChoice),
+    CopyPath(PathBuf),
+    CopyName(String),
+    ClearStatus(String),
+    SortBy(SortKey),
+    PinCurrentFolder,
+    Unpin(PathBuf),
+    ToggleRecent,
+    RevealRecent(PathBuf),
+    ClearRecent,
+    RefreshDrives,
+    DrivesListed(Result<Vec<Drive>, String>),
+    MountDrive(String),
+    DriveMounted(Result<PathBuf, String>),
+    UnmountDrive(Drive),
+    DriveUnmounted(PathBuf, Result<(), String>),
+    Extract(PathBuf),
+    ExtractComplete(Result<PathBuf, String>),
+    CalculateSize(PathBuf),
+    DirSizeComputed(PathBuf, u64),
+    ToggleDualPane,
+    OpenTerminal,
+    SwitchPane,
+    CopyToOtherPane,
+    MoveToOtherPane,
+    CopyTo,
+    MoveTo,
+    PickerOpen(PathBuf),
+    PickerUp,
+    PickerConfirm,
+    PickerCancel,
+    EditFile(PathBuf),
+    EditorAction(text_editor::Action),
+    SaveEdit,
+    CancelEdit,
+    DiscardEdit,
+    KeepEditing,
     RefreshView,
     Close,
 }
 
-#[derive(Debug, Clone)]
-pub struct FileItem {
-    pub name: String,
-    pub path: PathBuf,
-    pub is_dir: bool,
-    pub size: u64,
+/// Largest file the built-in editor will open.
+const MAX_EDIT_BYTES: u64 = 256 * 1024;
+
+/// How often a background operation updates its progress bar.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the Drives section checks for plugged-in media.
+const DRIVE_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Files kept in the sidebar's Recent section.
+const MAX_RECENT_FILES: usize = 10;
+
+/// Tallest the batch rename preview grows before it scrolls.
+const BATCH_RENAME_PREVIEW_HEIGHT: f32 = 160.0;
+
+const SIZE_COLUMN_WIDTH: f32 = 80.0;
+const MODIFIED_COLUMN_WIDTH: f32 = 120.0;
+
+fn list_scrollable_id() -> scrollable::Id {
+    scrollable::Id::new("file-list")
+}
+
+fn filter_input_id() -> text_input::Id {
+    text_input::Id::new("file-filter")
+}
+
+fn go_to_input_id() -> text_input::Id {
+    text_input::Id::new("go-to-path")
+}
+
+fn open_with_input_id() -> text_input::Id {
+    text_input::Id::new("open-with-input")
+}
+
+fn batch_rename_input_id() -> text_input::Id {
+    text_input::Id::new("batch-rename-input")
 }
 
+/// Folders suggested below the go to path input.
+const MAX_GO_TO_SUGGESTIONS: usize = 8;
+
 #[derive(Debug, Clone, PartialEq)]
 enum CreateMode {
     None,
@@ -39,57 +157,449 @@ enum CreateMode {
     Folder,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClipboardOp {
+    Copy,
+    Cut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Name,
+    Size,
+    Modified,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortKey {
+    /// Parses the config's `default_sort`, falling back to name.
+    fn from_config(value: &str) -> Self {
+        match value {
+            "size" => SortKey::Size,
+            "modified" => SortKey::Modified,
+            _ => SortKey::Name,
+        }
+    }
+}
+
+impl SortOrder {
+    /// Parses the config's `default_sort_order`, falling back to ascending.
+    fn from_config(value: &str) -> Self {
+        match value {
+            "desc" => SortOrder::Descending,
+            _ => SortOrder::Ascending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollisionChoice {
+    Overwrite,
+    Rename,
+    Skip,
+}
+
+/// A paste in progress. Items are processed in order until one collides
+/// with an existing file, at which point the paste pauses on `conflict`
+/// until the user picks a `CollisionChoice`.
+struct PendingPaste {
+    remaining: VecDeque<PathBuf>,
+    op: ClipboardOp,
+    destination: PathBuf,
+    errors: Vec<String>,
+    /// Items transferred so far.
+    done: usize,
+    /// Source and destination of the collision awaiting a decision.
+    conflict: Option<(PathBuf, PathBuf)>,
+}
+
+/// How far a background copy, move or delete has got. The totals are
+/// counted by walking the tree before starting.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Progress {
+    items: u64,
+    bytes: u64,
+    total_items: u64,
+    total_bytes: u64,
+}
+
+/// A filesystem on removable media, such as a USB stick, as listed by
+/// `lsblk`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drive {
+    /// Device node, e.g. `/dev/sdb1`.
+    device: String,
+    label: String,
+    size: String,
+    mount_point: Option<PathBuf>,
+}
+
+/// The batch rename bar, with the new names for the pattern typed so far.
+struct BatchRename {
+    pattern: String,
+    paths: Vec<PathBuf>,
+    plan: Vec<fs_ops::PlannedRename>,
+}
+
+impl BatchRename {
+    fn new(paths: Vec<PathBuf>) -> Self {
+        let pattern = "{name}.{ext}".to_string();
+        let plan = fs_ops::plan_renames(&paths, &pattern);
+        Self { pattern, paths, plan }
+    }
+
+    fn set_pattern(&mut self, pattern: String) {
+        self.plan = fs_ops::plan_renames(&self.paths, &pattern);
+        self.pattern = pattern;
+    }
+
+    /// Whether every new name is usable and at least one changes.
+    fn is_ready(&self) -> bool {
+        self.plan.iter().all(|rename| rename.problem.is_none())
+            && self.plan.iter().any(|rename| !rename.is_unchanged())
+    }
+}
+
+/// The Ctrl+G "go to path" bar.
+struct GoTo {
+    input: String,
+    /// Whether `input` can be opened, with a description either way.
+    feedback: Result<String, String>,
+    /// Folders that complete the last component of `input`.
+    suggestions: Vec<String>,
+}
+
+impl GoTo {
+    fn new(input: String) -> Self {
+        let mut go_to = Self {
+            input: String::new(),
+            feedback: Ok(String::new()),
+            suggestions: Vec::new(),
+        };
+        go_to.set_input(input);
+        go_to
+    }
+
+    fn set_input(&mut self, input: String) {
+        let path = expand_home(&input);
+        self.feedback = if !path.is_absolute() {
+            Err("Enter an absolute path".to_string())
+        } else {
+            match fs::metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => Ok("Folder".to_string()),
+                Ok(_) => Ok("File, opens its folder with it selected".to_string()),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Err("Doesn't exist".to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        };
+        self.suggestions = complete_folder(&input);
+        self.input = input;
+    }
+
+    /// Extends the input to the single suggestion, or as far as all the
+    /// suggestions agree.
+    fn complete(&mut self) {
+        let Some(first) = self.suggestions.first() else { return };
+        let common = self.suggestions.iter().skip(1).fold(first.as_str(), |common, suggestion| {
+            let length = common
+                .char_indices()
+                .zip(suggestion.chars())
+                .take_while(|((_, a), b)| a == b)
+                .last()
+                .map_or(0, |((index, ch), _)| index + ch.len_utf8());
+            &common[..length]
+        });
+        if common.len() > self.input.len() {
+            let common = common.to_string();
+            self.set_input(common);
+        }
+    }
+}
+
+/// A copy, move or delete running off the UI thread.
+struct Operation {
+    id: u64,
+    label: String,
+    progress: Progress,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Progress of an operation as seen by its worker thread.
+struct Tracker<'a> {
+    progress: Progress,
+    cancel: &'a AtomicBool,
+    report: &'a mut dyn FnMut(Progress),
+}
+
+impl Tracker<'_> {
+    /// Adds the entries and file bytes under `path` to the totals.
+    fn measure(&mut self, path: &Path) {
+        for entry in WalkDir::new(path).into_iter().filter_map(Result::ok) {
+            self.progress.total_items += 1;
+            if entry.file_type().is_file() {
+                self.progress.total_bytes += entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            }
+        }
+    }
+
+    /// Records one finished entry, failing if the user cancelled so the
+    /// operation stops before the next one.
+    fn advance(&mut self, bytes: u64) -> io::Result<()> {
+        self.progress.items += 1;
+        self.progress.bytes += bytes;
+        (self.report)(self.progress);
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        Ok(())
+    }
+}
+
+/// Folder, listing and selection of the pane that isn't focused in
+/// dual-pane mode. The focused pane lives in `FileManager`'s own fields, and
+/// switching panes swaps the two.
+struct Pane {
+    current_path: PathBuf,
+    items: Vec<FileItem>,
+    selected: BTreeSet<usize>,
+    selection_anchor: Option<usize>,
+    dir_sizes: HashMap<PathBuf, Option<u64>>,
+    filter: String,
+}
+
+/// Destination chooser for "Copy to…" and "Move to…", listing only
+/// folders.
+struct FolderPicker {
+    op: ClipboardOp,
+    /// The selection when the picker was opened.
+    sources: Vec<PathBuf>,
+    current: PathBuf,
+    folders: Vec<FileItem>,
+    error: Option<String>,
+}
+
+impl FolderPicker {
+    fn new(op: ClipboardOp, sources: Vec<PathBuf>) -> Self {
+        let mut picker = Self {
+            op,
+            sources,
+            current: PathBuf::from("/"),
+            folders: Vec::new(),
+            error: None,
+        };
+        picker.open(dirs::home_dir().unwrap_or_else(|| PathBuf::from("/")));
+        picker
+    }
+
+    /// Moves into `path`, staying put if it can't be read.
+    fn open(&mut self, path: PathBuf) {
+        match read_directory(&path) {
+            Ok(items) => {
+                self.folders = items.into_iter().filter(|item| item.is_dir).collect();
+                self.folders.sort_by_key(|item| item.name.to_lowercase());
+                self.current = path;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+}
+
+/// A small text file open in the built-in editor.
+struct Editor {
+    path: PathBuf,
+    content: text_editor::Content,
+    /// The editor always ends its text with a newline, so this records
+    /// whether the file did to write it back unchanged.
+    trailing_newline: bool,
+    dirty: bool,
+    /// Cancel was pressed with unsaved changes.
+    confirm_discard: bool,
+}
+
+impl Editor {
+    fn open(path: PathBuf) -> Result<Self, String> {
+        let bytes = fs::read(&path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+        if bytes.len() as u64 > MAX_EDIT_BYTES {
+            return Err(format!("{} is too large to edit", path.display()));
+        }
+        let text = match String::from_utf8(bytes) {
+            Ok(text) if !text.contains('\0') => text,
+            _ => return Err(format!("{} isn't a text file", path.display())),
+        };
+
+        Ok(Self {
+            path,
+            content: text_editor::Content::with_text(&text),
+            trailing_newline: text.is_empty() || text.ends_with('\n'),
+            dirty: false,
+            confirm_discard: false,
+        })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let mut text = self.content.text();
+        if !self.trailing_newline {
+            text.pop();
+        }
+        fs::write(&self.path, text).map_err(|e| format!("Failed to save {}: {}", self.path.display(), e))
+    }
+}
+
 pub struct FileManager {
     config: Config,
     current_path: PathBuf,
     items: Vec<FileItem>,
-    selected_index: Option<usize>,
+    selected: BTreeSet<usize>,
+    selection_anchor: Option<usize>,
+    modifiers: keyboard::Modifiers,
+    clipboard: Vec<PathBuf>,
+    clipboard_op: ClipboardOp,
+    pending_paste: Option<PendingPaste>,
+    /// Permanent or multi-item delete waiting for confirmation, and whether
+    /// it bypasses the trash.
+    pending_delete: Option<(Vec<PathBuf>, bool)>,
+    /// Script or executable opened from the listing, waiting for the user to
+    /// choose whether to run it.
+    pending_run: Option<PathBuf>,
+    /// File being opened with "Open With…", and the command typed so far.
+    open_with: Option<(PathBuf, String)>,
+    /// Command last used with "Open With…", by lowercased extension.
+    open_with_last: BTreeMap<String, String>,
+    operation: Option<Operation>,
+    next_operation_id: u64,
+    go_to: Option<GoTo>,
+    batch_rename: Option<BatchRename>,
+    /// Fuzzy filter on the listing's names; empty shows everything.
+    filter: String,
+    sort_key: SortKey,
+    sort_order: SortOrder,
+    /// Folder sizes calculated on request; `None` while still walking.
+    dir_sizes: HashMap<PathBuf, Option<u64>>,
+    /// The unfocused pane, present while in dual-pane mode.
+    other_pane: Option<Pane>,
+    folder_picker: Option<FolderPicker>,
+    editor: Option<Editor>,
+    /// Files recently opened, newest first.
+    recent_files: Vec<PathBuf>,
+    show_recent: bool,
+    drives: Vec<Drive>,
+    /// Which side the focused pane is drawn on: 0 for left, 1 for right.
+    active_pane: usize,
     create_mode: CreateMode,
     new_item_name: String,
     error_message: Option<String>,
+    status_message: Option<String>,
 }
 
 impl FileManager {
-    pub fn new(config: Config) -> Self {
+    /// The file manager, and the command listing the drives, which can take
+    /// a while.
+    pub fn new(config: Config) -> (Self, Command<Message>) {
         let default_path = PathBuf::from(&config.applications.file_manager.default_path);
-        let current_path = if default_path.exists() {
+        let last_path = config
+            .applications
+            .file_manager
+            .restore_last_path
+            .then(load_last_path)
+            .flatten()
+            .filter(|path| path.is_dir());
+
+        let current_path = if let Some(last_path) = last_path {
+            last_path
+        } else if default_path.exists() {
             default_path
         } else {
             dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
         };
 
+        let sort_key = SortKey::from_config(&config.applications.file_manager.default_sort);
+        let sort_order = SortOrder::from_config(&config.applications.file_manager.default_sort_order);
+
         let mut manager = Self {
             config,
             current_path: current_path.clone(),
             items: Vec::new(),
-            selected_index: None,
+            selected: BTreeSet::new(),
+            selection_anchor: None,
+            modifiers: keyboard::Modifiers::default(),
+            clipboard: Vec::new(),
+            clipboard_op: ClipboardOp::Copy,
+            pending_paste: None,
+            pending_delete: None,
+            pending_run: None,
+            open_with: None,
+            open_with_last: load_open_with_last(),
+            operation: None,
+            next_operation_id: 0,
+            go_to: None,
+            batch_rename: None,
+            filter: String::new(),
+            sort_key,
+            sort_order,
+            dir_sizes: HashMap::new(),
+            other_pane: None,
+            folder_picker: None,
+            editor: None,
+            recent_files: load_recent_files(),
+            show_recent: true,
+            drives: Vec::new(),
+            active_pane: 0,
             create_mode: CreateMode::None,
             new_item_name: String::new(),
             error_message: None,
+            status_message: None,
         };
 
-        manager.load_directory(current_path.clone());
-        manager
+        if let Err(e) = manager.load_directory(current_path.clone()) {
+            manager.error_message = Some(format!("Error: {}", e));
+        }
+        (manager, Command::perform(list_drives(), Message::DrivesListed))
+    }
+
+    /// Replaces the config after settings are saved.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    pub fn current_path(&self) -> &Path {
+        &self.current_path
+    }
+
+    /// What closing the file manager would throw away, if anything.
+    pub fn unsaved_state(&self) -> Option<&'static str> {
+        if self.editor.as_ref().is_some_and(|editor| editor.dirty) {
+            Some("The file being edited has unsaved changes.")
+        } else if self.create_mode != CreateMode::None && !self.new_item_name.is_empty() {
+            Some("The new item's name hasn't been created yet.")
+        } else {
+            None
+        }
+    }
+
+    /// Drops the state `unsaved_state` warns about.
+    pub fn discard_unsaved(&mut self) {
+        self.editor = None;
+        self.create_mode = CreateMode::None;
+        self.new_item_name.clear();
     }
 
     pub fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::NavigateTo(path) => {
                 if path.is_dir() && path.exists() {
-                    self.current_path = path.clone();
-                    self.load_directory(path);
-                    self.selected_index = None;
-                    self.error_message = None;
+                    self.change_directory(path);
                 }
                 Command::none()
             }
             Message::GoUp => {
                 if let Some(parent) = self.current_path.parent() {
-                    self.current_path = parent.to_path_buf();
-                    let path = self.current_path.clone();
-                    self.load_directory(path);
-                    self.selected_index = None;
-                    self.error_message = None;
+                    self.change_directory(parent.to_path_buf());
                 }
                 Command::none()
             }
@@ -121,11 +631,10 @@ impl FileManager {
 
                     match result {
                         Ok(_) => {
-                            let path = self.current_path.clone();
-                            self.load_directory(path);
                             self.create_mode = CreateMode::None;
                             self.new_item_name.clear();
                             self.error_message = None;
+                            self.reload();
                         }
                         Err(e) => {
                             self.error_message = Some(format!("Error: {}", e));
@@ -141,209 +650,2059 @@ impl FileManager {
                 Command::none()
             }
             Message::SelectItem(index) => {
-                self.selected_index = Some(index);
+                if self.modifiers.shift() {
+                    let anchor = self.selection_anchor.unwrap_or(index);
+                    let (start, end) = if anchor <= index { (anchor, index) } else { (index, anchor) };
+                    if !self.modifiers.command() {
+                        self.selected.clear();
+                    }
+                    self.selected.extend(start..=end);
+                } else if self.modifiers.command() {
+                    if !self.selected.remove(&index) {
+                        self.selected.insert(index);
+                    }
+                    self.selection_anchor = Some(index);
+                } else {
+                    self.selected.clear();
+                    self.selected.insert(index);
+                    self.selection_anchor = Some(index);
+                }
                 Command::none()
             }
             Message::OpenItem(path) => {
                 if path.is_dir() {
-                    self.current_path = path.clone();
-                    self.load_directory(path);
-                    self.selected_index = None;
+                    // Follow symlinked folders to where they actually live
+                    let path = if path.is_symlink() {
+                        fs::canonicalize(&path).unwrap_or(path)
+                    } else {
+                        path
+                    };
+                    self.change_directory(path);
+                } else {
+                    self.open_file(path);
                 }
                 Command::none()
             }
-            Message::RefreshView => {
-                let path = self.current_path.clone();
-                self.load_directory(path);
+            Message::ToggleRecent => {
+                self.show_recent = !self.show_recent;
                 Command::none()
             }
-            Message::Close => {
+            Message::RevealRecent(path) => {
+                if let Err(e) = self.open_path(&path) {
+                    self.error_message = Some(format!("Error: {}", e));
+                }
                 Command::none()
             }
-        }
-    }
-
-    pub fn view(&self) -> Element<Message> {
-        let header = self.create_header();
-        let toolbar = self.create_toolbar();
-        let content = self.create_content();
-        let status_bar = self.create_status_bar();
+            Message::ClearRecent => {
+                self.recent_files.clear();
+                self.save_recent_files();
+                Command::none()
+            }
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+                Command::none()
+            }
+            Message::MoveSelection(delta) => {
+                if self.items.is_empty() {
+                    return Command::none();
+                }
 
-        let main_content = column![
-            header,
-            toolbar,
-            content,
-            status_bar,
-        ]
-        .spacing(5)
-        .padding(10);
+                let last = self.items.len() - 1;
+                let index = match self.selection_anchor {
+                    Some(current) => current.saturating_add_signed(delta).min(last),
+                    None if delta < 0 => last,
+                    None => 0,
+                };
+                self.selected.clear();
+                self.selected.insert(index);
+                self.selection_anchor = Some(index);
 
-        container(main_content)
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .style(|theme: &iced::Theme| {
-                let palette = theme.extended_palette();
-                container::Appearance {
-                    background: Some(iced::Background::Color(palette.background.base.color)),
-                    border: iced::Border::with_radius(0),
-                    ..Default::default()
+                // Keep the selection visible by scrolling proportionally
+                let offset = if last == 0 { 0.0 } else { index as f32 / last as f32 };
+                scrollable::snap_to(
+                    list_scrollable_id(),
+                    scrollable::RelativeOffset { x: 0.0, y: offset },
+                )
+            }
+            Message::OpenSelected => {
+                match self.selected_paths().as_slice() {
+                    [path] => self.update(Message::OpenItem(path.clone())),
+                    _ => Command::none(),
                 }
-            })
-            .into()
-    }
-
-    fn create_header(&self) -> Element<Message> {
-        let close_btn = button(text("✕").size(20))
-            .on_press(Message::Close)
-            .padding(5)
-            .style(|theme: &iced::Theme, status| {
-                let palette = theme.extended_palette();
-                button::Appearance {
-                    background: Some(iced::Background::Color(
-                        if matches!(status, button::Status::Hovered) {
-                            iced::Color::from_rgba8(255, 100, 100, 0.8)
-                        } else {
-                            iced::Color::TRANSPARENT
-                        }
-                    )),
-                    border: iced::Border::with_radius(4),
-                    text_color: palette.background.base.text,
-                    ..Default::default()
+            }
+            Message::DeleteSelected | Message::DeleteSelectedPermanently => {
+                let permanent = matches!(message, Message::DeleteSelectedPermanently);
+                let paths = self.selected_paths();
+                // Only moving a single item to the trash, which can be
+                // undone from there, skips the prompt
+                match paths.len() {
+                    0 => Command::none(),
+                    1 if !permanent => self.delete(paths, permanent),
+                    _ => {
+                        self.pending_delete = Some((paths, permanent));
+                        Command::none()
+                    }
                 }
-            });
-
-        let path_display = text(self.current_path.display().to_string())
-            .size(14)
-            .style(theme::Text::Color(iced::Color::from_rgb(0.7, 0.7, 0.7)));
-
-        row![
-            text("📁 File Manager").size(18),
-            row![].width(Length::Fill),
-            path_display,
-            close_btn,
-        ]
-        .spacing(10)
-        .align_items(alignment::Alignment::Center)
-        .into()
-    }
-
-    fn create_toolbar(&self) -> Element<Message> {
-        let up_btn = button(text("⬆ Up"))
-            .on_press(Message::GoUp)
-            .padding(8);
-
-        let refresh_btn = button(text("🔄 Refresh"))
-            .on_press(Message::RefreshView)
-            .padding(8);
-
-        let new_folder_btn = button(text("📁+ New Folder"))
-            .on_press(Message::CreateFolder)
-            .padding(8);
-
-        let new_file_btn = button(text("📄+ New File"))
-            .on_press(Message::CreateFile)
-            .padding(8);
-
-        let mut toolbar = row![
-            up_btn,
-            refresh_btn,
-            new_folder_btn,
-            new_file_btn,
-        ]
-        .spacing(10);
-
-        // Add create input if in create mode
-        if self.create_mode != CreateMode::None {
-            let placeholder = match self.create_mode {
-                CreateMode::File => "Enter file name...",
-                CreateMode::Folder => "Enter folder name...",
-                CreateMode::None => "",
-            };
-
-            let input = text_input(placeholder, &self.new_item_name)
-                .on_input(Message::UpdateNewItemName)
-                .on_submit(Message::ConfirmCreate)
-                .padding(5)
-                .width(Length::Fixed(200.0));
-
-            let confirm_btn = button(text("✓"))
-                .on_press(Message::ConfirmCreate)
-                .padding(5)
-                .style(|theme: &iced::Theme, _| {
-                    button::Appearance {
-                        background: Some(iced::Background::Color(
-                            iced::Color::from_rgb(0.2, 0.6, 0.2)
-                        )),
-                        border: iced::Border::with_radius(4),
-                        text_color: iced::Color::WHITE,
-                        ..Default::default()
+            }
+            Message::ConfirmDelete => match self.pending_delete.take() {
+                Some((paths, permanent)) => self.delete(paths, permanent),
+                None => Command::none(),
+            },
+            Message::OperationProgress(id, progress) => {
+                if let Some(operation) = self.operation.as_mut().filter(|operation| operation.id == id) {
+                    operation.progress = progress;
+                }
+                Command::none()
+            }
+            Message::OperationFinished(id, errors) => {
+                if self.operation.as_ref().map(|operation| operation.id) != Some(id) {
+                    return Command::none();
+                }
+                let Some(operation) = self.operation.take() else {
+                    return Command::none();
+                };
+                let cancelled = operation.cancel.load(Ordering::Relaxed);
+                match &mut self.pending_paste {
+                    Some(pending) => {
+                        if cancelled {
+                            pending.remaining.clear();
+                        } else if errors.is_empty() {
+                            pending.done += 1;
+                        }
+                        pending.errors.extend(errors);
+                        let command = self.continue_paste();
+                        if cancelled {
+                            self.status_message = Some("Cancelled".to_string());
+                        }
+                        command
                     }
-                });
-
-            let cancel_btn = button(text("✗"))
-                .on_press(Message::CancelCreate)
-                .padding(5)
-                .style(|theme: &iced::Theme, _| {
-                    button::Appearance {
-                        background: Some(iced::Background::Color(
-                            iced::Color::from_rgb(0.6, 0.2, 0.2)
-                        )),
-                        border: iced::Border::with_radius(4),
-                        text_color: iced::Color::WHITE,
-                        ..Default::default()
+                    None => {
+                        self.finish_operation(errors);
+                        if cancelled {
+                            self.status_message = Some("Cancelled".to_string());
+                        }
+                        Command::none()
                     }
-                });
-
-            toolbar = toolbar.push(row![].width(Length::Fixed(20.0)));
-            toolbar = toolbar.push(input);
-            toolbar = toolbar.push(confirm_btn);
-            toolbar = toolbar.push(cancel_btn);
-        }
-
-        container(toolbar)
-            .padding(10)
-            .style(|theme: &iced::Theme| {
-                let palette = theme.extended_palette();
-                container::Appearance {
-                    background: Some(iced::Background::Color(palette.background.weak.color)),
-                    border: iced::Border::with_radius(4),
-                    ..Default::default()
                 }
-            })
+            }
+            Message::CancelOperation => {
+                if let Some(operation) = &self.operation {
+                    operation.cancel.store(true, Ordering::Relaxed);
+                }
+                Command::none()
+            }
+            Message::RunScript => {
+                if let Some(path) = self.pending_run.take() {
+                    self.run_script(path);
+                }
+                Command::none()
+            }
+            Message::EditScript => match self.pending_run.take() {
+                Some(path) => self.update(Message::EditFile(path)),
+                None => Command::none(),
+            },
+            Message::CancelRun => {
+                self.pending_run = None;
+                Command::none()
+            }
+            Message::OpenWith(path) => {
+                let command = self
+                    .open_with_last
+                    .get(&extension_key(&path))
+                    .cloned()
+                    .unwrap_or_default();
+                self.open_with = Some((path, command));
+                text_input::focus(open_with_input_id())
+            }
+            Message::OpenWithInput(input) => {
+                if let Some((_, command)) = &mut self.open_with {
+                    *command = input;
+                }
+                Command::none()
+            }
+            Message::OpenWithSubmit => {
+                let ready = self.open_with.as_ref().is_some_and(|(_, command)| !command.trim().is_empty());
+                let Some((path, command)) = self.open_with.take().filter(|_| ready) else {
+                    return Command::none();
+                };
+                let command = command.trim().to_string();
+                self.status_message = Some(format!("Opening {} with {}…", path.display(), command));
+                let mut process = tokio::process::Command::new("sh");
+                // The path goes in as $1 so the shell never parses it
+                process
+                    .arg("-c")
+                    .arg(format!("{} \"$1\"", command))
+                    .arg("sh")
+                    .arg(&path)
+                    .current_dir(&self.current_path);
+                Command::perform(crate::spawn_checked(process), move |result| {
+                    Message::OpenedWith(path, command, result)
+                })
+            }
+            Message::OpenWithCancel => {
+                self.open_with = None;
+                Command::none()
+            }
+            Message::BatchRename => {
+                let paths = self.selected_paths();
+                if paths.len() < 2 {
+                    return Command::none();
+                }
+                self.batch_rename = Some(BatchRename::new(paths));
+                text_input::focus(batch_rename_input_id())
+            }
+            Message::BatchRenameInput(pattern) => {
+                if let Some(batch_rename) = &mut self.batch_rename {
+                    batch_rename.set_pattern(pattern);
+                }
+                Command::none()
+            }
+            Message::BatchRenameConfirm => {
+                let ready = self.batch_rename.as_ref().is_some_and(BatchRename::is_ready);
+                let Some(batch_rename) = self.batch_rename.take().filter(|_| ready) else {
+                    return Command::none();
+                };
+                match fs_ops::rename_all(&batch_rename.plan) {
+                    Ok(count) => {
+                        self.error_message = None;
+                        self.status_message = Some(format!("Renamed {} items", count));
+                        self.reload();
+                        let renamed: Vec<PathBuf> =
+                            batch_rename.plan.into_iter().map(|rename| rename.to).collect();
+                        self.reselect(&renamed);
+                    }
+                    Err(e) => {
+                        self.error_message = Some(format!("Error: {}", e));
+                        self.reload();
+                    }
+                }
+                Command::none()
+            }
+            Message::BatchRenameCancel => {
+                self.batch_rename = None;
+                Command::none()
+            }
+            Message::OpenedWith(path, command, result) => {
+                self.status_message = None;
+                match result {
+                    Ok(()) => {
+                        self.open_with_last.insert(extension_key(&path), command);
+                        if let Err(e) = save_open_with_last(&self.open_with_last) {
+                            log::warn!("Failed to save Open With choices: {}", e);
+                        }
+                        self.remember_recent(path);
+                    }
+                    Err(e) => {
+                        self.error_message = Some(format!(
+                            "Error: Cannot open {} with {}: {}",
+                            path.display(),
+                            command,
+                            e
+                        ));
+                    }
+                }
+                Command::none()
+            }
+            Message::FilterChanged(filter) => {
+                self.filter = filter;
+                self.clear_selection();
+                self.reload();
+                Command::none()
+            }
+            Message::FocusFilter => text_input::focus(filter_input_id()),
+            Message::OpenGoTo => {
+                let mut input = self.current_path.display().to_string();
+                if !input.ends_with('/') {
+                    input.push('/');
+                }
+                self.go_to = Some(GoTo::new(input));
+                Command::batch(vec![
+                    text_input::focus(go_to_input_id()),
+                    text_input::move_cursor_to_end(go_to_input_id()),
+                ])
+            }
+            Message::GoToInput(input) => {
+                if let Some(go_to) = &mut self.go_to {
+                    go_to.set_input(input);
+                }
+                Command::none()
+            }
+            Message::GoToComplete => {
+                if let Some(go_to) = &mut self.go_to {
+                    go_to.complete();
+                }
+                text_input::move_cursor_to_end(go_to_input_id())
+            }
+            Message::GoToSubmit => {
+                let Some(go_to) = &mut self.go_to else {
+                    return Command::none();
+                };
+                if go_to.feedback.is_err() {
+                    return Command::none();
+                }
+                let path = expand_home(&go_to.input);
+                match self.open_path(&path) {
+                    Ok(()) => self.go_to = None,
+                    Err(e) => {
+                        if let Some(go_to) = &mut self.go_to {
+                            go_to.feedback = Err(e);
+                        }
+                    }
+                }
+                Command::none()
+            }
+            Message::GoToCancel => {
+                self.go_to = None;
+                Command::none()
+            }
+            Message::CancelDelete => {
+                self.pending_delete = None;
+                Command::none()
+            }
+            Message::CopySelected => {
+                self.clipboard = self.selected_paths();
+                self.clipboard_op = ClipboardOp::Copy;
+                Command::none()
+            }
+            Message::CutSelected => {
+                self.clipboard = self.selected_paths();
+                self.clipboard_op = ClipboardOp::Cut;
+                Command::none()
+            }
+            Message::Paste => {
+                if self.is_busy() {
+                    return Command::none();
+                }
+                self.pending_paste = Some(PendingPaste {
+                    remaining: self.clipboard.iter().cloned().collect(),
+                    op: self.clipboard_op,
+                    destination: self.current_path.clone(),
+                    errors: Vec::new(),
+                    done: 0,
+                    conflict: None,
+                });
+                // A cut can only be pasted once
+                if self.clipboard_op == ClipboardOp::Cut {
+                    self.clipboard.clear();
+                }
+                self.continue_paste()
+            }
+            Message::FilesDropped(paths) => {
+                if self.is_busy() {
+                    return Command::none();
+                }
+                self.pending_paste = Some(PendingPaste {
+                    remaining: paths.into(),
+                    op: ClipboardOp::Copy,
+                    destination: self.current_path.clone(),
+                    errors: Vec::new(),
+                    done: 0,
+                    conflict: None,
+                });
+                self.continue_paste()
+            }
+            Message::ResolveCollision(choice) => {
+                let Some((src, dst)) = self.pending_paste.as_mut().and_then(|pending| pending.conflict.take())
+                else {
+                    return Command::none();
+                };
+                match choice {
+                    // Overwriting a file with itself would truncate it
+                    CollisionChoice::Overwrite if src != dst => self.start_transfer(src, dst, true),
+                    CollisionChoice::Rename => {
+                        let dst = fs_ops::unique_name(&dst);
+                        self.start_transfer(src, dst, false)
+                    }
+                    CollisionChoice::Overwrite | CollisionChoice::Skip => self.continue_paste(),
+                }
+            }
+            Message::Extract(archive) => {
+                self.error_message = None;
+                self.status_message = Some(format!("Extracting {}...", archive.display()));
+                Command::perform(extract_archive(archive), Message::ExtractComplete)
+            }
+            Message::ExtractComplete(result) => {
+                match result {
+                    Ok(dest) => {
+                        self.status_message = Some(format!("Extracted to {}", dest.display()));
+                        self.error_message = None;
+                    }
+                    Err(e) => {
+                        self.status_message = None;
+                        self.error_message = Some(format!("Error: {}", e));
+                    }
+                }
+                self.reload();
+                Command::none()
+            }
+            Message::CalculateSize(path) => {
+                self.dir_sizes.insert(path.clone(), None);
+                Command::perform(dir_size(path.clone()), move |size| {
+                    Message::DirSizeComputed(path, size)
+                })
+            }
+            Message::DirSizeComputed(path, size) => {
+                // Results for a folder we've since navigated away from are dropped
+                if let Some(entry) = self.dir_sizes.get_mut(&path) {
+                    *entry = Some(size);
+                }
+                Command::none()
+            }
+            Message::CopyPath(path) => {
+                self.copy_to_clipboard(path.display().to_string(), "Copied path")
+            }
+            Message::CopyName(name) => self.copy_to_clipboard(name, "Copied name"),
+            Message::ClearStatus(message) => {
+                // Leave newer messages alone
+                if self.status_message.as_ref() == Some(&message) {
+                    self.status_message = None;
+                }
+                Command::none()
+            }
+            Message::PinCurrentFolder => {
+                let path = self.current_path.clone();
+                if !self.config.applications.file_manager.favorites.contains(&path) {
+                    self.config.applications.file_manager.favorites.push(path);
+                    self.save_favorites();
+                }
+                Command::none()
+            }
+            Message::Unpin(path) => {
+                self.config.applications.file_manager.favorites.retain(|favorite| favorite != &path);
+                self.save_favorites();
+                Command::none()
+            }
+            Message::RefreshDrives => Command::perform(list_drives(), Message::DrivesListed),
+            Message::DrivesListed(result) => {
+                // Polled, so a missing lsblk is logged rather than shown
+                match result {
+                    Ok(drives) => self.drives = drives,
+                    Err(e) => log::debug!("Can't list drives: {}", e),
+                }
+                Command::none()
+            }
+            Message::MountDrive(device) => {
+                self.status_message = Some(format!("Mounting {}...", device));
+                Command::perform(mount_drive(device), Message::DriveMounted)
+            }
+            Message::DriveMounted(result) => {
+                self.status_message = None;
+                match result {
+                    Ok(mount_point) => {
+                        if let Err(e) = self.try_change_directory(mount_point) {
+                            self.error_message = Some(format!("Error: {}", e));
+                        }
+                    }
+                    Err(e) => self.error_message = Some(format!("Error: {}", e)),
+                }
+                Command::perform(list_drives(), Message::DrivesListed)
+            }
+            Message::UnmountDrive(drive) => match drive.mount_point {
+                Some(mount_point) => Command::perform(unmount_drive(drive.device), move |result| {
+                    Message::DriveUnmounted(mount_point, result)
+                }),
+                None => Command::none(),
+            },
+            Message::DriveUnmounted(mount_point, result) => {
+                match result {
+                    Ok(()) => {
+                        self.status_message = Some(format!("Unmounted {}", mount_point.display()));
+                        // Leave the folder, which is now an empty mount point
+                        if self.current_path.starts_with(&mount_point) {
+                            let parent = mount_point.parent().unwrap_or(Path::new("/")).to_path_buf();
+                            self.change_directory(parent);
+                        }
+                    }
+                    Err(e) => self.error_message = Some(format!("Error: {}", e)),
+                }
+                Command::perform(list_drives(), Message::DrivesListed)
+            }
+            Message::SortBy(key) => {
+                // Clicking the active column flips the direction
+                if self.sort_key == key {
+                    self.sort_order = match self.sort_order {
+                        SortOrder::Ascending => SortOrder::Descending,
+                        SortOrder::Descending => SortOrder::Ascending,
+                    };
+                } else {
+                    self.sort_key = key;
+                    self.sort_order = SortOrder::Ascending;
+                }
+
+                let selected = self.selected_paths();
+                self.sort_items();
+                self.reselect(&selected);
+                Command::none()
+            }
+            Message::OpenTerminal => {
+                self.open_terminal();
+                Command::none()
+            }
+            Message::ToggleDualPane => {
+                // Both panes start out on the current folder
+                self.other_pane = match self.other_pane {
+                    Some(_) => None,
+                    None => Some(Pane {
+                        current_path: self.current_path.clone(),
+                        items: self.items.clone(),
+                        selected: BTreeSet::new(),
+                        selection_anchor: None,
+                        dir_sizes: HashMap::new(),
+                        filter: self.filter.clone(),
+                    }),
+                };
+                self.active_pane = 0;
+                Command::none()
+            }
+            Message::SwitchPane => {
+                if self.other_pane.is_some() {
+                    self.swap_panes();
+                    // The watcher only follows the focused pane
+                    self.reload();
+                }
+                Command::none()
+            }
+            Message::CopyToOtherPane => self.transfer_to_other_pane(ClipboardOp::Copy),
+            Message::MoveToOtherPane => self.transfer_to_other_pane(ClipboardOp::Cut),
+            Message::CopyTo => {
+                self.folder_picker = Some(FolderPicker::new(ClipboardOp::Copy, self.selected_paths()));
+                Command::none()
+            }
+            Message::MoveTo => {
+                self.folder_picker = Some(FolderPicker::new(ClipboardOp::Cut, self.selected_paths()));
+                Command::none()
+            }
+            Message::PickerOpen(path) => {
+                if let Some(picker) = &mut self.folder_picker {
+                    picker.open(path);
+                }
+                Command::none()
+            }
+            Message::PickerUp => {
+                if let Some(picker) = &mut self.folder_picker {
+                    if let Some(parent) = picker.current.parent() {
+                        picker.open(parent.to_path_buf());
+                    }
+                }
+                Command::none()
+            }
+            Message::PickerConfirm => {
+                if self.is_busy() {
+                    return Command::none();
+                }
+                match self.folder_picker.take() {
+                    Some(picker) => {
+                        self.pending_paste = Some(PendingPaste {
+                            remaining: picker.sources.into(),
+                            op: picker.op,
+                            destination: picker.current,
+                            errors: Vec::new(),
+                            done: 0,
+                            conflict: None,
+                        });
+                        self.continue_paste()
+                    }
+                    None => Command::none(),
+                }
+            }
+            Message::PickerCancel => {
+                self.folder_picker = None;
+                Command::none()
+            }
+            Message::EditFile(path) => {
+                match Editor::open(path.clone()) {
+                    Ok(editor) => {
+                        self.editor = Some(editor);
+                        self.remember_recent(path);
+                    }
+                    Err(e) => self.error_message = Some(format!("Error: {}", e)),
+                }
+                Command::none()
+            }
+            Message::EditorAction(action) => {
+                if let Some(editor) = &mut self.editor {
+                    editor.dirty |= action.is_edit();
+                    editor.content.perform(action);
+                }
+                Command::none()
+            }
+            Message::SaveEdit => {
+                if let Some(editor) = &mut self.editor {
+                    match editor.save() {
+                        Ok(()) => {
+                            editor.dirty = false;
+                            editor.confirm_discard = false;
+                            self.error_message = None;
+                            self.status_message = Some(format!("Saved {}", editor.path.display()));
+                        }
+                        Err(e) => self.error_message = Some(format!("Error: {}", e)),
+                    }
+                }
+                Command::none()
+            }
+            Message::CancelEdit => {
+                match &mut self.editor {
+                    Some(editor) if editor.dirty => editor.confirm_discard = true,
+                    _ => self.editor = None,
+                }
+                Command::none()
+            }
+            Message::DiscardEdit => {
+                self.editor = None;
+                Command::none()
+            }
+            Message::KeepEditing => {
+                if let Some(editor) = &mut self.editor {
+                    editor.confirm_discard = false;
+                }
+                Command::none()
+            }
+            Message::RefreshView => {
+                let selected = self.selected_paths();
+                self.reload();
+                self.reselect(&selected);
+                Command::none()
+            }
+            Message::Close => {
+                Command::none()
+            }
+        }
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        let modifiers = event::listen_with(|event, _status| match event {
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                Some(Message::ModifiersChanged(modifiers))
+            }
+            _ => None,
+        });
+
+        // Key presses captured by a focused text input never reach this
+        let keys = keyboard::on_key_press(|key, modifiers| match key {
+            keyboard::Key::Character(c) if c.as_str() == "g" && modifiers.command() => Some(Message::OpenGoTo),
+            keyboard::Key::Character(c) if c.as_str() == "f" && modifiers.command() => Some(Message::FocusFilter),
+            keyboard::Key::Named(keyboard::key::Named::ArrowUp) => Some(Message::MoveSelection(-1)),
+            keyboard::Key::Named(keyboard::key::Named::ArrowDown) => Some(Message::MoveSelection(1)),
+            keyboard::Key::Named(keyboard::key::Named::Enter) => Some(Message::OpenSelected),
+            keyboard::Key::Named(keyboard::key::Named::Backspace) => Some(Message::GoUp),
+            keyboard::Key::Named(keyboard::key::Named::Tab) => Some(Message::SwitchPane),
+            keyboard::Key::Named(keyboard::key::Named::Delete) if modifiers.shift() => {
+                Some(Message::DeleteSelectedPermanently)
+            }
+            keyboard::Key::Named(keyboard::key::Named::Delete) => Some(Message::DeleteSelected),
+            _ => None,
+        });
+
+        let mut subscriptions = vec![modifiers, watch_directory(self.current_path.clone())];
+        // Polled so drives plugged in while the sidebar is on screen show up
+        if self.editor.is_none() && self.folder_picker.is_none() {
+            subscriptions.push(iced::time::every(DRIVE_POLL_INTERVAL).map(|_| Message::RefreshDrives));
+        }
+        // The listing's shortcuts would act on a hidden selection while the
+        // editor or folder picker covers it
+        if self.go_to.is_some() {
+            // Tab isn't taken by the focused input, so it can complete
+            subscriptions.push(keyboard::on_key_press(|key, _| match key {
+                keyboard::Key::Named(keyboard::key::Named::Tab) => Some(Message::GoToComplete),
+                _ => None,
+            }));
+        } else if self.editor.is_none() && self.folder_picker.is_none() {
+            subscriptions.push(keys);
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    /// Shows `path`: a folder is opened, and a file is selected in its
+    /// parent folder.
+    pub fn open_path(&mut self, path: &Path) -> Result<(), String> {
+        let metadata = fs::metadata(path)
+            .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+
+        if metadata.is_dir() {
+            return self.try_change_directory(path.to_path_buf());
+        }
+
+        let parent = path
+            .parent()
+            .ok_or_else(|| format!("Cannot open {}: no parent folder", path.display()))?;
+        self.try_change_directory(parent.to_path_buf())?;
+        self.reselect(&[path.to_path_buf()]);
+        Ok(())
+    }
+
+    fn change_directory(&mut self, path: PathBuf) {
+        if let Err(e) = self.try_change_directory(path) {
+            self.error_message = Some(format!("Error: {}", e));
+        }
+    }
+
+    fn try_change_directory(&mut self, path: PathBuf) -> Result<(), String> {
+        // Stay put, listing intact, if the new folder can't be read. A
+        // filter typed for one folder rarely fits the next
+        let filter = std::mem::take(&mut self.filter);
+        if let Err(e) = self.load_directory(path.clone()) {
+            self.filter = filter;
+            return Err(e);
+        }
+
+        self.current_path = path;
+        self.clear_selection();
+        self.dir_sizes.clear();
+        self.error_message = None;
+        self.status_message = None;
+
+        if self.config.applications.file_manager.restore_last_path {
+            if let Err(e) = save_last_path(&self.current_path) {
+                log::warn!("Failed to remember last directory: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `contents` to the OS clipboard and briefly confirms it in the
+    /// status bar.
+    fn copy_to_clipboard(&mut self, contents: String, confirmation: &str) -> Command<Message> {
+        let confirmation = confirmation.to_string();
+        self.status_message = Some(confirmation.clone());
+
+        Command::batch(vec![
+            clipboard::write(contents),
+            Command::perform(tokio::time::sleep(Duration::from_secs(2)), move |_| {
+                Message::ClearStatus(confirmation)
+            }),
+        ])
+    }
+
+    /// Opens a file with the desktop's default application. Scripts and
+    /// executables are never launched directly; the user is asked first.
+    fn open_file(&mut self, path: PathBuf) {
+        if is_runnable(&path) {
+            self.pending_run = Some(path);
+            return;
+        }
+
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(&path);
+        match spawn_detached(command) {
+            Ok(()) => self.remember_recent(path),
+            Err(e) => {
+                self.error_message = Some(format!("Error: Cannot open {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    /// Runs a script or executable from the current folder. Scripts without
+    /// the executable bit are run through `sh`.
+    fn run_script(&mut self, path: PathBuf) {
+        let mut command = if is_executable(&path) {
+            std::process::Command::new(&path)
+        } else {
+            let mut command = std::process::Command::new("sh");
+            command.arg(&path);
+            command
+        };
+        command.current_dir(&self.current_path);
+
+        match spawn_detached(command) {
+            Ok(()) => {
+                self.remember_recent(path.clone());
+                self.status_message = Some(format!("Started {}", path.display()));
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Error: Cannot run {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    /// Opens `$TERMINAL`, or xterm without one, in the current folder.
+    fn open_terminal(&mut self) {
+        let terminal = std::env::var("TERMINAL")
+            .ok()
+            .filter(|terminal| !terminal.is_empty())
+            .unwrap_or_else(|| "xterm".to_string());
+        let mut command = std::process::Command::new(&terminal);
+        command.current_dir(&self.current_path);
+
+        if let Err(e) = spawn_detached(command) {
+            self.error_message = Some(format!("Error: Cannot start {}: {}", terminal, e));
+        }
+    }
+
+    fn remember_recent(&mut self, path: PathBuf) {
+        self.recent_files.retain(|recent| *recent != path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+        self.save_recent_files();
+    }
+
+    fn save_recent_files(&self) {
+        if let Err(e) = save_recent_files(&self.recent_files) {
+            log::warn!("Failed to save recent files: {}", e);
+        }
+    }
+
+    fn save_favorites(&mut self) {
+        let favorites = self.config.applications.file_manager.favorites.clone();
+        if let Err(e) = Config::persist(|config| config.applications.file_manager.favorites = favorites) {
+            self.error_message = Some(format!("Error: Failed to save favorites: {}", e));
+        }
+    }
+
+    /// Copies or moves the selection into the other pane's folder, going
+    /// through the same collision handling as a paste.
+    fn transfer_to_other_pane(&mut self, op: ClipboardOp) -> Command<Message> {
+        if self.is_busy() {
+            return Command::none();
+        }
+        let Some(other) = &self.other_pane else { return Command::none() };
+        self.pending_paste = Some(PendingPaste {
+            remaining: self.selected_paths().into(),
+            op,
+            destination: other.current_path.clone(),
+            errors: Vec::new(),
+            done: 0,
+            conflict: None,
+        });
+        self.continue_paste()
+    }
+
+    fn swap_panes(&mut self) {
+        let Some(other) = &mut self.other_pane else { return };
+        std::mem::swap(&mut self.current_path, &mut other.current_path);
+        std::mem::swap(&mut self.items, &mut other.items);
+        std::mem::swap(&mut self.selected, &mut other.selected);
+        std::mem::swap(&mut self.selection_anchor, &mut other.selection_anchor);
+        std::mem::swap(&mut self.dir_sizes, &mut other.dir_sizes);
+        std::mem::swap(&mut self.filter, &mut other.filter);
+        self.active_pane = 1 - self.active_pane;
+    }
+
+    fn clear_selection(&mut self) {
+        self.selected.clear();
+        self.selection_anchor = None;
+    }
+
+    /// Restores a selection by path, since indices shift whenever the
+    /// list is reloaded or re-sorted.
+    fn reselect(&mut self, paths: &[PathBuf]) {
+        self.clear_selection();
+        for (index, item) in self.items.iter().enumerate() {
+            if paths.contains(&item.path) {
+                self.selected.insert(index);
+                self.selection_anchor = Some(index);
+            }
+        }
+    }
+
+    fn selected_paths(&self) -> Vec<PathBuf> {
+        self.selected
+            .iter()
+            .filter_map(|&index| self.items.get(index))
+            .map(|item| item.path.clone())
+            .collect()
+    }
+
+    /// Reports whether an operation is already underway, which has to
+    /// finish before another can start.
+    fn is_busy(&mut self) -> bool {
+        let busy = self.pending_paste.is_some() || self.operation.is_some();
+        if busy {
+            self.error_message = Some("Error: Wait for the current operation to finish".to_string());
+        }
+        busy
+    }
+
+    /// Starts transferring the next queued item, pausing if it collides.
+    /// Once the queue is empty the paste is finished.
+    fn continue_paste(&mut self) -> Command<Message> {
+        let Some(pending) = &mut self.pending_paste else { return Command::none() };
+
+        while let Some(src) = pending.remaining.pop_front() {
+            let Some(name) = src.file_name() else { continue };
+            let dst = pending.destination.join(name);
+            if dst.exists() {
+                pending.conflict = Some((src, dst));
+                return Command::none();
+            }
+            return self.start_transfer(src, dst, false);
+        }
+
+        if let Some(pending) = self.pending_paste.take() {
+            self.finish_operation(pending.errors);
+            if pending.done > 0 {
+                self.status_message = Some(format!(
+                    "{} {} {}",
+                    if pending.op == ClipboardOp::Copy { "Copied" } else { "Moved" },
+                    pending.done,
+                    if pending.done == 1 { "item" } else { "items" }
+                ));
+            }
+        }
+        Command::none()
+    }
+
+    /// Copies or moves one item of the pending paste in the background,
+    /// first removing `dst` if `replace`.
+    fn start_transfer(&mut self, src: PathBuf, dst: PathBuf, replace: bool) -> Command<Message> {
+        let Some(pending) = &self.pending_paste else { return Command::none() };
+        let op = pending.op;
+        let label = format!(
+            "{} {}",
+            if op == ClipboardOp::Copy { "Copying" } else { "Moving" },
+            src.file_name().unwrap_or_default().to_string_lossy()
+        );
+
+        self.start_operation(label, move |tracker| {
+            let result = if replace { remove_path(&dst) } else { Ok(()) }
+                .and_then(|_| transfer(op, &src, &dst, tracker));
+            match result {
+                Ok(skipped) => skipped,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => Vec::new(),
+                Err(e) => vec![format!("{}: {}", src.display(), e)],
+            }
+        })
+    }
+
+    /// Moves `paths` to the trash, or deletes them outright if `permanent`.
+    fn delete(&mut self, paths: Vec<PathBuf>, permanent: bool) -> Command<Message> {
+        if self.is_busy() {
+            return Command::none();
+        }
+        let label = format!(
+            "{} {} {}",
+            if permanent { "Deleting" } else { "Moving to the trash" },
+            paths.len(),
+            if paths.len() == 1 { "item" } else { "items" }
+        );
+
+        self.start_operation(label, move |tracker| {
+            // Trashing is a rename per item, so only a permanent delete
+            // needs the trees walked
+            if permanent {
+                for path in &paths {
+                    tracker.measure(path);
+                }
+            } else {
+                tracker.progress.total_items = paths.len() as u64;
+            }
+
+            let mut errors = Vec::new();
+            for path in &paths {
+                let result = if permanent {
+                    remove_tree(path, tracker)
+                } else {
+                    move_to_trash(path).and_then(|_| tracker.advance(0))
+                };
+                match result {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => break,
+                    Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+                    Ok(()) => {}
+                }
+            }
+            errors
+        })
+    }
+
+    /// Runs `work` on a blocking thread, reporting its progress at most every
+    /// `PROGRESS_INTERVAL` and finishing with the errors it returns.
+    fn start_operation(
+        &mut self,
+        label: String,
+        work: impl FnOnce(&mut Tracker) -> Vec<String> + Send + 'static,
+    ) -> Command<Message> {
+        let id = self.next_operation_id;
+        self.next_operation_id += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.operation = Some(Operation {
+            id,
+            label,
+            progress: Progress::default(),
+            cancel: cancel.clone(),
+        });
+
+        let (sender, receiver) = mpsc::unbounded();
+        let progress = receiver.map(move |progress| Message::OperationProgress(id, progress));
+        let finished = stream::once(async move {
+            let errors = tokio::task::spawn_blocking(move || {
+                let mut last_report = Instant::now();
+                let mut report = |progress| {
+                    if last_report.elapsed() >= PROGRESS_INTERVAL {
+                        last_report = Instant::now();
+                        let _ = sender.unbounded_send(progress);
+                    }
+                };
+                let mut tracker = Tracker {
+                    progress: Progress::default(),
+                    cancel: &cancel,
+                    report: &mut report,
+                };
+                work(&mut tracker)
+            })
+            .await
+            .unwrap_or_else(|e| vec![format!("Operation failed: {}", e)]);
+            Message::OperationFinished(id, errors)
+        });
+
+        Command::run(stream::select(progress, finished), |message| message)
+    }
+
+    fn create_delete_prompt(&self) -> Option<Element<Message>> {
+        let (paths, permanent) = self.pending_delete.as_ref()?;
+        let items = match paths.as_slice() {
+            [path] => format!(
+                "\"{}\"",
+                path.file_name().unwrap_or(path.as_os_str()).to_string_lossy()
+            ),
+            _ => format!("{} items", paths.len()),
+        };
+        let question = if *permanent {
+            format!("Permanently delete {}? This can't be undone.", items)
+        } else {
+            format!("Move {} to the trash?", items)
+        };
+
+        let prompt = row![
+            text(question).size(14),
+            row![].width(Length::Fill),
+            button(text("Delete").size(12))
+                .on_press(Message::ConfirmDelete)
+                .padding(5),
+            button(text("Cancel").size(12))
+                .on_press(Message::CancelDelete)
+                .padding(5),
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center);
+
+        Some(
+            container(prompt)
+                .padding(10)
+                .style(|theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    container::Appearance {
+                        background: Some(iced::Background::Color(palette.background.strong.color)),
+                        border: iced::Border::with_radius(4),
+                        ..Default::default()
+                    }
+                })
+                .into(),
+        )
+    }
+
+    fn create_go_to_bar(&self) -> Option<Element<Message>> {
+        let go_to = self.go_to.as_ref()?;
+
+        let input = text_input("/path/to/folder", &go_to.input)
+            .id(go_to_input_id())
+            .on_input(Message::GoToInput)
+            .on_submit(Message::GoToSubmit)
+            .padding(5)
+            .width(Length::Fill);
+
+        let feedback = match &go_to.feedback {
+            Ok(description) => text(description)
+                .size(12)
+                .style(theme::Text::Color(iced::Color::from_rgb(0.4, 0.8, 0.4))),
+            Err(problem) => text(problem)
+                .size(12)
+                .style(theme::Text::Color(iced::Color::from_rgb(1.0, 0.4, 0.4))),
+        };
+
+        let mut bar = column![
+            row![
+                text("Go to").size(14),
+                input,
+                button(text("Go").size(12))
+                    .on_press_maybe(go_to.feedback.is_ok().then_some(Message::GoToSubmit))
+                    .padding(5),
+                button(text("Cancel").size(12))
+                    .on_press(Message::GoToCancel)
+                    .padding(5),
+            ]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center),
+            feedback,
+        ]
+        .spacing(5);
+
+        if !go_to.suggestions.is_empty() {
+            let mut suggestions = row![].spacing(5);
+            for suggestion in &go_to.suggestions {
+                let name = suggestion.trim_end_matches('/').rsplit('/').next().unwrap_or(suggestion);
+                suggestions = suggestions.push(
+                    button(text(format!("📁 {}", name)).size(12))
+                        .on_press(Message::GoToInput(suggestion.clone()))
+                        .padding(3),
+                );
+            }
+            bar = bar.push(suggestions);
+        }
+
+        Some(
+            container(bar)
+                .padding(10)
+                .style(|theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    container::Appearance {
+                        background: Some(iced::Background::Color(palette.background.strong.color)),
+                        border: iced::Border::with_radius(4),
+                        ..Default::default()
+                    }
+                })
+                .into(),
+        )
+    }
+
+    fn create_run_prompt(&self) -> Option<Element<Message>> {
+        let path = self.pending_run.as_ref()?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+
+        let prompt = row![
+            text(format!("⚠ \"{}\" is a program. Running it can change or delete your files.", name))
+                .size(14),
+            row![].width(Length::Fill),
+            button(text("Run").size(12))
+                .on_press(Message::RunScript)
+                .padding(5),
+            button(text("Edit").size(12))
+                .on_press(Message::EditScript)
+                .padding(5),
+            button(text("Cancel").size(12))
+                .on_press(Message::CancelRun)
+                .padding(5),
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center);
+
+        Some(
+            container(prompt)
+                .padding(10)
+                .style(|theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    container::Appearance {
+                        background: Some(iced::Background::Color(palette.background.strong.color)),
+                        border: iced::Border::with_radius(4),
+                        ..Default::default()
+                    }
+                })
+                .into(),
+        )
+    }
+
+    /// The rename pattern, with a preview of every selected item's new name
+    /// and any clash that stops the rename.
+    fn create_batch_rename_bar(&self) -> Option<Element<Message>> {
+        let batch_rename = self.batch_rename.as_ref()?;
+
+        let input = text_input("{name}.{ext}", &batch_rename.pattern)
+            .id(batch_rename_input_id())
+            .on_input(Message::BatchRenameInput)
+            .on_submit(Message::BatchRenameConfirm)
+            .padding(5)
+            .width(Length::Fill);
+
+        let mut preview = column![].spacing(2);
+        for rename in &batch_rename.plan {
+            let from = rename.from.file_name().unwrap_or_default().to_string_lossy();
+            let to = rename.to.file_name().unwrap_or_default().to_string_lossy();
+            let line = match &rename.problem {
+                Some(problem) => text(format!("{}  →  {}  ({})", from, to, problem))
+                    .size(12)
+                    .style(theme::Text::Color(iced::Color::from_rgb(1.0, 0.4, 0.4))),
+                None if rename.is_unchanged() => text(format!("{}  (unchanged)", from))
+                    .size(12)
+                    .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
+                None => text(format!("{}  →  {}", from, to)).size(12),
+            };
+            preview = preview.push(line);
+        }
+
+        let bar = column![
+            row![
+                text(format!("Rename {} items to", batch_rename.plan.len())).size(14),
+                input,
+                button(text("Rename").size(12))
+                    .on_press_maybe(batch_rename.is_ready().then_some(Message::BatchRenameConfirm))
+                    .padding(5),
+                button(text("Cancel").size(12))
+                    .on_press(Message::BatchRenameCancel)
+                    .padding(5),
+            ]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center),
+            text("{name} is the old name without its extension, {ext} the extension, {n} a count")
+                .size(12)
+                .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
+            container(iced::widget::scrollable(preview).width(Length::Fill))
+                .max_height(BATCH_RENAME_PREVIEW_HEIGHT),
+        ]
+        .spacing(5);
+
+        Some(
+            container(bar)
+                .padding(10)
+                .style(|theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    container::Appearance {
+                        background: Some(iced::Background::Color(palette.background.strong.color)),
+                        border: iced::Border::with_radius(4),
+                        ..Default::default()
+                    }
+                })
+                .into(),
+        )
+    }
+
+    /// The configured programs, with the one matching the typed command
+    /// highlighted, and a field for any other command.
+    fn create_open_with_prompt(&self) -> Option<Element<Message>> {
+        let (path, command) = self.open_with.as_ref()?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+
+        let mut prompt = row![text(format!("Open \"{}\" with", name)).size(14)]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center);
+        for app in &self.config.applications.file_manager.open_with {
+            let chosen = app.command == command.trim();
+            prompt = prompt.push(
+                button(text(&app.name).size(12))
+                    .on_press(Message::OpenWithInput(app.command.clone()))
+                    .padding(5)
+                    .style(move |theme: &iced::Theme, _| {
+                        let palette = theme.extended_palette();
+                        button::Appearance {
+                            background: Some(iced::Background::Color(if chosen {
+                                theme.palette().primary
+                            } else {
+                                palette.background.weak.color
+                            })),
+                            border: iced::Border::with_radius(4),
+                            text_color: if chosen {
+                                palette.primary.base.text
+                            } else {
+                                palette.background.base.text
+                            },
+                            ..Default::default()
+                        }
+                    }),
+            );
+        }
+
+        let prompt = prompt
+            .push(
+                text_input("or type a command", command)
+                    .id(open_with_input_id())
+                    .on_input(Message::OpenWithInput)
+                    .on_submit(Message::OpenWithSubmit)
+                    .padding(5)
+                    .width(Length::Fill),
+            )
+            .push(
+                button(text("Open").size(12))
+                    .on_press_maybe((!command.trim().is_empty()).then_some(Message::OpenWithSubmit))
+                    .padding(5),
+            )
+            .push(
+                button(text("Cancel").size(12))
+                    .on_press(Message::OpenWithCancel)
+                    .padding(5),
+            );
+
+        Some(
+            container(prompt)
+                .padding(10)
+                .style(|theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    container::Appearance {
+                        background: Some(iced::Background::Color(palette.background.strong.color)),
+                        border: iced::Border::with_radius(4),
+                        ..Default::default()
+                    }
+                })
+                .into(),
+        )
+    }
+
+    fn create_collision_prompt(&self) -> Option<Element<Message>> {
+        let (src, dst) = self.pending_paste.as_ref()?.conflict.as_ref()?;
+        let name = dst.file_name()?.to_string_lossy().to_string();
+        let renamed = fs_ops::unique_name(dst)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let prompt = row![
+            text(format!("\"{}\" already exists.", name)).size(14),
+            row![].width(Length::Fill),
+            button(text("Overwrite").size(12))
+                .on_press_maybe((src != dst).then_some(Message::ResolveCollision(CollisionChoice::Overwrite)))
+                .padding(5),
+            button(text(format!("Rename to \"{}\"", renamed)).size(12))
+                .on_press(Message::ResolveCollision(CollisionChoice::Rename))
+                .padding(5),
+            button(text("Skip").size(12))
+                .on_press(Message::ResolveCollision(CollisionChoice::Skip))
+                .padding(5),
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center);
+
+        Some(
+            container(prompt)
+                .padding(10)
+                .style(|theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    container::Appearance {
+                        background: Some(iced::Background::Color(palette.background.strong.color)),
+                        border: iced::Border::with_radius(4),
+                        ..Default::default()
+                    }
+                })
+                .into(),
+        )
+    }
+
+    fn create_editor(&self) -> Option<Element<Message>> {
+        let editor = self.editor.as_ref()?;
+        let title = format!(
+            "{}{}",
+            editor.path.display(),
+            if editor.dirty { " ●" } else { "" }
+        );
+
+        let header: Element<Message> = if editor.confirm_discard {
+            row![
+                text("Discard unsaved changes?").size(14),
+                row![].width(Length::Fill),
+                button(text("Discard").size(12))
+                    .on_press(Message::DiscardEdit)
+                    .padding(5),
+                button(text("Keep Editing").size(12))
+                    .on_press(Message::KeepEditing)
+                    .padding(5),
+            ]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center)
+            .into()
+        } else {
+            row![
+                text(title).size(14),
+                row![].width(Length::Fill),
+                button(text("Cancel").size(12))
+                    .on_press(Message::CancelEdit)
+                    .padding(5),
+                button(text("Save").size(12))
+                    .on_press_maybe(editor.dirty.then_some(Message::SaveEdit))
+                    .padding(5),
+            ]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center)
+            .into()
+        };
+
+        let panel = column![
+            header,
+            text_editor(&editor.content)
+                .on_action(Message::EditorAction)
+                .height(Length::Fill),
+        ]
+        .spacing(10);
+
+        Some(
+            container(panel)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .padding(10)
+                .style(|theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    container::Appearance {
+                        background: Some(iced::Background::Color(palette.background.weak.color)),
+                        border: iced::Border {
+                            color: palette.background.strong.color,
+                            width: 1.0,
+                            radius: 4.0.into(),
+                        },
+                        ..Default::default()
+                    }
+                })
+                .into(),
+        )
+    }
+
+    fn create_folder_picker(&self) -> Option<Element<Message>> {
+        let picker = self.folder_picker.as_ref()?;
+        let count = picker.sources.len();
+        let (verb, confirm) = match picker.op {
+            ClipboardOp::Copy => ("Copy", "Copy Here"),
+            ClipboardOp::Cut => ("Move", "Move Here"),
+        };
+        // A folder can't go inside itself
+        let valid = !picker.sources.iter().any(|src| picker.current.starts_with(src));
+
+        let header = row![
+            text(format!(
+                "{} {} {} to:",
+                verb,
+                count,
+                if count == 1 { "item" } else { "items" }
+            ))
+            .size(14),
+            text(picker.current.display().to_string()).size(14),
+            row![].width(Length::Fill),
+            button(text("⬆ Up").size(12))
+                .on_press_maybe(picker.current.parent().map(|_| Message::PickerUp))
+                .padding(5),
+            button(text("Cancel").size(12))
+                .on_press(Message::PickerCancel)
+                .padding(5),
+            button(text(confirm).size(12))
+                .on_press_maybe(valid.then_some(Message::PickerConfirm))
+                .padding(5),
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center);
+
+        let mut folders = column![].spacing(2);
+        for folder in &picker.folders {
+            folders = folders.push(
+                button(text(format!("📁 {}", folder.name)).size(14))
+                    .on_press(Message::PickerOpen(folder.path.clone()))
+                    .width(Length::Fill)
+                    .padding(5)
+                    .style(|theme: &iced::Theme, status| {
+                        let palette = theme.extended_palette();
+                        button::Appearance {
+                            background: Some(iced::Background::Color(
+                                if matches!(status, button::Status::Hovered) {
+                                    palette.background.strong.color
+                                } else {
+                                    iced::Color::TRANSPARENT
+                                }
+                            )),
+                            border: iced::Border::with_radius(4),
+                            text_color: palette.background.base.text,
+                            ..Default::default()
+                        }
+                    }),
+            );
+        }
+        if picker.folders.is_empty() {
+            folders = folders.push(
+                text("No folders here")
+                    .size(12)
+                    .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
+            );
+        }
+
+        let mut panel = column![header].spacing(10);
+        if let Some(error) = &picker.error {
+            panel = panel.push(
+                text(error)
+                    .size(12)
+                    .style(theme::Text::Color(iced::Color::from_rgb(1.0, 0.4, 0.4))),
+            );
+        }
+        let panel = panel.push(scrollable(folders).height(Length::Fill));
+
+        Some(
+            container(panel)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .padding(10)
+                .style(|theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    container::Appearance {
+                        background: Some(iced::Background::Color(palette.background.weak.color)),
+                        border: iced::Border {
+                            color: palette.background.strong.color,
+                            width: 1.0,
+                            radius: 4.0.into(),
+                        },
+                        ..Default::default()
+                    }
+                })
+                .into(),
+        )
+    }
+
+    fn finish_operation(&mut self, mut errors: Vec<String>) {
+        let path = self.current_path.clone();
+        if let Err(e) = self.load_directory(path) {
+            errors.push(e);
+        }
+        // Transfers between panes change the other listing too
+        if self.other_pane.is_some() {
+            self.swap_panes();
+            self.reload();
+            self.clear_selection();
+            self.swap_panes();
+        }
+        self.clear_selection();
+        self.error_message = if errors.is_empty() {
+            None
+        } else {
+            Some(format!("Error: {}", errors.join("; ")))
+        };
+    }
+
+    pub fn view(&self) -> Element<Message> {
+        let header = self.create_header();
+        let toolbar = self.create_toolbar();
+        let content = self.create_content();
+        let status_bar = self.create_status_bar();
+
+        let mut main_content = column![
+            header,
+            toolbar,
+        ]
+        .spacing(5)
+        .padding(10);
+
+        if let Some(bar) = self.create_go_to_bar() {
+            main_content = main_content.push(bar);
+        }
+        if let Some(prompt) = self.create_collision_prompt() {
+            main_content = main_content.push(prompt);
+        }
+        if let Some(prompt) = self.create_delete_prompt() {
+            main_content = main_content.push(prompt);
+        }
+        if let Some(prompt) = self.create_run_prompt() {
+            main_content = main_content.push(prompt);
+        }
+        if let Some(prompt) = self.create_open_with_prompt() {
+            main_content = main_content.push(prompt);
+        }
+        if let Some(bar) = self.create_batch_rename_bar() {
+            main_content = main_content.push(bar);
+        }
+
+        let body: Element<Message> = match self.create_editor().or_else(|| self.create_folder_picker()) {
+            Some(panel) => panel,
+            None => row![self.create_sidebar(), content].spacing(5).into(),
+        };
+
+        let main_content = main_content.push(body).push(status_bar);
+
+        container(main_content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(|theme: &iced::Theme| {
+                let palette = theme.extended_palette();
+                container::Appearance {
+                    background: Some(iced::Background::Color(palette.background.base.color)),
+                    border: iced::Border::with_radius(0),
+                    ..Default::default()
+                }
+            })
+            .into()
+    }
+
+    fn create_header(&self) -> Element<Message> {
+        let close_btn = button(text("✕").size(20))
+            .on_press(Message::Close)
+            .padding(5)
+            .style(|theme: &iced::Theme, status| {
+                let palette = theme.extended_palette();
+                button::Appearance {
+                    background: Some(iced::Background::Color(
+                        if matches!(status, button::Status::Hovered) {
+                            iced::Color::from_rgba8(255, 100, 100, 0.8)
+                        } else {
+                            iced::Color::TRANSPARENT
+                        }
+                    )),
+                    border: iced::Border::with_radius(4),
+                    text_color: palette.background.base.text,
+                    ..Default::default()
+                }
+            });
+
+        let filter_input = text_input("🔍 Filter (Ctrl+F)", &self.filter)
+            .id(filter_input_id())
+            .on_input(Message::FilterChanged)
+            .padding(5)
+            .width(Length::Fixed(200.0));
+
+        let path_display = text(self.current_path.display().to_string())
+            .size(14)
+            .style(theme::Text::Color(iced::Color::from_rgb(0.7, 0.7, 0.7)));
+
+        row![
+            text("📁 File Manager").size(18),
+            row![].width(Length::Fill),
+            path_display,
+            filter_input,
+            close_btn,
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center)
+        .into()
+    }
+
+    /// The button for an entry of the `toolbar` setting, or `None` if the
+    /// action doesn't apply to the current selection or isn't known.
+    fn toolbar_button(&self, action: &str, selected: &[PathBuf]) -> Option<Element<Message>> {
+        let has_selection = !selected.is_empty();
+        let single = match selected {
+            [path] => Some(path),
+            _ => None,
+        };
+
+        let (label, message) = match action {
+            // Nothing is above the root
+            "up" => ("⬆ Up", self.current_path.parent().map(|_| Message::GoUp)),
+            "refresh" => ("🔄 Refresh", Some(Message::RefreshView)),
+            "new_folder" => ("📁+ New Folder", Some(Message::CreateFolder)),
+            "new_file" => ("📄+ New File", Some(Message::CreateFile)),
+            "pin" => {
+                let is_pinned = self
+                    .config
+                    .applications
+                    .file_manager
+                    .favorites
+                    .contains(&self.current_path);
+                ("📌 Pin", (!is_pinned).then_some(Message::PinCurrentFolder))
+            }
+            "copy" => ("📋 Copy", has_selection.then_some(Message::CopySelected)),
+            "cut" => ("✂ Cut", has_selection.then_some(Message::CutSelected)),
+            "paste" => ("📥 Paste", (!self.clipboard.is_empty()).then_some(Message::Paste)),
+            "delete" => ("🗑 Delete", has_selection.then_some(Message::DeleteSelected)),
+            "dual_pane" => {
+                let label = if self.other_pane.is_some() { "▣ Single Pane" } else { "◫ Dual Pane" };
+                (label, Some(Message::ToggleDualPane))
+            }
+            "copy_to" => ("Copy To…", has_selection.then_some(Message::CopyTo)),
+            "move_to" => ("Move To…", has_selection.then_some(Message::MoveTo)),
+            "copy_to_other" if self.other_pane.is_some() => {
+                ("Copy to Other ⇆", has_selection.then_some(Message::CopyToOtherPane))
+            }
+            "move_to_other" if self.other_pane.is_some() => {
+                ("Move to Other ⇆", has_selection.then_some(Message::MoveToOtherPane))
+            }
+            "terminal" => ("🖥 Terminal", Some(Message::OpenTerminal)),
+            "copy_path" => ("🔗 Copy Path", Some(Message::CopyPath(single?.clone()))),
+            "batch_rename" => {
+                ("✎ Batch Rename", (selected.len() > 1).then_some(Message::BatchRename))
+            }
+            "copy_name" => {
+                let name = single?.file_name().map(|name| name.to_string_lossy().to_string())?;
+                ("🏷 Copy Name", Some(Message::CopyName(name)))
+            }
+            "edit" => {
+                let path = single.filter(|path| {
+                    self.items
+                        .iter()
+                        .find(|item| &item.path == *path)
+                        .is_some_and(|item| !item.is_dir && item.size <= MAX_EDIT_BYTES)
+                })?;
+                ("✏ Edit", Some(Message::EditFile(path.clone())))
+            }
+            "open_with" => {
+                let path = single.filter(|path| !path.is_dir())?;
+                ("Open With…", Some(Message::OpenWith(path.clone())))
+            }
+            // Walking a folder can be slow, so sizes are only computed on request
+            "calculate_size" => {
+                let path = single.filter(|path| path.is_dir())?;
+                let computing = matches!(self.dir_sizes.get(path), Some(None));
+                ("📏 Calculate Size", (!computing).then(|| Message::CalculateSize(path.clone())))
+            }
+            "extract" => {
+                // Offered only for archives
+                let archive = single.filter(|path| archive_stem(path).is_some())?;
+                ("📦 Extract", Some(Message::Extract(archive.clone())))
+            }
+            _ => return None,
+        };
+
+        Some(button(text(label)).on_press_maybe(message).padding(8).into())
+    }
+
+    fn create_toolbar(&self) -> Element<Message> {
+        let selected = self.selected_paths();
+        let mut toolbar = row![].spacing(10);
+        for action in &self.config.applications.file_manager.toolbar {
+            if let Some(button) = self.toolbar_button(action, &selected) {
+                toolbar = toolbar.push(button);
+            }
+        }
+
+        // Add create input if in create mode
+        if self.create_mode != CreateMode::None {
+            let placeholder = match self.create_mode {
+                CreateMode::File => "Enter file name...",
+                CreateMode::Folder => "Enter folder name...",
+                CreateMode::None => "",
+            };
+
+            let input = text_input(placeholder, &self.new_item_name)
+                .on_input(Message::UpdateNewItemName)
+                .on_submit(Message::ConfirmCreate)
+                .padding(5)
+                .width(Length::Fixed(200.0));
+
+            let confirm_btn = button(text("✓"))
+                .on_press(Message::ConfirmCreate)
+                .padding(5)
+                .style(|theme: &iced::Theme, _| {
+                    button::Appearance {
+                        background: Some(iced::Background::Color(
+                            theme.palette().primary
+                        )),
+                        border: iced::Border::with_radius(4),
+                        text_color: theme.extended_palette().primary.base.text,
+                        ..Default::default()
+                    }
+                });
+
+            let cancel_btn = button(text("✗"))
+                .on_press(Message::CancelCreate)
+                .padding(5)
+                .style(|theme: &iced::Theme, _| {
+                    button::Appearance {
+                        background: Some(iced::Background::Color(
+                            iced::Color::from_rgb(0.6, 0.2, 0.2)
+                        )),
+                        border: iced::Border::with_radius(4),
+                        text_color: iced::Color::WHITE,
+                        ..Default::default()
+                    }
+                });
+
+            toolbar = toolbar.push(row![].width(Length::Fixed(20.0)));
+            toolbar = toolbar.push(input);
+            toolbar = toolbar.push(confirm_btn);
+            toolbar = toolbar.push(cancel_btn);
+        }
+
+        container(toolbar)
+            .padding(10)
+            .style(|theme: &iced::Theme| {
+                let palette = theme.extended_palette();
+                container::Appearance {
+                    background: Some(iced::Background::Color(palette.background.weak.color)),
+                    border: iced::Border::with_radius(4),
+                    ..Default::default()
+                }
+            })
+            .into()
+    }
+
+    /// Removable drives: mounted ones open on click and have an eject
+    /// button, unmounted ones are greyed out with a Mount button.
+    fn create_drive_list(&self) -> Element<Message> {
+        let mut drives = column![text("Drives").size(14)].spacing(4);
+
+        for drive in &self.drives {
+            let name = format!("💾 {} ({})", drive.label, drive.size);
+            let entry = match &drive.mount_point {
+                Some(mount_point) => {
+                    let open_btn = button(text(name).size(13))
+                        .on_press(Message::NavigateTo(mount_point.clone()))
+                        .width(Length::Fill)
+                        .padding(5)
+                        .style(|theme: &iced::Theme, status| {
+                            let palette = theme.extended_palette();
+                            button::Appearance {
+                                background: Some(iced::Background::Color(
+                                    if matches!(status, button::Status::Hovered) {
+                                        iced::Color::from_rgba8(255, 255, 255, 0.1)
+                                    } else {
+                                        iced::Color::TRANSPARENT
+                                    }
+                                )),
+                                border: iced::Border::with_radius(4),
+                                text_color: palette.background.base.text,
+                                ..Default::default()
+                            }
+                        });
+                    let eject_btn = button(text("⏏").size(12))
+                        .on_press(Message::UnmountDrive(drive.clone()))
+                        .padding(5);
+                    row![open_btn, eject_btn]
+                }
+                None => row![
+                    container(
+                        text(name)
+                            .size(13)
+                            .style(theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5))),
+                    )
+                    .width(Length::Fill)
+                    .padding(5),
+                    button(text("Mount").size(11))
+                        .on_press(Message::MountDrive(drive.device.clone()))
+                        .padding(3),
+                ],
+            };
+            drives = drives.push(entry.spacing(2).align_items(alignment::Alignment::Center));
+        }
+        drives.into()
+    }
+
+    fn create_sidebar(&self) -> Element<Message> {
+        let mut favorites = column![
+            text("Favorites").size(14),
+        ]
+        .spacing(4);
+
+        for path in &self.config.applications.file_manager.favorites {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string());
+
+            // Missing folders stay listed, greyed out, so they can be unpinned
+            let exists = path.is_dir();
+            let label = if exists {
+                text(format!("📌 {}", name)).size(13)
+            } else {
+                text(format!("📌 {}", name))
+                    .size(13)
+                    .style(theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5)))
+            };
+
+            let open_btn = button(label)
+                .on_press_maybe(exists.then(|| Message::NavigateTo(path.clone())))
+                .width(Length::Fill)
+                .padding(5)
+                .style(|theme: &iced::Theme, status| {
+                    let palette = theme.extended_palette();
+                    button::Appearance {
+                        background: Some(iced::Background::Color(
+                            if matches!(status, button::Status::Hovered) {
+                                iced::Color::from_rgba8(255, 255, 255, 0.1)
+                            } else {
+                                iced::Color::TRANSPARENT
+                            }
+                        )),
+                        border: iced::Border::with_radius(4),
+                        text_color: palette.background.base.text,
+                        ..Default::default()
+                    }
+                });
+
+            let unpin_btn = button(text("✕").size(12))
+                .on_press(Message::Unpin(path.clone()))
+                .padding(5);
+
+            favorites = favorites.push(
+                row![open_btn, unpin_btn]
+                    .spacing(2)
+                    .align_items(alignment::Alignment::Center),
+            );
+        }
+
+        let toggle_label = if self.show_recent { "▾ Recent" } else { "▸ Recent" };
+        let mut recent_header = row![
+            button(text(toggle_label).size(14))
+                .on_press(Message::ToggleRecent)
+                .width(Length::Fill)
+                .padding(0)
+                .style(|theme: &iced::Theme, _| button::Appearance {
+                    background: None,
+                    text_color: theme.extended_palette().background.base.text,
+                    ..Default::default()
+                }),
+        ]
+        .align_items(alignment::Alignment::Center);
+        if self.show_recent && !self.recent_files.is_empty() {
+            recent_header = recent_header.push(
+                button(text("Clear").size(11))
+                    .on_press(Message::ClearRecent)
+                    .padding(3),
+            );
+        }
+
+        let mut sections = column![favorites].spacing(12);
+        if !self.drives.is_empty() {
+            sections = sections.push(self.create_drive_list());
+        }
+        sections = sections.push(recent_header);
+        if self.show_recent {
+            let mut recent = column![].spacing(4);
+            for path in &self.recent_files {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string());
+
+                // Clicking shows the file in its folder; ↗ opens it
+                let reveal_btn = button(text(format!("📄 {}", name)).size(13))
+                    .on_press(Message::RevealRecent(path.clone()))
+                    .width(Length::Fill)
+                    .padding(5)
+                    .style(|theme: &iced::Theme, status| {
+                        let palette = theme.extended_palette();
+                        button::Appearance {
+                            background: Some(iced::Background::Color(
+                                if matches!(status, button::Status::Hovered) {
+                                    iced::Color::from_rgba8(255, 255, 255, 0.1)
+                                } else {
+                                    iced::Color::TRANSPARENT
+                                }
+                            )),
+                            border: iced::Border::with_radius(4),
+                            text_color: palette.background.base.text,
+                            ..Default::default()
+                        }
+                    });
+
+                let open_btn = button(text("↗").size(12))
+                    .on_press(Message::OpenItem(path.clone()))
+                    .padding(5);
+
+                recent = recent.push(
+                    row![reveal_btn, open_btn]
+                        .spacing(2)
+                        .align_items(alignment::Alignment::Center),
+                );
+            }
+            sections = sections.push(recent);
+        }
+
+        container(scrollable(sections))
+            .width(Length::Fixed(180.0))
+            .height(Length::Fill)
+            .padding(5)
+            .style(|theme: &iced::Theme| {
+                let palette = theme.extended_palette();
+                container::Appearance {
+                    background: Some(iced::Background::Color(palette.background.weak.color)),
+                    border: iced::Border {
+                        color: palette.background.strong.color,
+                        width: 1.0,
+                        radius: 4.0.into(),
+                    },
+                    ..Default::default()
+                }
+            })
             .into()
     }
 
     fn create_content(&self) -> Element<Message> {
-        let mut items_column = column![].spacing(2);
+        let Some(other) = &self.other_pane else {
+            return self.create_listing(&self.items, &self.selected, &self.dir_sizes, &self.filter, true);
+        };
 
-        for (index, item) in self.items.iter().enumerate() {
-            let icon = if item.is_dir { "📁" } else { "📄" };
-            let size_text = if item.is_dir {
+        let focused = column![
+            pane_title(&self.current_path, true),
+            self.create_listing(&self.items, &self.selected, &self.dir_sizes, &self.filter, true),
+        ]
+        .spacing(2)
+        .width(Length::Fill);
+        let unfocused = column![
+            pane_title(&other.current_path, false),
+            self.create_listing(&other.items, &other.selected, &other.dir_sizes, &other.filter, false),
+        ]
+        .spacing(2)
+        .width(Length::Fill);
+
+        let (left, right) = if self.active_pane == 0 {
+            (focused, unfocused)
+        } else {
+            (unfocused, focused)
+        };
+        row![left, right].spacing(5).into()
+    }
+
+    /// Lists one pane's items. Clicking anywhere in an unfocused pane just
+    /// focuses it.
+    fn create_listing<'a>(
+        &'a self,
+        items: &'a [FileItem],
+        selected: &BTreeSet<usize>,
+        dir_sizes: &HashMap<PathBuf, Option<u64>>,
+        filter: &str,
+        focused: bool,
+    ) -> Element<'a, Message> {
+        let density = Density::from_config(&self.config.desktop.density);
+        let mut items_column = column![].spacing(density.padding(2));
+
+        for (index, item) in items.iter().enumerate() {
+            let icon = icon_for(item);
+            let link = match &item.link_target {
+                Some(target) if item.is_dangling => format!(" → {} (missing)", target.display()),
+                Some(target) => format!(" → {}", target.display()),
+                None if item.is_inaccessible => " (inaccessible)".to_string(),
+                None => String::new(),
+            };
+            let text_size = density.text_size(14);
+
+            // The characters the filter matched are drawn in the accent color
+            let matched = fuzzy_match(filter, &item.name).map(|(_, positions)| positions).unwrap_or_default();
+            let mut label = row![text(format!("{} ", icon)).size(text_size)];
+            let mut segment = String::new();
+            let mut segment_matched = false;
+            for (position, ch) in item.name.chars().enumerate() {
+                let is_match = matched.contains(&position);
+                if is_match != segment_matched && !segment.is_empty() {
+                    label = label.push(name_segment(std::mem::take(&mut segment), segment_matched, text_size));
+                }
+                segment_matched = is_match;
+                segment.push(ch);
+            }
+            if !segment.is_empty() {
+                label = label.push(name_segment(segment, segment_matched, text_size));
+            }
+            if !link.is_empty() {
+                label = label.push(text(link).size(text_size));
+            }
+            let size_text = if item.is_dangling {
                 String::new()
+            } else if item.is_inaccessible {
+                "?".to_string()
+            } else if item.is_dir {
+                match dir_sizes.get(&item.path) {
+                    Some(Some(size)) => format_file_size(*size),
+                    Some(None) => "…".to_string(),
+                    None => String::new(),
+                }
             } else {
                 format_file_size(item.size)
             };
 
-            let is_selected = self.selected_index == Some(index);
+            let is_selected = selected.contains(&index);
+
+            // Plain clicks open folders; holding Ctrl/Shift selects them instead
+            let on_press = if !focused {
+                Message::SwitchPane
+            } else if item.is_dir && !self.modifiers.command() && !self.modifiers.shift() {
+                Message::OpenItem(item.path.clone())
+            } else {
+                Message::SelectItem(index)
+            };
 
-            let item_button = button(
+            let item_row = button(
                 row![
-                    text(format!("{} {}", icon, item.name)).size(14),
-                    row![].width(Length::Fill),
-                    text(size_text).size(12).style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
+                    label.width(Length::Fill),
+                    text(size_text)
+                        .size(density.text_size(12))
+                        .width(Length::Fixed(SIZE_COLUMN_WIDTH))
+                        .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
+                    text(item.modified.map(format_modified).unwrap_or_default())
+                        .size(density.text_size(12))
+                        .width(Length::Fixed(MODIFIED_COLUMN_WIDTH))
+                        .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
                 ]
                 .spacing(10)
                 .align_items(alignment::Alignment::Center)
             )
-            .on_press(Message::SelectItem(index))
+            .on_press(on_press)
             .width(Length::Fill)
-            .padding(8)
+            .padding(density.padding(8))
             .style(move |theme: &iced::Theme, status| {
                 let palette = theme.extended_palette();
                 button::Appearance {
                     background: Some(iced::Background::Color(
                         if is_selected {
-                            iced::Color::from_rgba8(100, 150, 255, 0.3)
+                            iced::Color { a: 0.3, ..theme.palette().primary }
                         } else if matches!(status, button::Status::Hovered) {
                             iced::Color::from_rgba8(255, 255, 255, 0.1)
                         } else {
@@ -356,48 +2715,34 @@ impl FileManager {
                 }
             });
 
-            let item_row = if item.is_dir {
-                button(
-                    row![
-                        text(format!("{} {}", icon, item.name)).size(14),
-                        row![].width(Length::Fill),
-                        text(size_text).size(12).style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
-                    ]
-                    .spacing(10)
-                    .align_items(alignment::Alignment::Center)
-                )
-                .on_press(Message::OpenItem(item.path.clone()))
-                .width(Length::Fill)
-                .padding(8)
-                .style(move |theme: &iced::Theme, status| {
-                    let palette = theme.extended_palette();
-                    button::Appearance {
-                        background: Some(iced::Background::Color(
-                            if is_selected {
-                                iced::Color::from_rgba8(100, 150, 255, 0.3)
-                            } else if matches!(status, button::Status::Hovered) {
-                                iced::Color::from_rgba8(255, 255, 255, 0.1)
-                            } else {
-                                iced::Color::TRANSPARENT
-                            }
-                        )),
-                        border: iced::Border::with_radius(4),
-                        text_color: palette.background.base.text,
-                        ..Default::default()
-                    }
-                })
+            items_column = items_column.push(item_row);
+        }
+
+        if items.is_empty() && !(focused && self.error_message.is_some()) {
+            let message = if filter.is_empty() {
+                "This folder is empty".to_string()
             } else {
-                item_button
+                format!("No matches for \"{}\"", filter)
             };
-
-            items_column = items_column.push(item_row);
+            items_column = items_column.push(
+                container(
+                    text(message).size(14)
+                        .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6)))
+                )
+                .width(Length::Fill)
+                .center_x()
+                .padding(20)
+            );
         }
 
         let scrollable_content = scrollable(items_column)
+            .id(if focused { list_scrollable_id() } else { scrollable::Id::new("file-list-other") })
             .width(Length::Fill)
             .height(Length::Fill);
 
-        container(scrollable_content)
+        let list = column![self.create_column_headers(), scrollable_content].spacing(2);
+
+        container(list)
             .width(Length::Fill)
             .height(Length::Fill)
             .padding(5)
@@ -416,67 +2761,715 @@ impl FileManager {
             .into()
     }
 
+    fn create_column_headers(&self) -> Element<Message> {
+        let header = |label: &str, key: SortKey, width: Length| {
+            let arrow = match (self.sort_key == key, self.sort_order) {
+                (false, _) => "",
+                (true, SortOrder::Ascending) => " ▲",
+                (true, SortOrder::Descending) => " ▼",
+            };
+
+            button(text(format!("{}{}", label, arrow)).size(12))
+                .on_press(Message::SortBy(key))
+                .width(width)
+                .padding(0)
+                .style(|theme: &iced::Theme, status| {
+                    let palette = theme.extended_palette();
+                    button::Appearance {
+                        background: Some(iced::Background::Color(iced::Color::TRANSPARENT)),
+                        text_color: if matches!(status, button::Status::Hovered) {
+                            theme.palette().primary
+                        } else {
+                            palette.background.weak.text
+                        },
+                        ..Default::default()
+                    }
+                })
+        };
+
+        // Padding lines the headers up with the row contents below
+        row![
+            header("Name", SortKey::Name, Length::Fill),
+            header("Size", SortKey::Size, Length::Fixed(SIZE_COLUMN_WIDTH)),
+            header("Modified", SortKey::Modified, Length::Fixed(MODIFIED_COLUMN_WIDTH)),
+        ]
+        .spacing(10)
+        .padding([4, 8])
+        .into()
+    }
+
     fn create_status_bar(&self) -> Element<Message> {
         let status_text = if let Some(error) = &self.error_message {
             text(error).style(theme::Text::Color(iced::Color::from_rgb(1.0, 0.4, 0.4)))
+        } else if let Some(message) = &self.status_message {
+            text(message).style(theme::Text::Color(iced::Color::from_rgb(0.4, 0.8, 0.4)))
+        } else if !self.selected.is_empty() {
+            let total: u64 = self
+                .selected
+                .iter()
+                .filter_map(|&index| self.items.get(index))
+                .filter(|item| !item.is_dir)
+                .map(|item| item.size)
+                .sum();
+            let count = self.selected.len();
+            text(format!(
+                "{} {} selected, {} total",
+                count,
+                if count == 1 { "item" } else { "items" },
+                format_file_size(total)
+            ))
+            .style(theme::Text::Color(iced::Color::from_rgb(0.7, 0.7, 0.7)))
         } else {
             text(format!("{} items", self.items.len()))
                 .style(theme::Text::Color(iced::Color::from_rgb(0.7, 0.7, 0.7)))
         };
 
-        container(status_text.size(12))
-            .padding(5)
-            .width(Length::Fill)
-            .into()
+        let Some(operation) = &self.operation else {
+            return container(status_text.size(12))
+                .padding(5)
+                .width(Length::Fill)
+                .into();
+        };
+
+        // Bytes give a smoother bar, but moves to the trash only count items
+        let progress = operation.progress;
+        let (done, total) = if progress.total_bytes > 0 {
+            (progress.bytes, progress.total_bytes)
+        } else {
+            (progress.items, progress.total_items)
+        };
+        let detail = if progress.total_bytes > 0 {
+            format!(
+                "{} of {}",
+                format_file_size(progress.bytes),
+                format_file_size(progress.total_bytes)
+            )
+        } else {
+            format!("{} of {} items", progress.items, progress.total_items)
+        };
+
+        container(
+            row![
+                text(format!("{}... {}", operation.label, detail)).size(12),
+                progress_bar(0.0..=total.max(1) as f32, done as f32)
+                    .height(Length::Fixed(8.0))
+                    .width(Length::Fill),
+                button(text("Cancel").size(12))
+                    .on_press(Message::CancelOperation)
+                    .padding(3),
+            ]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center),
+        )
+        .padding(5)
+        .width(Length::Fill)
+        .into()
+    }
+
+    /// Re-reads the current directory, reporting failures in the status bar.
+    fn reload(&mut self) {
+        let path = self.current_path.clone();
+        if let Err(e) = self.load_directory(path) {
+            self.error_message = Some(format!("Error: {}", e));
+        }
     }
 
-    fn load_directory(&mut self, path: PathBuf) {
-        self.items.clear();
+    /// Lists `path` into `self.items`. If it can't be read the previous
+    /// listing is left untouched.
+    fn load_directory(&mut self, path: PathBuf) -> Result<(), String> {
+        self.items = read_directory(&path)?;
+        if !self.filter.is_empty() {
+            let filter = &self.filter;
+            self.items.retain(|item| fuzzy_match(filter, &item.name).is_some());
+        }
+        self.sort_items();
+        Ok(())
+    }
 
-        if let Ok(entries) = fs::read_dir(&path) {
-            for entry in entries.flatten() {
-                if let Ok(metadata) = entry.metadata() {
-                    let name = entry.file_name().to_string_lossy().to_string();
+    /// Sorts by the active column, always keeping directories first. While
+    /// filtering, the best matches come first instead.
+    fn sort_items(&mut self) {
+        if !self.filter.is_empty() {
+            let filter = &self.filter;
+            self.items.sort_by_cached_key(|item| {
+                let score = fuzzy_match(filter, &item.name).map_or(0, |(score, _)| score);
+                (std::cmp::Reverse(score), item.name.to_lowercase())
+            });
+            return;
+        }
 
-                    // Skip hidden files unless configured to show them
-                    if name.starts_with('.') {
-                        continue;
-                    }
+        let key = self.sort_key;
+        let order = self.sort_order;
 
-                    self.items.push(FileItem {
-                        name,
-                        path: entry.path(),
-                        is_dir: metadata.is_dir(),
-                        size: metadata.len(),
-                    });
+        self.items.sort_by(|a, b| {
+            match (a.is_dir, b.is_dir) {
+                (true, false) => return std::cmp::Ordering::Less,
+                (false, true) => return std::cmp::Ordering::Greater,
+                _ => {}
+            }
+
+            let by_name = a.name.to_lowercase().cmp(&b.name.to_lowercase());
+            let ordering = match key {
+                SortKey::Name => by_name,
+                SortKey::Size => a.size.cmp(&b.size).then(by_name),
+                SortKey::Modified => a.modified.cmp(&b.modified).then(by_name),
+            };
+
+            match order {
+                SortOrder::Ascending => ordering,
+                SortOrder::Descending => ordering.reverse(),
+            }
+        });
+    }
+}
+
+/// The folder a pane shows, highlighted on the focused pane.
+fn pane_title<'a>(path: &Path, focused: bool) -> Element<'a, Message> {
+    let color = if focused {
+        iced::Color::from_rgb(0.4, 0.6, 1.0)
+    } else {
+        iced::Color::from_rgb(0.6, 0.6, 0.6)
+    };
+    text(path.display().to_string())
+        .size(12)
+        .style(theme::Text::Color(color))
+        .into()
+}
+
+/// Formats a modification time as a UTC `YYYY-MM-DD HH:MM` timestamp.
+fn format_modified(time: SystemTime) -> String {
+    match utc_date_time(time) {
+        Some((year, month, day, day_secs)) => format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            year,
+            month,
+            day,
+            day_secs / 3_600,
+            day_secs % 3_600 / 60
+        ),
+        None => String::new(),
+    }
+}
+
+/// Splits a time into its UTC year, month, day and seconds into the day,
+/// or `None` if it's before the epoch.
+fn utc_date_time(time: SystemTime) -> Option<(i64, i64, i64, u64)> {
+    let secs = time.duration_since(SystemTime::UNIX_EPOCH).ok()?.as_secs();
+    let (days, day_secs) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Days since the epoch to a proleptic Gregorian date
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    Some((year, month, day, day_secs))
+}
+
+/// Quiet period after a change before refreshing, so a burst of events
+/// (e.g. a large copy) causes a single reload.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Emits `RefreshView` whenever the contents of `path` change. The
+/// subscription is keyed on the path, so navigating elsewhere drops the old
+/// watcher and starts a new one.
+fn watch_directory(path: PathBuf) -> Subscription<Message> {
+    subscription::channel(path.clone(), 10, move |mut output| async move {
+        let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if event.is_ok() {
+                let _ = sender.send(());
+            }
+        })
+        .and_then(|mut watcher| {
+            watcher.watch(&path, notify::RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        });
+
+        match watcher {
+            // Keep the watcher alive for as long as the subscription runs
+            Ok(_watcher) => {
+                while events.recv().await.is_some() {
+                    while tokio::time::timeout(WATCH_DEBOUNCE, events.recv()).await.is_ok() {}
+                    let _ = output.send(Message::RefreshView).await;
                 }
             }
+            Err(e) => log::warn!("Failed to watch {}: {}", path.display(), e),
         }
 
-        // Sort: directories first, then alphabetically
-        self.items.sort_by(|a, b| {
-            match (a.is_dir, b.is_dir) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        loop {
+            iced::futures::future::pending::<()>().await;
+        }
+    })
+}
+
+fn name_segment(segment: String, matched: bool, size: u16) -> Element<'static, Message> {
+    if matched {
+        text(segment)
+            .size(size)
+            .style(theme::Text::Color(iced::Color::from_rgb(0.4, 0.6, 1.0)))
+            .into()
+    } else {
+        text(segment).size(size).into()
+    }
+}
+
+/// Turns a leading `~` into the home folder.
+fn expand_home(input: &str) -> PathBuf {
+    match (input.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(input),
+    }
+}
+
+/// Folders whose names start with the last component of `input`, as full
+/// paths ending in `/`. Hidden folders are only offered once a `.` is typed.
+fn complete_folder(input: &str) -> Vec<String> {
+    let Some(split) = input.rfind('/') else { return Vec::new() };
+    let (parent, prefix) = input.split_at(split + 1);
+    let Ok(entries) = fs::read_dir(expand_home(parent)) else { return Vec::new() };
+
+    let mut suggestions: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.')))
+        .map(|name| format!("{}{}/", parent, name))
+        .collect();
+    suggestions.sort();
+    suggestions.truncate(MAX_GO_TO_SUGGESTIONS);
+    suggestions
+}
+
+/// Lists filesystems on removable or hot-plugged devices.
+fn read_drives() -> Result<Vec<Drive>, String> {
+    let output = std::process::Command::new("lsblk")
+        .args(["--json", "--output", "PATH,NAME,LABEL,SIZE,FSTYPE,MOUNTPOINT,RM,HOTPLUG"])
+        .output()
+        .map_err(|e| format!("Cannot run lsblk: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    parse_lsblk(&String::from_utf8_lossy(&output.stdout))
+}
+
+async fn list_drives() -> Result<Vec<Drive>, String> {
+    tokio::task::spawn_blocking(read_drives)
+        .await
+        .map_err(|e| format!("Drive listing failed: {}", e))?
+}
+
+/// Picks the removable filesystems out of `lsblk --json` output.
+/// Partitions inherit removability from their disk, since some devices
+/// only flag the disk itself.
+fn parse_lsblk(json: &str) -> Result<Vec<Drive>, String> {
+    // `rm` and `hotplug` are booleans in newer util-linux and "0"/"1" in
+    // older releases
+    fn flag(device: &serde_json::Value, key: &str) -> bool {
+        match &device[key] {
+            serde_json::Value::Bool(value) => *value,
+            serde_json::Value::String(value) => value == "1",
+            _ => false,
+        }
+    }
+
+    fn walk(device: &serde_json::Value, removable: bool, drives: &mut Vec<Drive>) {
+        let removable = removable || flag(device, "rm") || flag(device, "hotplug");
+        let fstype = device["fstype"].as_str().unwrap_or("");
+        if removable && !fstype.is_empty() && fstype != "swap" {
+            if let Some(path) = device["path"].as_str() {
+                let name = device["name"].as_str().unwrap_or(path);
+                drives.push(Drive {
+                    device: path.to_string(),
+                    label: device["label"].as_str().unwrap_or(name).to_string(),
+                    size: device["size"].as_str().unwrap_or("").to_string(),
+                    mount_point: device["mountpoint"].as_str().map(PathBuf::from),
+                });
             }
+        }
+        for child in device["children"].as_array().into_iter().flatten() {
+            walk(child, removable, drives);
+        }
+    }
+
+    let listing: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| format!("Cannot parse lsblk output: {}", e))?;
+    let mut drives = Vec::new();
+    for device in listing["blockdevices"].as_array().into_iter().flatten() {
+        walk(device, false, &mut drives);
+    }
+    Ok(drives)
+}
+
+/// Mounts `device` with udisks and returns where it was mounted.
+async fn mount_drive(device: String) -> Result<PathBuf, String> {
+    let output = tokio::process::Command::new("udisksctl")
+        .args(["mount", "--block-device", &device, "--no-user-interaction"])
+        .output()
+        .await
+        .map_err(|e| format!("Cannot run udisksctl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Cannot mount {}: {}",
+            device,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // Prints "Mounted /dev/sdb1 at /media/user/LABEL", with a trailing
+    // period in older versions
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if let Some((_, mount_point)) = stdout.trim().split_once(" at ") {
+        return Ok(PathBuf::from(mount_point.trim_end_matches('.')));
+    }
+    list_drives()
+        .await?
+        .into_iter()
+        .find(|drive| drive.device == device)
+        .and_then(|drive| drive.mount_point)
+        .ok_or_else(|| format!("Mounted {}, but can't tell where", device))
+}
+
+async fn unmount_drive(device: String) -> Result<(), String> {
+    let output = tokio::process::Command::new("udisksctl")
+        .args(["unmount", "--block-device", &device, "--no-user-interaction"])
+        .output()
+        .await
+        .map_err(|e| format!("Cannot run udisksctl: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Cannot unmount {}: {}",
+            device,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Extensions treated as scripts even without the executable bit.
+const SCRIPT_EXTENSIONS: &[&str] = &["sh", "bash", "zsh", "py", "pl", "rb"];
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Whether opening `path` would run code rather than show a document.
+fn is_runnable(path: &Path) -> bool {
+    let is_script = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SCRIPT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    is_script || is_executable(path)
+}
+
+/// Starts `command` without waiting for it, with its output discarded.
+fn spawn_detached(mut command: std::process::Command) -> io::Result<()> {
+    let mut child = command
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    // Reap the child in the background so it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Key for remembering "Open With…" choices: the lowercased extension, or
+/// an empty string for files without one.
+fn extension_key(path: &Path) -> String {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn open_with_file() -> Option<PathBuf> {
+    Config::state_dir().map(|dir| dir.join("open_with.json"))
+}
+
+fn load_open_with_last() -> BTreeMap<String, String> {
+    open_with_file()
+        .and_then(|file| fs::read_to_string(file).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_open_with_last(choices: &BTreeMap<String, String>) -> io::Result<()> {
+    let file = open_with_file()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(file, serde_json::to_string(choices)?)
+}
+
+fn recent_files_file() -> Option<PathBuf> {
+    Config::state_dir().map(|dir| dir.join("recent_files"))
+}
+
+/// Reads the recent files, NUL-separated raw bytes like `last_path`,
+/// dropping any that no longer exist.
+fn load_recent_files() -> Vec<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Some(contents) = recent_files_file().and_then(|file| fs::read(file).ok()) else {
+        return Vec::new();
+    };
+    contents
+        .split(|&byte| byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(std::ffi::OsStr::from_bytes(path)))
+        .filter(|path| path.is_file())
+        .take(MAX_RECENT_FILES)
+        .collect()
+}
+
+fn save_recent_files(paths: &[PathBuf]) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let file = recent_files_file()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents: Vec<u8> = paths
+        .iter()
+        .flat_map(|path| path.as_os_str().as_bytes().iter().copied().chain([0]))
+        .collect();
+    fs::write(file, contents)
+}
+
+fn last_path_file() -> Option<PathBuf> {
+    Config::state_dir().map(|dir| dir.join("last_path"))
+}
+
+// Stored as raw bytes so non-UTF-8 paths round-trip
+fn load_last_path() -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+
+    let contents = fs::read(last_path_file()?).ok()?;
+    (!contents.is_empty()).then(|| PathBuf::from(std::ffi::OsString::from_vec(contents)))
+}
+
+fn save_last_path(path: &Path) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let file = last_path_file()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(file, path.as_os_str().as_bytes())
+}
+
+/// Copies `src` to `dst`, recursively for folders, returning an error
+/// message for each entry that couldn't be read and was left out. A
+/// cancelled copy leaves whatever was copied so far in place.
+fn copy_path(src: &Path, dst: &Path, tracker: &mut Tracker) -> io::Result<Vec<String>> {
+    tracker.measure(src);
+    let report = fs_ops::copy_dir_recursive_with(src, dst, |bytes| tracker.advance(bytes))?;
+    Ok(report
+        .skipped
+        .iter()
+        .map(|(path, e)| format!("{}: {}", path.display(), e))
+        .collect())
+}
+
+fn transfer(op: ClipboardOp, src: &Path, dst: &Path, tracker: &mut Tracker) -> io::Result<Vec<String>> {
+    match op {
+        ClipboardOp::Copy => copy_path(src, dst, tracker),
+        ClipboardOp::Cut => move_path(src, dst, tracker),
+    }
+}
+
+fn move_path(src: &Path, dst: &Path, tracker: &mut Tracker) -> io::Result<Vec<String>> {
+    // rename fails across filesystems, so fall back to copy + delete
+    if fs::rename(src, dst).is_ok() {
+        return Ok(Vec::new());
+    }
+    let mut skipped = copy_path(src, dst, tracker)?;
+    // Deleting the source would lose whatever couldn't be copied
+    if skipped.is_empty() {
+        remove_path(src)?;
+    } else {
+        skipped.push(format!("{}: not everything could be copied, so it was kept", src.display()));
+    }
+    Ok(skipped)
+}
+
+/// Returns the folder name an archive extracts into, or `None` if the
+/// extension isn't a supported archive format.
+fn archive_stem(path: &Path) -> Option<std::ffi::OsString> {
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+    // Compared as bytes so archives with non-UTF-8 names still extract
+    let name = path.file_name()?.as_bytes();
+    let lower = name.to_ascii_lowercase();
+    [".tar.gz", ".tgz", ".zip"]
+        .iter()
+        .map(|ext| ext.as_bytes())
+        .find(|ext| lower.ends_with(ext) && lower.len() > ext.len())
+        .map(|ext| std::ffi::OsString::from_vec(name[..name.len() - ext.len()].to_vec()))
+}
+
+async fn extract_archive(archive: PathBuf) -> Result<PathBuf, String> {
+    tokio::task::spawn_blocking(move || {
+        let stem = archive_stem(&archive)
+            .ok_or_else(|| format!("Unsupported archive: {}", archive.display()))?;
+        let dest = archive.with_file_name(stem);
+        fs::create_dir_all(&dest).map_err(|e| e.to_string())?;
+
+        let file = fs::File::open(&archive).map_err(|e| e.to_string())?;
+        if archive.to_string_lossy().to_lowercase().ends_with(".zip") {
+            zip::ZipArchive::new(file)
+                .and_then(|mut zip| zip.extract(&dest))
+                .map_err(|e| e.to_string())?;
+        } else {
+            tar::Archive::new(flate2::read::GzDecoder::new(file))
+                .unpack(&dest)
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(dest)
+    })
+    .await
+    .map_err(|e| format!("Extraction task failed: {}", e))?
+}
+
+/// Total size of the files under `path`. Entries that can't be read are
+/// skipped rather than failing the whole walk.
+async fn dir_size(path: PathBuf) -> u64 {
+    tokio::task::spawn_blocking(move || {
+        WalkDir::new(&path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| entry.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    })
+    .await
+    .unwrap_or(0)
+}
+
+/// Moves `path` into the user's trash as laid out by the freedesktop.org
+/// trash spec, so other file managers can list and restore it.
+fn move_to_trash(path: &Path) -> io::Result<()> {
+    let trash = dirs::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no trash folder"))?
+        .join("Trash");
+    let files = trash.join("files");
+    let info = trash.join("info");
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "nothing to trash"))?;
+    let mut destination = files.join(name);
+    if destination.exists() {
+        destination = fs_ops::unique_name(&destination);
+    }
+    let mut info_name = destination.file_name().unwrap_or(name).to_os_string();
+    info_name.push(".trashinfo");
+    let info_path = info.join(info_name);
+
+    let original = match path.parent() {
+        Some(parent) => fs::canonicalize(parent)?.join(name),
+        None => path.to_path_buf(),
+    };
+    let deleted = match utc_date_time(SystemTime::now()) {
+        Some((year, month, day, day_secs)) => format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            day_secs / 3_600,
+            day_secs % 3_600 / 60,
+            day_secs % 60
+        ),
+        None => String::new(),
+    };
+    fs::write(
+        &info_path,
+        format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            percent_encode_path(&original),
+            deleted
+        ),
+    )?;
+
+    if let Err(e) = fs::rename(path, &destination) {
+        let _ = fs::remove_file(&info_path);
+        // EXDEV: the trash lives on another filesystem
+        return Err(if e.raw_os_error() == Some(18) {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "can't move to the trash from another filesystem, use Shift+Delete to delete permanently",
+            )
+        } else {
+            e
         });
     }
+    Ok(())
 }
 
-fn format_file_size(size: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
-    let mut size = size as f64;
-    let mut unit_index = 0;
+/// Percent-encodes a path for a `.trashinfo` file, leaving `/` and
+/// unreserved characters as they are.
+fn percent_encode_path(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
 
-    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
-        size /= 1024.0;
-        unit_index += 1;
+    path.as_os_str()
+        .as_bytes()
+        .iter()
+        .map(|&byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Deletes `path` entry by entry, deepest first, so progress can be
+/// reported and a cancel takes effect partway through.
+fn remove_tree(path: &Path, tracker: &mut Tracker) -> io::Result<()> {
+    if !path.is_dir() || path.is_symlink() {
+        let bytes = fs::symlink_metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+        remove_path(path)?;
+        return tracker.advance(bytes);
+    }
+
+    for entry in WalkDir::new(path).contents_first(true) {
+        let entry = entry.map_err(io::Error::from)?;
+        if entry.file_type().is_dir() {
+            fs::remove_dir(entry.path())?;
+            tracker.advance(0)?;
+        } else {
+            let bytes = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            fs::remove_file(entry.path())?;
+            tracker.advance(bytes)?;
+        }
     }
+    Ok(())
+}
 
-    if unit_index == 0 {
-        format!("{} {}", size as u64, UNITS[unit_index])
+fn remove_path(path: &Path) -> io::Result<()> {
+    // Deleting a symlink removes the link, never what it points at
+    if path.is_dir() && !path.is_symlink() {
+        fs::remove_dir_all(path)
     } else {
-        format!("{:.1} {}", size, UNITS[unit_index])
+        fs::remove_file(path)
     }
 }
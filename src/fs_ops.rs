@@ -0,0 +1,571 @@
+//! Filesystem operations behind the file manager's paste, move and batch
+//! rename, without any UI, so they can be reused by other tools.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// What a recursive copy did.
+#[derive(Debug, Default)]
+pub struct CopyReport {
+    /// Files and symlinks copied.
+    pub files: u64,
+    /// Bytes of file contents copied.
+    pub bytes: u64,
+    /// Source entries that couldn't be read, and so weren't copied.
+    pub skipped: Vec<(PathBuf, io::Error)>,
+}
+
+/// Copies `src` to `dst`, recursing into folders. See
+/// `copy_dir_recursive_with`.
+pub fn copy_dir_recursive(src: &Path, dst: &Path) -> io::Result<CopyReport> {
+    copy_dir_recursive_with(src, dst, |_| Ok(()))
+}
+
+/// Copies `src` to `dst`, which may also be a single file or symlink.
+/// `dst` can't be inside `src`, which would copy the copy over and over.
+///
+/// Permissions and modification times are kept, except the times of
+/// symlinks, and symlinks are copied as links rather than followed.
+/// Anything under `src` that can't be read is skipped and listed in the
+/// report, as are sockets, FIFOs and devices. Only a failure to write to
+/// `dst` or to read `src` itself stops the copy.
+///
+/// `on_entry` is called with the bytes copied after each entry, skipped
+/// ones included, and the copy stops with its error if it returns one,
+/// leaving whatever was copied so far in place.
+pub fn copy_dir_recursive_with(
+    src: &Path,
+    dst: &Path,
+    mut on_entry: impl FnMut(u64) -> io::Result<()>,
+) -> io::Result<CopyReport> {
+    if fs::symlink_metadata(src)?.is_dir() && resolve(dst).starts_with(fs::canonicalize(src)?) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("cannot copy {} into itself", src.display()),
+        ));
+    }
+
+    let mut report = CopyReport::default();
+    // Folder metadata is applied last, so copying their contents doesn't
+    // bump the times and read-only folders can still be filled
+    let mut folders = Vec::new();
+
+    for entry in WalkDir::new(src).follow_root_links(false) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if e.depth() == 0 => return Err(e.into()),
+            Err(e) => {
+                let path = e.path().unwrap_or(src).to_path_buf();
+                report.skipped.push((path, e.into()));
+                on_entry(0)?;
+                continue;
+            }
+        };
+        let relative = entry.path().strip_prefix(src).unwrap_or(entry.path());
+        let target = if relative.as_os_str().is_empty() {
+            dst.to_path_buf()
+        } else {
+            dst.join(relative)
+        };
+
+        // Only reading the source may fail without stopping the copy
+        let source = entry.metadata().map_err(io::Error::from).and_then(|metadata| {
+            let file_type = metadata.file_type();
+            let source = if file_type.is_dir() {
+                Source::Folder
+            } else if file_type.is_symlink() {
+                Source::Link(fs::read_link(entry.path())?)
+            } else if file_type.is_file() {
+                Source::File(File::open(entry.path())?)
+            } else {
+                return Err(io::Error::other("special file, not copied"));
+            };
+            Ok((source, metadata))
+        });
+        let (source, metadata) = match source {
+            Ok(source) => source,
+            Err(e) if entry.depth() == 0 => return Err(e),
+            Err(e) => {
+                report.skipped.push((entry.path().to_path_buf(), e));
+                on_entry(0)?;
+                continue;
+            }
+        };
+
+        let bytes = match source {
+            Source::Folder => {
+                fs::create_dir_all(&target)?;
+                folders.push((target, metadata));
+                0
+            }
+            Source::Link(link) => {
+                std::os::unix::fs::symlink(link, &target)?;
+                report.files += 1;
+                0
+            }
+            Source::File(reader) => {
+                let bytes = copy_file(reader, &target, &metadata)?;
+                report.files += 1;
+                bytes
+            }
+        };
+        report.bytes += bytes;
+        on_entry(bytes)?;
+    }
+
+    for (folder, metadata) in folders.iter().rev() {
+        if let Ok(modified) = metadata.modified() {
+            File::open(folder)?.set_modified(modified)?;
+        }
+        fs::set_permissions(folder, metadata.permissions())?;
+    }
+    Ok(report)
+}
+
+/// Returns `path` with " (n)" appended to the stem, using the first `n`
+/// that doesn't exist yet.
+pub fn unique_name(path: &Path) -> PathBuf {
+    unique_name_where(path, |candidate| !candidate.exists())
+}
+
+/// Like `unique_name`, using the first `n` whose name `is_free` accepts.
+pub fn unique_name_where(path: &Path, is_free: impl Fn(&Path) -> bool) -> PathBuf {
+    // Built from OsStr pieces so names that aren't UTF-8 keep their bytes
+    let candidate = |n: usize| {
+        let mut name = path.file_stem().unwrap_or_default().to_os_string();
+        name.push(format!(" ({})", n));
+        if let Some(extension) = path.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+        path.with_file_name(name)
+    };
+
+    (1..)
+        .map(candidate)
+        .find(|candidate| is_free(candidate))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// One file of a batch rename, as worked out by `plan_renames`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedRename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// Why the file can't be renamed to `to`, if it can't.
+    pub problem: Option<String>,
+}
+
+impl PlannedRename {
+    pub fn is_unchanged(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+/// Works out the new name of each of `paths` from `pattern`, where
+/// `{name}` is the name without its extension, `{ext}` the extension and
+/// `{n}` the position in `paths`, counting from 1 and zero-padded so they
+/// all have the same width. `.{ext}` is left out for names without an
+/// extension, and folders are taken as having none.
+///
+/// Nothing is renamed. A new name that is empty, contains a `/`, is taken
+/// by another file or would be given to two of them is reported in that
+/// entry's `problem`.
+pub fn plan_renames(paths: &[PathBuf], pattern: &str) -> Vec<PlannedRename> {
+    let width = paths.len().to_string().len();
+    let mut plan: Vec<PlannedRename> = Vec::with_capacity(paths.len());
+    for (index, from) in paths.iter().enumerate() {
+        let name = expand_rename_pattern(pattern, from, index + 1, width);
+        let to = from.with_file_name(&name);
+        let problem = if name.is_empty() {
+            Some("The new name is empty".to_string())
+        } else if name == "." || name == ".." {
+            Some(format!("\"{}\" can't be used as a name", name.to_string_lossy()))
+        } else if name.as_encoded_bytes().contains(&b'/') {
+            Some("Names can't contain /".to_string())
+        } else if let Some(other) = plan.iter().find(|other| other.to == to) {
+            Some(format!("Same new name as {}", display_name(&other.from)))
+        } else if to != *from && fs::symlink_metadata(&to).is_ok() {
+            Some(format!("{} already exists", name.to_string_lossy()))
+        } else {
+            None
+        };
+        plan.push(PlannedRename { from: from.clone(), to, problem });
+    }
+    plan
+}
+
+/// Renames the files of `plan` whose names change, in order, and returns
+/// how many. Existing files are never replaced. If a rename fails, the
+/// files renamed before it are put back, and the error says which one
+/// failed and whether they all could be.
+pub fn rename_all(plan: &[PlannedRename]) -> io::Result<usize> {
+    let mut done: Vec<&PlannedRename> = Vec::new();
+    for rename in plan.iter().filter(|rename| !rename.is_unchanged()) {
+        // fs::rename replaces an existing file, so check again in case one
+        // appeared since planning
+        let result = match &rename.problem {
+            Some(problem) => Err(io::Error::new(io::ErrorKind::InvalidInput, problem.clone())),
+            None if fs::symlink_metadata(&rename.to).is_ok() => {
+                Err(io::Error::new(io::ErrorKind::AlreadyExists, "the new name is taken"))
+            }
+            None => fs::rename(&rename.from, &rename.to),
+        };
+        let Err(e) = result else {
+            done.push(rename);
+            continue;
+        };
+
+        let failed_to_restore: Vec<String> = done
+            .iter()
+            .rev()
+            .filter_map(|earlier| {
+                let restored = fs::rename(&earlier.to, &earlier.from);
+                restored.err().map(|e| format!("{} ({})", earlier.to.display(), e))
+            })
+            .collect();
+        let mut message = format!(
+            "renaming {} to {} failed: {}",
+            display_name(&rename.from),
+            display_name(&rename.to),
+            e
+        );
+        if !failed_to_restore.is_empty() {
+            message.push_str(&format!(
+                ", and {} of {} renamed before it couldn't be put back: {}",
+                failed_to_restore.len(),
+                files(done.len()),
+                failed_to_restore.join(", ")
+            ));
+        } else if !done.is_empty() {
+            let were = if done.len() == 1 { "was" } else { "were" };
+            message.push_str(&format!(", so {} renamed before it {} put back", files(done.len()), were));
+        }
+        return Err(io::Error::new(e.kind(), message));
+    }
+    Ok(done.len())
+}
+
+fn expand_rename_pattern(pattern: &str, path: &Path, n: usize, width: usize) -> OsString {
+    let (stem, extension) = if path.is_dir() {
+        (path.file_name(), None)
+    } else {
+        (path.file_stem(), path.extension())
+    };
+    let mut name = OsString::new();
+    let mut rest = pattern;
+    while let Some(ch) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("{name}") {
+            name.push(stem.unwrap_or_default());
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix(".{ext}") {
+            if let Some(extension) = extension {
+                name.push(".");
+                name.push(extension);
+            }
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{ext}") {
+            name.push(extension.unwrap_or_default());
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{n}") {
+            name.push(format!("{:0width$}", n));
+            rest = after;
+        } else {
+            name.push(&rest[..ch.len_utf8()]);
+            rest = &rest[ch.len_utf8()..];
+        }
+    }
+    name
+}
+
+fn files(count: usize) -> String {
+    if count == 1 {
+        "1 file".to_string()
+    } else {
+        format!("{} files", count)
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// `path` with symlinks resolved as far as it exists, for comparing paths
+/// that may not have been created yet.
+fn resolve(path: &Path) -> PathBuf {
+    for ancestor in path.ancestors() {
+        if let Ok(resolved) = fs::canonicalize(ancestor) {
+            let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            return resolved.join(rest);
+        }
+    }
+    path.to_path_buf()
+}
+
+/// An entry of the source tree, opened for copying.
+enum Source {
+    Folder,
+    /// A symlink and the path it points at.
+    Link(PathBuf),
+    File(File),
+}
+
+/// Copies a regular file's contents, then its modification time and
+/// permissions. A file that fails partway is removed again.
+fn copy_file(mut reader: File, dst: &Path, metadata: &fs::Metadata) -> io::Result<u64> {
+    let mut writer = File::create(dst)?;
+    let copied = io::copy(&mut reader, &mut writer).and_then(|bytes| {
+        if let Ok(modified) = metadata.modified() {
+            writer.set_modified(modified)?;
+        }
+        Ok(bytes)
+    });
+    drop(writer);
+
+    match copied {
+        Ok(bytes) => {
+            fs::set_permissions(dst, metadata.permissions())?;
+            Ok(bytes)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(dst);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn copies_files_folders_and_nested_symlinks() {
+        let temp = TempDir::new("nested");
+        let src = temp.0.join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), "alpha").unwrap();
+        fs::write(src.join("sub/b.txt"), "beta").unwrap();
+        symlink("../a.txt", src.join("sub/link")).unwrap();
+        fs::set_permissions(src.join("a.txt"), fs::Permissions::from_mode(0o640)).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options()
+            .write(true)
+            .open(src.join("a.txt"))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let dst = temp.0.join("dst");
+        let report = copy_dir_recursive(&src, &dst).unwrap();
+
+        assert_eq!(report.files, 3);
+        assert_eq!(report.bytes, 9);
+        assert!(report.skipped.is_empty());
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "alpha");
+        assert_eq!(fs::read_to_string(dst.join("sub/b.txt")).unwrap(), "beta");
+
+        let link = dst.join("sub/link");
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("../a.txt"));
+
+        let metadata = fs::metadata(dst.join("a.txt")).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+        assert_eq!(metadata.modified().unwrap(), modified);
+    }
+
+    #[test]
+    fn symlinked_root_is_copied_as_a_link() {
+        let temp = TempDir::new("root-link");
+        let target = temp.0.join("target");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("file"), "contents").unwrap();
+        let src = temp.0.join("link");
+        symlink(&target, &src).unwrap();
+
+        let dst = temp.0.join("copy");
+        let report = copy_dir_recursive(&src, &dst).unwrap();
+
+        assert_eq!(report.files, 1);
+        assert_eq!(report.bytes, 0);
+        assert!(fs::symlink_metadata(&dst).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_link(&dst).unwrap(), target);
+    }
+
+    #[test]
+    fn destination_inside_source_is_rejected() {
+        let temp = TempDir::new("into-itself");
+        let src = temp.0.join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("file"), "contents").unwrap();
+
+        for dst in [src.join("sub/copy"), src.clone(), temp.0.join("./src/../src/new")] {
+            let error = copy_dir_recursive(&src, &dst).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+        assert!(!src.join("sub/copy").exists());
+        assert!(!src.join("new").exists());
+
+        // Through a symlink to the source too
+        let alias = temp.0.join("alias");
+        symlink(&src, &alias).unwrap();
+        let error = copy_dir_recursive(&src, &alias.join("copy")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    fn new_names(plan: &[PlannedRename]) -> Vec<String> {
+        plan.iter()
+            .map(|rename| rename.to.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn rename_pattern_tokens_are_expanded() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp = TempDir::new("pattern");
+        let names = [
+            OsStr::new("photo.jpg"),
+            OsStr::new("notes"),
+            OsStr::new("archive.tar.gz"),
+            OsStr::new(".hidden"),
+            OsStr::from_bytes(b"caf\xe9.txt"),
+            OsStr::new("album.d"),
+        ];
+        let paths: Vec<PathBuf> = names.iter().map(|name| temp.0.join(name)).collect();
+        for path in &paths[..5] {
+            fs::write(path, "").unwrap();
+        }
+        fs::create_dir(&paths[5]).unwrap();
+
+        let plan = plan_renames(&paths, "{n}-{name}.{ext}");
+        assert_eq!(
+            new_names(&plan),
+            ["1-photo.jpg", "2-notes", "3-archive.tar.gz", "4-.hidden", "5-caf\u{fffd}.txt", "6-album.d"]
+        );
+        assert!(plan.iter().all(|rename| rename.problem.is_none()));
+        // The name's bytes are kept as they are
+        assert_eq!(plan[4].to.file_name().unwrap().as_bytes(), b"5-caf\xe9.txt");
+
+        let plan = plan_renames(&paths[..2], "{ext}_{name} {unknown}");
+        assert_eq!(new_names(&plan), ["jpg_photo {unknown}", "_notes {unknown}"]);
+    }
+
+    #[test]
+    fn sequence_numbers_are_padded_to_the_same_width() {
+        let paths: Vec<PathBuf> = (0..12).map(|i| PathBuf::from(format!("/nonexistent/{}", i))).collect();
+        let names = new_names(&plan_renames(&paths, "img{n}"));
+        assert_eq!(names[0], "img01");
+        assert_eq!(names[11], "img12");
+    }
+
+    #[test]
+    fn bad_new_names_are_reported() {
+        let temp = TempDir::new("problems");
+        for name in ["a.txt", "b.txt", "taken"] {
+            fs::write(temp.0.join(name), "").unwrap();
+        }
+        let paths = [temp.0.join("a.txt"), temp.0.join("b.txt")];
+        let problem = |pattern| {
+            plan_renames(&paths, pattern)
+                .into_iter()
+                .map(|rename| rename.problem)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(problem("{name}.{ext}"), [None, None]);
+        assert_eq!(problem("copy"), [None, Some("Same new name as a.txt".to_string())]);
+        assert!(problem("taken")[0].as_ref().unwrap().contains("already exists"));
+        assert!(problem("")[0].is_some());
+        assert!(problem("..")[0].is_some());
+        assert!(problem("sub/{name}")[0].is_some());
+    }
+
+    #[test]
+    fn renames_are_applied() {
+        let temp = TempDir::new("apply");
+        let paths = [temp.0.join("a.txt"), temp.0.join("b.txt"), temp.0.join("c.md")];
+        for path in &paths {
+            fs::write(path, path.file_name().unwrap().as_encoded_bytes()).unwrap();
+        }
+
+        let plan = plan_renames(&paths, "{n}.{ext}");
+        assert_eq!(rename_all(&plan).unwrap(), 3);
+        assert_eq!(fs::read_to_string(temp.0.join("1.txt")).unwrap(), "a.txt");
+        assert_eq!(fs::read_to_string(temp.0.join("3.md")).unwrap(), "c.md");
+        assert!(!paths[0].exists());
+
+        // Unchanged names are skipped
+        let plan = plan_renames(&[temp.0.join("1.txt")], "{name}.{ext}");
+        assert_eq!(rename_all(&plan).unwrap(), 0);
+    }
+
+    #[test]
+    fn failed_batch_is_put_back() {
+        let temp = TempDir::new("rollback");
+        let paths = [temp.0.join("a"), temp.0.join("b"), temp.0.join("c")];
+        for path in &paths {
+            fs::write(path, "").unwrap();
+        }
+        let plan = plan_renames(&paths, "new-{n}");
+        // Taken after the plan was checked, so the third rename fails
+        fs::write(temp.0.join("new-3"), "someone else's").unwrap();
+
+        let error = rename_all(&plan).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert!(error.to_string().contains("2 files renamed before it were put back"), "{}", error);
+        assert!(paths.iter().all(|path| path.exists()));
+        assert!(!temp.0.join("new-1").exists());
+        assert_eq!(fs::read_to_string(temp.0.join("new-3")).unwrap(), "someone else's");
+    }
+
+    #[test]
+    fn unique_names_count_past_existing_copies() {
+        let temp = TempDir::new("unique");
+        for name in ["a.txt", "a (1).txt", "a (2).txt", "notes", ".bashrc", "archive.tar.gz"] {
+            fs::write(temp.0.join(name), "").unwrap();
+        }
+
+        assert_eq!(unique_name(&temp.0.join("a.txt")), temp.0.join("a (3).txt"));
+        assert_eq!(unique_name(&temp.0.join("notes")), temp.0.join("notes (1)"));
+        assert_eq!(unique_name(&temp.0.join(".bashrc")), temp.0.join(".bashrc (1)"));
+        // Only the last extension is kept apart
+        assert_eq!(unique_name(&temp.0.join("archive.tar.gz")), temp.0.join("archive.tar (1).gz"));
+    }
+
+    #[test]
+    fn unique_names_keep_non_utf8_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp = TempDir::new("unique-bytes");
+        let path = temp.0.join(OsStr::from_bytes(b"caf\xe9.txt"));
+        fs::write(&path, "").unwrap();
+        fs::write(temp.0.join(OsStr::from_bytes(b"caf\xe9 (1).txt")), "").unwrap();
+
+        let unique = unique_name(&path);
+        assert_eq!(unique.file_name().unwrap().as_bytes(), b"caf\xe9 (2).txt");
+        assert!(!unique.exists());
+    }
+
+    #[test]
+    fn unique_names_skip_names_the_caller_rejects() {
+        let temp = TempDir::new("unique-where");
+        fs::write(temp.0.join("a (1).txt.part"), "").unwrap();
+
+        let is_free = |path: &Path| {
+            let mut part = path.as_os_str().to_os_string();
+            part.push(".part");
+            !path.exists() && !Path::new(&part).exists()
+        };
+        assert_eq!(unique_name_where(&temp.0.join("a.txt"), is_free), temp.0.join("a (2).txt"));
+    }
+}
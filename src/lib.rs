@@ -0,0 +1,9 @@
+//! Reusable pieces of MinDesk.
+
+pub mod fs_ops;
+pub mod listing;
+pub mod packages;
+pub mod page;
+
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,364 @@
+//! A folder's entries as the file manager lists them, and how each is
+//! drawn, without any UI.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone)]
+pub struct FileItem {
+    /// Display name, lossily converted if it isn't valid UTF-8. File
+    /// operations must use `path`, which keeps the real bytes.
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub is_symlink: bool,
+    pub link_target: Option<PathBuf>,
+    /// A symlink whose target doesn't exist.
+    pub is_dangling: bool,
+    /// Listed in its folder, but its details can't be read, e.g. because
+    /// the folder can be read but not searched.
+    pub is_inaccessible: bool,
+}
+
+/// Reads the entries of `path`, unsorted, skipping hidden files.
+pub fn read_directory(path: &Path) -> Result<Vec<FileItem>, String> {
+    let entries = fs::read_dir(path).map_err(|e| {
+        let reason = match e.kind() {
+            io::ErrorKind::PermissionDenied => "Permission denied".to_string(),
+            _ => e.to_string(),
+        };
+        format!("Cannot open {}: {}", path.display(), reason)
+    })?;
+
+    let mut items = Vec::new();
+    for entry in entries.flatten() {
+        let entry_path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        // Skip hidden files unless configured to show them
+        if name.starts_with('.') {
+            continue;
+        }
+
+        // Entries that can't be stat'ed are still listed, so they don't
+        // silently disappear
+        let Ok(link_metadata) = entry_path.symlink_metadata() else {
+            items.push(FileItem {
+                name,
+                is_dir: entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false),
+                path: entry_path,
+                size: 0,
+                modified: None,
+                is_symlink: false,
+                link_target: None,
+                is_dangling: false,
+                is_inaccessible: true,
+            });
+            continue;
+        };
+
+        let is_symlink = link_metadata.file_type().is_symlink();
+        let link_target = is_symlink.then(|| fs::read_link(&entry_path).ok()).flatten();
+
+        // Describe symlinks by their target; a dangling link falls
+        // back to the link itself
+        let target_metadata = if is_symlink { fs::metadata(&entry_path).ok() } else { None };
+        let is_dangling = is_symlink && target_metadata.is_none();
+        let metadata = target_metadata.unwrap_or(link_metadata);
+
+        items.push(FileItem {
+            name,
+            path: entry_path,
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            is_symlink,
+            link_target,
+            is_dangling,
+            is_inaccessible: false,
+        });
+    }
+
+    Ok(items)
+}
+
+/// Picks the list icon for an item, by file extension for regular files.
+pub fn icon_for(item: &FileItem) -> &'static str {
+    if item.is_inaccessible {
+        return "🚫";
+    } else if item.is_dangling {
+        return "⚠";
+    } else if item.is_symlink {
+        return "🔗";
+    } else if item.is_dir {
+        return "📁";
+    }
+
+    let extension = item
+        .path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "svg" | "ico" | "tif" | "tiff" => "🖼",
+        "mp3" | "flac" | "ogg" | "opus" | "wav" | "m4a" | "aac" => "🎵",
+        "mp4" | "mkv" | "webm" | "avi" | "mov" | "m4v" => "🎬",
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "zst" | "7z" | "rar" | "apk" => "📦",
+        "sh" | "bash" | "py" | "rs" | "js" | "ts" | "c" | "h" | "cpp" | "go" | "rb" | "lua" | "pl"
+        | "json" | "toml" | "yaml" | "yml" | "html" | "css" => "📜",
+        "pdf" | "txt" | "md" | "doc" | "docx" | "odt" | "rtf" | "epub" => "📕",
+        _ => "📄",
+    }
+}
+
+/// A size in bytes for the listing, e.g. "512 B" or "1.5 MB", in steps of
+/// 1024 up to petabytes.
+pub fn format_file_size(size: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB", "PB"];
+    let mut size = size as f64;
+    let mut unit_index = 0;
+
+    // Also step up when one decimal would round to "1024.0"
+    while unit_index < UNITS.len() - 1 && (size >= 1024.0 || (unit_index > 0 && size >= 1023.95)) {
+        size /= 1024.0;
+        unit_index += 1;
+    }
+
+    if unit_index == 0 {
+        format!("{} {}", size as u64, UNITS[unit_index])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit_index])
+    }
+}
+
+/// Scores `name` against `query`, which matches if its characters appear in
+/// `name` in order, ignoring case. Runs of consecutive characters and
+/// matches at the start of a word score higher, and shorter names win ties.
+/// Returns the score and the matched character positions.
+pub fn fuzzy_match(query: &str, name: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return None;
+    }
+    // Folded per character so positions line up with `name.chars()`
+    let name: Vec<char> = name.chars().map(|ch| ch.to_lowercase().next().unwrap_or(ch)).collect();
+
+    let score_from = |start: usize| -> Option<(i64, Vec<usize>)> {
+        let mut positions = Vec::with_capacity(query.len());
+        let mut score = 0;
+        let mut next = start;
+        for &wanted in &query {
+            let position = (next..name.len()).find(|&index| name[index] == wanted)?;
+            score += 10;
+            if position > 0 && positions.last() == Some(&(position - 1)) {
+                score += 15;
+            }
+            if position == 0 || !name[position - 1].is_alphanumeric() {
+                score += 10;
+            }
+            positions.push(position);
+            next = position + 1;
+        }
+        Some((score - name.len() as i64, positions))
+    };
+
+    // Matching greedily from each occurrence of the first character finds
+    // the tightest run, e.g. the ".rs" at the end of "readme.rs"
+    (0..name.len())
+        .filter(|&index| name[index] == query[0])
+        .filter_map(score_from)
+        .max_by_key(|(score, _)| *score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::os::unix::fs::symlink;
+
+    fn file(name: &str) -> FileItem {
+        FileItem {
+            name: name.to_string(),
+            path: PathBuf::from("/folder").join(name),
+            is_dir: false,
+            size: 0,
+            modified: None,
+            is_symlink: false,
+            link_target: None,
+            is_dangling: false,
+            is_inaccessible: false,
+        }
+    }
+
+    #[test]
+    fn icons_follow_the_extension() {
+        let cases = [
+            ("photo.JPG", "🖼"),
+            ("logo.svg", "🖼"),
+            ("song.flac", "🎵"),
+            ("clip.mkv", "🎬"),
+            ("backup.tar.gz", "📦"),
+            ("alpine.apk", "📦"),
+            ("main.rs", "📜"),
+            ("Cargo.toml", "📜"),
+            ("report.pdf", "📕"),
+            ("README.md", "📕"),
+            ("data.xyz", "📄"),
+            ("Makefile", "📄"),
+            (".bashrc", "📄"),
+        ];
+        for (name, icon) in cases {
+            assert_eq!(icon_for(&file(name)), icon, "{}", name);
+        }
+    }
+
+    #[test]
+    fn kind_comes_before_extension() {
+        let folder = FileItem { is_dir: true, ..file("photos.jpg") };
+        assert_eq!(icon_for(&folder), "📁");
+
+        let link = FileItem { is_symlink: true, is_dir: true, ..file("latest") };
+        assert_eq!(icon_for(&link), "🔗");
+
+        let dangling = FileItem { is_symlink: true, is_dangling: true, ..file("old.png") };
+        assert_eq!(icon_for(&dangling), "⚠");
+
+        let inaccessible = FileItem { is_inaccessible: true, ..file("secret.txt") };
+        assert_eq!(icon_for(&inaccessible), "🚫");
+    }
+
+    #[test]
+    fn sizes_step_up_at_each_power_of_1024() {
+        const KB: u64 = 1024;
+        let cases = [
+            (0, "0 B"),
+            (1, "1 B"),
+            (1023, "1023 B"),
+            (KB, "1.0 KB"),
+            (KB + KB / 2, "1.5 KB"),
+            (KB * KB - 1, "1.0 MB"),
+            (KB.pow(2), "1.0 MB"),
+            (KB.pow(3), "1.0 GB"),
+            (KB.pow(4) - 1, "1.0 TB"),
+            (KB.pow(4), "1.0 TB"),
+            (3 * KB.pow(4), "3.0 TB"),
+            (KB.pow(5), "1.0 PB"),
+            (KB.pow(6), "1024.0 PB"),
+            (u64::MAX, "16384.0 PB"),
+        ];
+        for (size, formatted) in cases {
+            assert_eq!(format_file_size(size), formatted, "{} bytes", size);
+        }
+    }
+
+    #[test]
+    fn broken_and_working_links_are_listed() {
+        let temp = TempDir::new("links");
+        fs::write(temp.0.join("notes.txt"), "hello").unwrap();
+        fs::create_dir(temp.0.join("folder")).unwrap();
+        fs::write(temp.0.join(".hidden"), "").unwrap();
+        symlink("missing.txt", temp.0.join("broken")).unwrap();
+        symlink("folder", temp.0.join("to-folder")).unwrap();
+
+        let mut items = read_directory(&temp.0).unwrap();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["broken", "folder", "notes.txt", "to-folder"]);
+
+        let broken = &items[0];
+        assert!(broken.is_symlink && broken.is_dangling && !broken.is_dir);
+        assert_eq!(broken.link_target.as_deref(), Some(Path::new("missing.txt")));
+        assert_eq!(icon_for(broken), "⚠");
+
+        let notes = &items[2];
+        assert_eq!(notes.size, 5);
+        assert!(!notes.is_symlink && !notes.is_dangling && !notes.is_inaccessible);
+
+        let to_folder = &items[3];
+        assert!(to_folder.is_symlink && to_folder.is_dir && !to_folder.is_dangling);
+    }
+
+    #[test]
+    fn unreadable_folder_is_an_error() {
+        let temp = TempDir::new("missing");
+        let error = read_directory(&temp.0.join("nope")).unwrap_err();
+        assert!(error.starts_with("Cannot open "), "{}", error);
+    }
+
+    #[test]
+    fn non_utf8_names_keep_their_real_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp = TempDir::new("bytes");
+        let path = temp.0.join(OsStr::from_bytes(b"r\xe9sum\xe9.pdf"));
+        fs::write(&path, "pdf").unwrap();
+
+        let items = read_directory(&temp.0).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "r\u{fffd}sum\u{fffd}.pdf");
+        assert_eq!(items[0].path, path);
+        assert_eq!(fs::read_to_string(&items[0].path).unwrap(), "pdf");
+        assert_eq!(icon_for(&items[0]), "📕");
+    }
+
+    fn positions(query: &str, name: &str) -> Vec<usize> {
+        fuzzy_match(query, name).map(|(_, positions)| positions).unwrap_or_default()
+    }
+
+    /// Names matching `query`, best first.
+    fn ranked<'a>(query: &str, names: &[&'a str]) -> Vec<&'a str> {
+        let mut matches: Vec<_> = names
+            .iter()
+            .filter_map(|&name| Some((fuzzy_match(query, name)?.0, name)))
+            .collect();
+        matches.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        matches.into_iter().map(|(_, name)| name).collect()
+    }
+
+    #[test]
+    fn characters_must_appear_in_order() {
+        assert!(fuzzy_match("sr", "readme.rs").is_none());
+        assert!(fuzzy_match("xyz", "readme.rs").is_none());
+        assert!(fuzzy_match("", "readme.rs").is_none());
+        assert_eq!(positions("rdm", "readme.rs"), [0, 3, 4]);
+    }
+
+    #[test]
+    fn the_tightest_run_is_highlighted() {
+        // Not the "r" at the start and the "s" at the end
+        assert_eq!(positions("rs", "readme.rs"), [7, 8]);
+        assert_eq!(positions(".rs", "readme.rs"), [6, 7, 8]);
+    }
+
+    #[test]
+    fn word_starts_and_runs_outrank_scattered_letters() {
+        // "f" and "m" both start words in file_manager.rs, beating the
+        // shorter farm.rs
+        assert_eq!(ranked("fm", &["farm.rs", "file_manager.rs"]), ["file_manager.rs", "farm.rs"]);
+        assert_eq!(positions("fm", "file_manager.rs"), [0, 5]);
+        assert_eq!(ranked("main", &["my_animation.rs", "main.rs"]), ["main.rs", "my_animation.rs"]);
+    }
+
+    #[test]
+    fn shorter_names_win_ties() {
+        assert_eq!(
+            ranked("notes", &["notes-2024.txt", "notes.txt", "notes"]),
+            ["notes", "notes.txt", "notes-2024.txt"]
+        );
+    }
+
+    #[test]
+    fn case_is_ignored_beyond_ascii() {
+        assert_eq!(positions("ÉLÈ", "élève.txt"), [0, 1, 2]);
+        assert_eq!(positions("öl", "Öl-Preise.ods"), [0, 1]);
+        assert_eq!(positions("README", "readme.rs"), [0, 1, 2, 3, 4, 5]);
+        // Positions count characters, not bytes, so highlighting lines up
+        assert_eq!(positions("tx", "élève.txt"), [6, 7]);
+    }
+}
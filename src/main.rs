@@ -1,6 +1,7 @@
 use iced::{
-    alignment, executor, font, theme,
+    alignment, event, executor, font, keyboard, theme,
     widget::{button, column, container, row, text},
+    window,
     Application, Command, Element, Length, Settings, Subscription, Theme,
 };
 use serde::{Deserialize, Serialize};
@@ -10,36 +11,333 @@ mod config;
 mod file_manager;
 mod package_manager;
 mod browser;
+mod settings;
+mod widgets;
 
 use config::Config;
 use file_manager::FileManager;
 use package_manager::PackageManager;
 use browser::Browser;
+use settings::SettingsPanel;
 
 fn main() -> iced::Result {
     env_logger::init();
+
+    if std::env::args_os().skip(1).any(|arg| arg == "--init-config") {
+        match Config::init_user_config() {
+            Ok((path, true)) => println!("Wrote default config to {}", path.display()),
+            Ok((path, false)) => println!("Config already exists at {}", path.display()),
+            Err(e) => {
+                eprintln!("min-desk: cannot write config: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    let config = load_config();
+
+    if let Some(query) = search_argument() {
+        let json = std::env::args_os().skip(1).any(|arg| arg == "--json");
+        let backend = &config.applications.package_manager.backend;
+        std::process::exit(run_search(backend, query, json));
+    }
+
+    // iced 0.12 doesn't expose the renderer's present mode, which is
+    // always vsynced
+    if !config.system.vsync {
+        log::warn!("system.vsync = false is not supported; rendering stays vsynced");
+    }
+
+    // A transparent window only looks right with a compositor running
+    let transparent = config.system.compositor;
+    let default_text_size = iced::Pixels(config.desktop.font_size as f32);
+
+    let geometry = config
+        .system
+        .remember_window
+        .then(load_window_geometry)
+        .flatten()
+        .map(|geometry| fit_to_screen(geometry, screen_size()))
+        .unwrap_or_default();
+    let position = match geometry.position {
+        Some((x, y)) => window::Position::Specific(iced::Point::new(x as f32, y as f32)),
+        None => window::Position::Default,
+    };
+
     MinDesk::run(Settings {
+        flags: Flags {
+            config,
+            open_path: path_argument(),
+            window: geometry,
+        },
         window: iced::window::Settings {
-            size: (1280, 720),
+            size: iced::Size::new(geometry.width as f32, geometry.height as f32),
+            position,
             decorations: false,
-            transparent: true,
+            transparent,
             ..Default::default()
         },
+        default_text_size,
         antialiasing: true,
         ..Default::default()
     })
 }
 
+/// The config to run with. An explicitly requested config must exist;
+/// only the implicit search falls back to defaults.
+fn load_config() -> Config {
+    match config_override() {
+        Some(path) => match Config::load(Some(&path)) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("min-desk: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => {
+            // Give first-time users a file to edit
+            if !Config::exists() {
+                match Config::init_user_config() {
+                    // On stderr, so it can't end up in `--search --json` output
+                    Ok((path, _)) => eprintln!("Wrote default config to {}", path.display()),
+                    Err(e) => log::warn!("Cannot write default config: {}", e),
+                }
+            }
+            Config::load(None).unwrap_or_default()
+        }
+    }
+}
+
+/// `--search <query>`: prints the packages matching `query`, as JSON with
+/// `--json`, and returns the exit code.
+fn run_search(backend: &str, query: String, json: bool) -> i32 {
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("min-desk: cannot start runtime: {}", e);
+            return 1;
+        }
+    };
+
+    let packages = match runtime.block_on(min_desk::packages::search_packages(backend, query)) {
+        Ok(packages) => packages,
+        Err(e) => {
+            eprintln!("min-desk: search failed: {}", e.trim());
+            return 1;
+        }
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&packages) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("min-desk: cannot encode results: {}", e);
+                return 1;
+            }
+        }
+    } else {
+        for package in &packages {
+            let mark = if package.installed { "[installed] " } else { "" };
+            println!("{}{} {} - {}", mark, package.name, package.version, package.description);
+        }
+    }
+    0
+}
+
+/// Query from `--search <query>` (or `--search=<query>`).
+fn search_argument() -> Option<String> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--search" {
+            match args.next() {
+                Some(query) => return Some(query.to_string_lossy().to_string()),
+                None => {
+                    eprintln!("min-desk: --search requires a query");
+                    std::process::exit(2);
+                }
+            }
+        }
+        if let Some(query) = arg.to_str().and_then(|arg| arg.strip_prefix("--search=")) {
+            return Some(query.to_string());
+        }
+    }
+    None
+}
+
+/// Config path from `--config <path>` (or `--config=<path>`), then the
+/// `MINDESK_CONFIG` environment variable.
+fn config_override() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            match args.next() {
+                Some(path) => return Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("min-desk: --config requires a path");
+                    std::process::exit(2);
+                }
+            }
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    std::env::var_os("MINDESK_CONFIG")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The first positional argument, e.g. `min-desk /mnt/data`, which is
+/// opened in the file manager.
+fn path_argument() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            args.next();
+            continue;
+        }
+        if arg.to_str().map_or(false, |arg| arg.starts_with("--")) {
+            continue;
+        }
+        return Some(PathBuf::from(arg));
+    }
+    None
+}
+
+/// Size and position of the window, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+struct WindowGeometry {
+    width: u32,
+    height: u32,
+    /// Left to the window system when `None`.
+    position: Option<(i32, i32)>,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            position: None,
+        }
+    }
+}
+
+/// Smallest window a saved geometry is restored at.
+const MIN_WINDOW_SIZE: (u32, u32) = (400, 300);
+
+/// How long the window has to stay put before its geometry is saved, so
+/// dragging or resizing doesn't write the file on every step.
+const GEOMETRY_SAVE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+fn window_geometry_file() -> Option<PathBuf> {
+    Config::state_dir().map(|dir| dir.join("window.json"))
+}
+
+/// The geometry saved by the last run, if there is a readable one.
+fn load_window_geometry() -> Option<WindowGeometry> {
+    let contents = std::fs::read_to_string(window_geometry_file()?).ok()?;
+    serde_json::from_str(&contents)
+        .map_err(|e| log::warn!("Ignoring saved window geometry: {}", e))
+        .ok()
+}
+
+fn save_window_geometry(geometry: &WindowGeometry) -> Result<(), String> {
+    let path = window_geometry_file().ok_or("No config directory available")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let contents = serde_json::to_string(geometry).map_err(|e| e.to_string())?;
+    std::fs::write(&path, contents).map_err(|e| e.to_string())
+}
+
+/// Size of the X screen as reported by `xrandr`, or `None` if it can't be
+/// found out.
+fn screen_size() -> Option<(u32, u32)> {
+    let output = std::process::Command::new("xrandr").arg("--current").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // "Screen 0: minimum 8 x 8, current 1280 x 720, maximum 32767 x 32767"
+    let output = String::from_utf8_lossy(&output.stdout);
+    let current = output
+        .lines()
+        .next()?
+        .split(", ")
+        .find_map(|part| part.strip_prefix("current "))?;
+    let (width, height) = current.split_once(" x ")?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
+
+/// Shrinks and moves a saved geometry to fit on `screen`, so a window
+/// saved on a bigger or since-removed display can still be reached. With
+/// no screen size to check against, the saved position is dropped.
+fn fit_to_screen(geometry: WindowGeometry, screen: Option<(u32, u32)>) -> WindowGeometry {
+    let (min_width, min_height) = MIN_WINDOW_SIZE;
+    let Some((screen_width, screen_height)) = screen else {
+        return WindowGeometry {
+            width: geometry.width.max(min_width),
+            height: geometry.height.max(min_height),
+            position: None,
+        };
+    };
+
+    let width = geometry.width.clamp(min_width, screen_width.max(min_width));
+    let height = geometry.height.clamp(min_height, screen_height.max(min_height));
+    let position = geometry.position.map(|(x, y)| {
+        (
+            x.clamp(0, screen_width.saturating_sub(width) as i32),
+            y.clamp(0, screen_height.saturating_sub(height) as i32),
+        )
+    });
+    WindowGeometry { width, height, position }
+}
+
+/// Startup options passed from `main`.
+#[derive(Default)]
+pub struct Flags {
+    config: Config,
+    open_path: Option<PathBuf>,
+    /// Geometry the window was opened with.
+    window: WindowGeometry,
+}
+
 #[derive(Debug, Clone)]
 pub enum Message {
     OpenFileManager,
     OpenPackageManager,
     OpenBrowser,
+    OpenSettings,
+    /// Runs the external app at this index in the config.
+    LaunchApp(usize),
+    /// An external app started, or failed to, by name.
+    AppLaunched(String, Result<(), String>),
     FileManagerMessage(file_manager::Message),
     PackageManagerMessage(package_manager::Message),
     BrowserMessage(browser::Message),
+    SettingsMessage(settings::Message),
     CloseApp(AppView),
+    DismissBanner,
+    /// Clears the banner if it still shows this message.
+    ExpireBanner(String),
+    Quit,
+    ConfirmQuit,
+    CancelQuit,
+    ConfirmClose,
+    CancelClose,
+    FileDropped(PathBuf),
+    DropFinished,
+    WindowMoved(i32, i32),
+    WindowResized(u32, u32),
+    /// Saves the window geometry if it hasn't changed again since the
+    /// change with this number.
+    SaveWindowGeometry(u64),
     Tick,
+    /// Whether the OS prefers a dark color scheme, as last detected.
+    SystemThemeDetected(bool),
+    Animate,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -48,39 +346,100 @@ pub enum AppView {
     FileManager,
     PackageManager,
     Browser,
+    Settings,
 }
 
 pub struct MinDesk {
     config: Config,
+    theme: Theme,
+    /// The OS color scheme the "system" theme last found, dark until known.
+    system_dark: bool,
+    /// A system theme check is running, so ticks don't start another.
+    detecting_theme: bool,
     current_view: AppView,
     file_manager: FileManager,
     package_manager: PackageManager,
     browser: Browser,
+    settings: SettingsPanel,
+    /// Error shown on the desktop, e.g. for a bad path on the command line.
+    banner: Option<String>,
+    /// Quit was requested while a package operation is running.
+    confirm_quit: bool,
+    /// App view waiting for confirmation to close, and what would be lost.
+    confirm_close: Option<(AppView, &'static str)>,
+    /// Files dropped onto the window, collected until the drop finishes.
+    dropped: Vec<PathBuf>,
+    window_geometry: WindowGeometry,
+    /// Counts geometry changes, so only the last of a burst is saved.
+    geometry_changes: u64,
 }
 
 impl Application for MinDesk {
     type Executor = executor::Default;
     type Message = Message;
     type Theme = Theme;
-    type Flags = ();
+    type Flags = Flags;
 
-    fn new(_flags: ()) -> (Self, Command<Message>) {
-        let config = Config::load().unwrap_or_default();
+    fn new(flags: Flags) -> (Self, Command<Message>) {
+        let config = flags.config;
+        let (package_manager, package_manager_command) = PackageManager::new(config.clone());
+        let (browser, browser_command) = Browser::new(config.clone());
+        let (mut file_manager, file_manager_command) = FileManager::new(config.clone());
+
+        let system_theme = config.desktop.theme == "system";
+        let mut current_view = AppView::Desktop;
+        let mut banner = None;
+        if let Some(path) = flags.open_path {
+            match file_manager.open_path(&path) {
+                Ok(()) => current_view = AppView::FileManager,
+                Err(e) => banner = Some(e),
+            }
+        }
 
         (
             Self {
                 config: config.clone(),
-                current_view: AppView::Desktop,
-                file_manager: FileManager::new(config.clone()),
-                package_manager: PackageManager::new(config.clone()),
-                browser: Browser::new(config.clone()),
+                theme: build_theme(&config, true),
+                system_dark: true,
+                detecting_theme: system_theme,
+                current_view,
+                file_manager,
+                package_manager,
+                browser,
+                settings: SettingsPanel::new(config.clone()),
+                banner,
+                confirm_quit: false,
+                confirm_close: None,
+                dropped: Vec::new(),
+                window_geometry: flags.window,
+                geometry_changes: 0,
             },
-            Command::none(),
+            Command::batch(vec![
+                package_manager_command.map(Message::PackageManagerMessage),
+                browser_command.map(Message::BrowserMessage),
+                file_manager_command.map(Message::FileManagerMessage),
+                if system_theme {
+                    Command::perform(detect_system_dark(), Message::SystemThemeDetected)
+                } else {
+                    Command::none()
+                },
+            ]),
         )
     }
 
     fn title(&self) -> String {
-        String::from("MinDesk")
+        match self.current_view {
+            AppView::Desktop => String::from("MinDesk"),
+            AppView::FileManager => {
+                format!("MinDesk — Files ({})", self.file_manager.current_path().display())
+            }
+            AppView::PackageManager => String::from("MinDesk — Packages"),
+            AppView::Browser => match self.browser.current_host() {
+                Some(host) => format!("MinDesk — Browser ({})", host),
+                None => String::from("MinDesk — Browser"),
+            },
+            AppView::Settings => String::from("MinDesk — Settings"),
+        }
     }
 
     fn update(&mut self, message: Message) -> Command<Message> {
@@ -97,6 +456,36 @@ impl Application for MinDesk {
                 self.current_view = AppView::Browser;
                 Command::none()
             }
+            Message::OpenSettings => {
+                self.settings.open();
+                self.current_view = AppView::Settings;
+                Command::none()
+            }
+            Message::LaunchApp(index) => {
+                let Some(app) = self.config.applications.external.get(index) else {
+                    return Command::none();
+                };
+                let name = app.name.clone();
+                Command::perform(launch(app.exec.clone()), move |result| Message::AppLaunched(name, result))
+            }
+            Message::AppLaunched(_, Ok(())) => Command::none(),
+            Message::AppLaunched(name, Err(e)) => {
+                let error = format!("Cannot start {}: {}", name, e);
+                self.banner = Some(error.clone());
+                Command::perform(tokio::time::sleep(BANNER_TIMEOUT), move |_| Message::ExpireBanner(error))
+            }
+            Message::FileManagerMessage(file_manager::Message::Close) => {
+                self.close_app(AppView::FileManager);
+                Command::none()
+            }
+            Message::PackageManagerMessage(package_manager::Message::Close) => {
+                self.close_app(AppView::PackageManager);
+                Command::none()
+            }
+            Message::BrowserMessage(browser::Message::Close) => {
+                self.close_app(AppView::Browser);
+                Command::none()
+            }
             Message::FileManagerMessage(msg) => {
                 self.file_manager.update(msg).map(Message::FileManagerMessage)
             }
@@ -106,11 +495,119 @@ impl Application for MinDesk {
             Message::BrowserMessage(msg) => {
                 self.browser.update(msg).map(Message::BrowserMessage)
             }
+            Message::SettingsMessage(settings::Message::Save) => {
+                if let Some(config) = self.settings.save() {
+                    self.apply_config(config);
+                }
+                Command::none()
+            }
+            Message::SettingsMessage(settings::Message::Close) => {
+                self.current_view = AppView::Desktop;
+                Command::none()
+            }
+            Message::SettingsMessage(msg) => {
+                self.settings.update(msg).map(Message::SettingsMessage)
+            }
             Message::CloseApp(_) => {
                 self.current_view = AppView::Desktop;
                 Command::none()
             }
-            Message::Tick => Command::none(),
+            Message::DismissBanner => {
+                self.banner = None;
+                Command::none()
+            }
+            Message::ExpireBanner(message) => {
+                if self.banner.as_ref() == Some(&message) {
+                    self.banner = None;
+                }
+                Command::none()
+            }
+            Message::Quit => {
+                if self.package_manager.is_busy() {
+                    self.confirm_quit = true;
+                    Command::none()
+                } else {
+                    window::close(window::Id::MAIN)
+                }
+            }
+            Message::ConfirmQuit => {
+                // Dropping the operation kills its sudo/apk child process
+                let _ = self.package_manager.update(package_manager::Message::CancelOperation);
+                window::close(window::Id::MAIN)
+            }
+            Message::CancelQuit => {
+                self.confirm_quit = false;
+                Command::none()
+            }
+            Message::ConfirmClose => {
+                if let Some((view, _)) = self.confirm_close.take() {
+                    if view == AppView::FileManager {
+                        self.file_manager.discard_unsaved();
+                    }
+                    self.current_view = AppView::Desktop;
+                }
+                Command::none()
+            }
+            Message::CancelClose => {
+                self.confirm_close = None;
+                Command::none()
+            }
+            Message::FileDropped(path) => {
+                // Each file arrives as its own event; the no-op command runs
+                // once the queued events are handled, so one drop becomes a
+                // single copy
+                self.dropped.push(path);
+                if self.dropped.len() == 1 {
+                    Command::perform(async {}, |_| Message::DropFinished)
+                } else {
+                    Command::none()
+                }
+            }
+            Message::DropFinished => {
+                let paths = std::mem::take(&mut self.dropped);
+                self.file_manager
+                    .update(file_manager::Message::FilesDropped(paths))
+                    .map(Message::FileManagerMessage)
+            }
+            Message::WindowMoved(x, y) => {
+                self.window_geometry.position = Some((x, y));
+                self.geometry_changed()
+            }
+            Message::WindowResized(width, height) => {
+                self.window_geometry.width = width;
+                self.window_geometry.height = height;
+                self.geometry_changed()
+            }
+            Message::SaveWindowGeometry(change) => {
+                if change == self.geometry_changes {
+                    if let Err(e) = save_window_geometry(&self.window_geometry) {
+                        log::warn!("Failed to save window geometry: {}", e);
+                    }
+                }
+                Command::none()
+            }
+            Message::Tick => {
+                let browser = self.browser.update(browser::Message::Tick).map(Message::BrowserMessage);
+                // Pick up OS dark/light switches without a restart
+                if self.config.desktop.theme == "system" && !self.detecting_theme {
+                    self.detecting_theme = true;
+                    return Command::batch(vec![
+                        Command::perform(detect_system_dark(), Message::SystemThemeDetected),
+                        browser,
+                    ]);
+                }
+                browser
+            }
+            Message::SystemThemeDetected(dark) => {
+                self.detecting_theme = false;
+                if dark != self.system_dark {
+                    self.system_dark = dark;
+                    self.theme = build_theme(&self.config, dark);
+                }
+                Command::none()
+            }
+            // Only here to redraw, which advances the spinners
+            Message::Animate => Command::none(),
         }
     }
 
@@ -123,6 +620,16 @@ impl Application for MinDesk {
                 .map(Message::PackageManagerMessage),
             AppView::Browser => self.browser.view()
                 .map(Message::BrowserMessage),
+            AppView::Settings => self.settings.view()
+                .map(Message::SettingsMessage),
+        };
+
+        let content = if self.confirm_quit {
+            column![self.quit_prompt(), content].into()
+        } else if let Some((_, warning)) = self.confirm_close {
+            column![self.close_prompt(warning), content].into()
+        } else {
+            content
         };
 
         container(content)
@@ -140,20 +647,157 @@ impl Application for MinDesk {
     }
 
     fn theme(&self) -> Theme {
-        if self.config.desktop.theme == "dark" {
-            Theme::Dark
-        } else {
-            Theme::Light
-        }
+        self.theme.clone()
+    }
+
+    /// Scales the whole UI relative to a standard 96 DPI display.
+    fn scale_factor(&self) -> f64 {
+        (self.config.system.dpi as f64 / 96.0).clamp(0.5, 3.0)
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        iced::time::every(std::time::Duration::from_secs(1))
-            .map(|_| Message::Tick)
+        let mut subscriptions = vec![
+            self.package_manager.subscription()
+                .map(Message::PackageManagerMessage),
+            keyboard::on_key_press(|key, modifiers| match key.as_ref() {
+                keyboard::Key::Character("q") if modifiers.command() => Some(Message::Quit),
+                _ => None,
+            }),
+        ];
+
+        // The tick only drives the system theme check and the browser's
+        // loading timer, so don't wake up every second otherwise
+        if self.config.desktop.theme == "system" || self.browser.is_loading() {
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_secs(1))
+                    .map(|_| Message::Tick),
+            );
+        }
+
+        if self.browser.is_loading() || self.package_manager.is_loading() {
+            subscriptions.push(iced::time::every(widgets::SPINNER_INTERVAL).map(|_| Message::Animate));
+        }
+
+        if self.config.system.remember_window {
+            subscriptions.push(event::listen_with(|event, _status| match event {
+                iced::Event::Window(_, window::Event::Moved { x, y }) => Some(Message::WindowMoved(x, y)),
+                iced::Event::Window(_, window::Event::Resized { width, height }) => {
+                    Some(Message::WindowResized(width, height))
+                }
+                _ => None,
+            }));
+        }
+
+        // Keyboard shortcuts and file drops only apply to the app on screen
+        match self.current_view {
+            AppView::FileManager => {
+                subscriptions.push(self.file_manager.subscription().map(Message::FileManagerMessage));
+                subscriptions.push(event::listen_with(|event, _status| match event {
+                    iced::Event::Window(_, window::Event::FileDropped(path)) => Some(Message::FileDropped(path)),
+                    _ => None,
+                }));
+            }
+            AppView::PackageManager => {
+                subscriptions.push(self.package_manager.shortcuts().map(Message::PackageManagerMessage));
+            }
+            AppView::Browser => {
+                subscriptions.push(self.browser.subscription().map(Message::BrowserMessage));
+            }
+            _ => {}
+        }
+
+        Subscription::batch(subscriptions)
     }
 }
 
 impl MinDesk {
+    /// Hot-applies saved settings. Window options like the compositor and
+    /// font size still only take effect on restart.
+    fn apply_config(&mut self, config: Config) {
+        self.theme = build_theme(&config, self.system_dark);
+        self.file_manager.set_config(config.clone());
+        self.package_manager.set_config(config.clone());
+        self.browser.set_config(config.clone());
+        self.config = config;
+    }
+
+    /// Records a move or resize, saving it once the window settles.
+    fn geometry_changed(&mut self) -> Command<Message> {
+        self.geometry_changes += 1;
+        let change = self.geometry_changes;
+        Command::perform(tokio::time::sleep(GEOMETRY_SAVE_DELAY), move |_| {
+            Message::SaveWindowGeometry(change)
+        })
+    }
+
+    /// Returns to the desktop, first asking if the view has state that
+    /// closing would lose. A package operation keeps running either way.
+    fn close_app(&mut self, view: AppView) {
+        let warning = match view {
+            AppView::FileManager => self.file_manager.unsaved_state(),
+            AppView::Browser => self.browser.is_loading().then_some("A page is still loading."),
+            _ => None,
+        };
+        match warning {
+            Some(warning) => self.confirm_close = Some((view, warning)),
+            None => self.current_view = AppView::Desktop,
+        }
+    }
+
+    fn close_prompt(&self, warning: &str) -> Element<Message> {
+        let prompt = row![
+            text(format!("{} Close anyway?", warning)).size(14),
+            row![].width(Length::Fill),
+            button(text("Close").size(12))
+                .on_press(Message::ConfirmClose)
+                .padding(5),
+            button(text("Cancel").size(12))
+                .on_press(Message::CancelClose)
+                .padding(5),
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center);
+
+        container(prompt)
+            .padding(10)
+            .width(Length::Fill)
+            .style(|theme: &Theme| {
+                let palette = theme.extended_palette();
+                container::Appearance {
+                    background: Some(iced::Background::Color(palette.background.strong.color)),
+                    ..Default::default()
+                }
+            })
+            .into()
+    }
+
+    fn quit_prompt(&self) -> Element<Message> {
+        let prompt = row![
+            text("Operation in progress, quit anyway?").size(14),
+            row![].width(Length::Fill),
+            button(text("Quit").size(12))
+                .on_press(Message::ConfirmQuit)
+                .padding(5),
+            button(text("Cancel").size(12))
+                .on_press(Message::CancelQuit)
+                .padding(5),
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center);
+
+        container(prompt)
+            .padding(10)
+            .width(Length::Fill)
+            .style(|theme: &Theme| {
+                let palette = theme.extended_palette();
+                container::Appearance {
+                    background: Some(iced::Background::Color(palette.background.strong.color)),
+                    ..Default::default()
+                }
+            })
+            .into()
+    }
+
     fn desktop_view(&self) -> Element<Message> {
         let title = text("MinDesk")
             .size(32)
@@ -237,20 +881,92 @@ impl MinDesk {
             }
         });
 
-        let apps = row![
+        let settings_btn = button(
+            row![
+                text("⚙").size(48),
+                text("Settings").size(16)
+            ]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center)
+        )
+        .on_press(Message::OpenSettings)
+        .padding(20)
+        .style(|theme: &Theme, status| {
+            let palette = theme.extended_palette();
+            button::Appearance {
+                background: Some(iced::Background::Color(
+                    if matches!(status, button::Status::Hovered) {
+                        iced::Color::from_rgba8(255, 255, 255, 0.1)
+                    } else {
+                        iced::Color::TRANSPARENT
+                    }
+                )),
+                border: iced::Border::with_radius(8),
+                text_color: palette.background.base.text,
+                ..Default::default()
+            }
+        });
+
+        let mut apps = row![
             file_manager_btn,
             package_manager_btn,
-            browser_btn
+            browser_btn,
         ]
         .spacing(30);
 
-        let content = column![
+        for (index, app) in self.config.applications.external.iter().enumerate() {
+            apps = apps.push(
+                button(
+                    row![
+                        text(&app.icon).size(48),
+                        text(&app.name).size(16)
+                    ]
+                    .spacing(10)
+                    .align_items(alignment::Alignment::Center)
+                )
+                .on_press(Message::LaunchApp(index))
+                .padding(20)
+                .style(|theme: &Theme, status| {
+                    let palette = theme.extended_palette();
+                    button::Appearance {
+                        background: Some(iced::Background::Color(
+                            if matches!(status, button::Status::Hovered) {
+                                iced::Color::from_rgba8(255, 255, 255, 0.1)
+                            } else {
+                                iced::Color::TRANSPARENT
+                            }
+                        )),
+                        border: iced::Border::with_radius(8),
+                        text_color: palette.background.base.text,
+                        ..Default::default()
+                    }
+                }),
+            );
+        }
+        let apps = apps.push(settings_btn);
+
+        let mut content = column![
             title,
             apps
         ]
         .spacing(50)
         .align_items(alignment::Alignment::Center);
 
+        if let Some(banner) = &self.banner {
+            content = content.push(
+                row![
+                    text(banner)
+                        .size(14)
+                        .style(theme::Text::Color(iced::Color::from_rgb(1.0, 0.4, 0.4))),
+                    button(text("✕").size(14))
+                        .on_press(Message::DismissBanner)
+                        .padding(4),
+                ]
+                .spacing(10)
+                .align_items(alignment::Alignment::Center),
+            );
+        }
+
         // Simple dark background for now
         container(content)
             .width(Length::Fill)
@@ -267,3 +983,90 @@ impl MinDesk {
             .into()
     }
 }
+
+/// How long a launch error stays on the desktop.
+const BANNER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long a launched app has to fail for it to count as not starting.
+const LAUNCH_CHECK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Starts an external app's command line. The shell itself always starts,
+/// so a command that exits with an error straight away, such as one that
+/// isn't installed, is reported as a failed launch.
+async fn launch(exec: String) -> Result<(), String> {
+    let mut command = tokio::process::Command::new("sh");
+    command.arg("-c").arg(&exec);
+    spawn_checked(command).await
+}
+
+/// Spawns `command`, failing if it exits with an error within
+/// `LAUNCH_CHECK`.
+async fn spawn_checked(mut command: tokio::process::Command) -> Result<(), String> {
+    let mut child = command
+        .stdin(std::process::Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    match tokio::time::timeout(LAUNCH_CHECK, child.wait()).await {
+        // Still running, so it started. Dropping the handle leaves it
+        // running and tokio reaps it when it exits
+        Err(_) => Ok(()),
+        Ok(Ok(status)) if status.code() == Some(127) => Err("command not found".to_string()),
+        Ok(Ok(status)) if !status.success() => Err(format!("exited with {}", status)),
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+    }
+}
+
+/// Builds the app theme from config, swapping in the accent color as the
+/// primary palette color when one is set. An invalid hex keeps the default.
+/// `"system"` follows `system_dark`, the OS color scheme.
+fn build_theme(config: &Config, system_dark: bool) -> Theme {
+    let dark = match config.desktop.theme.as_str() {
+        "dark" => true,
+        "system" => system_dark,
+        _ => false,
+    };
+    let base = if dark { Theme::Dark } else { Theme::Light };
+
+    match parse_hex_color(&config.desktop.accent_color) {
+        Some(accent) => Theme::custom(
+            String::from("MinDesk"),
+            theme::Palette {
+                primary: accent,
+                ..base.palette()
+            },
+        ),
+        None => base,
+    }
+}
+
+/// Whether the OS prefers a dark color scheme, defaulting to dark when it
+/// can't tell. dark-light blocks on D-Bus and dconf to find out, so it runs
+/// off the UI thread.
+async fn detect_system_dark() -> bool {
+    tokio::task::spawn_blocking(|| dark_light::detect() != dark_light::Mode::Light)
+        .await
+        .unwrap_or(true)
+}
+
+fn parse_hex_color(hex: &str) -> Option<iced::Color> {
+    let hex = hex.trim().strip_prefix('#')?;
+    if !hex.is_ascii() {
+        return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+
+    let (r, g, b) = match hex.len() {
+        3 => {
+            let r = channel(&hex[0..1])?;
+            let g = channel(&hex[1..2])?;
+            let b = channel(&hex[2..3])?;
+            (r * 17, g * 17, b * 17)
+        }
+        6 => (channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?),
+        _ => return None,
+    };
+
+    Some(iced::Color::from_rgb8(r, g, b))
+}
@@ -1,11 +1,17 @@
 use iced::{
-    alignment, theme, widget::{button, column, container, row, scrollable, text, text_input},
-    Command, Element, Length,
+    alignment, keyboard, subscription, theme,
+    widget::{button, column, container, row, scrollable, text, text_input},
+    futures::SinkExt,
+    Command, Element, Length, Subscription,
 };
-use std::process::Command as ProcessCommand;
-use tokio::process::Command as TokioCommand;
+use std::sync::Arc;
 
 use crate::config::Config;
+use min_desk::packages::{
+    backend_for, check_passwordless_sudo, installed_names, search_with, validate_package_name,
+    Elevation, InstallPreview, Package, PackageBackend, PackageDetails, SUDO_TIMEOUT,
+};
+use crate::widgets::{self, Density};
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -13,54 +19,138 @@ pub enum Message {
     Install(String),
     Remove(String),
     UpdateCache,
+    Rescan,
+    Rescanned(Result<Vec<Package>, String>),
     SearchResults(Vec<Package>),
+    SearchFailed(String),
+    InstallProgress(String),
     OperationComplete(String),
     OperationError(String),
+    SudoChecked(bool),
+    UpdateSudoPassword(String),
+    ShowPackageDetails(String),
+    PackageDetailsLoaded(Result<PackageDetails, String>),
+    HidePackageDetails,
+    NextPage,
+    PreviousPage,
+    CancelOperation,
+    ConfirmPending,
+    CancelPending,
+    /// Dry run of an install awaiting confirmation, for that package.
+    InstallPreviewed(String, Result<InstallPreview, String>),
     Close,
 }
 
-#[derive(Debug, Clone)]
-pub struct Package {
-    pub name: String,
-    pub version: String,
-    pub description: String,
-    pub installed: bool,
+/// A privileged package operation. Each runs as a subscription, so dropping
+/// it (on completion or cancel) drops the child process, and `kill_on_drop`
+/// takes care of killing it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Operation {
+    Install(String),
+    Remove(String),
+    UpdateCache,
 }
 
+/// Number of search results rendered at once.
+const PAGE_SIZE: usize = 50;
+
+/// Relative widths of the status, name, version and action columns, so
+/// every row lines up regardless of its text.
+const STATUS_PORTION: u16 = 1;
+const NAME_PORTION: u16 = 12;
+const VERSION_PORTION: u16 = 3;
+const ACTION_PORTION: u16 = 2;
+
 pub struct PackageManager {
     config: Config,
+    /// Shared with running operations, which outlive a config change.
+    backend: Arc<dyn PackageBackend>,
     search_query: String,
     packages: Vec<Package>,
+    /// Index of the page of `packages` currently shown.
+    page: usize,
     loading: bool,
     message: Option<String>,
     error: Option<String>,
+    /// Operation currently running, tagged with a unique job id so
+    /// repeating the same operation restarts the subscription.
+    running: Option<(u64, Operation)>,
+    next_job_id: u64,
+    /// Install or removal waiting for the user to confirm it.
+    pending: Option<Operation>,
+    /// Dry run of a pending install, `None` while it runs.
+    preview: Option<Result<InstallPreview, String>>,
+    /// `None` until the startup check for passwordless sudo has finished.
+    passwordless_sudo: Option<bool>,
+    sudo_password: String,
+    details: Option<PackageDetails>,
+    /// Whether the installed list is being re-read for `Rescan`.
+    rescanning: bool,
 }
 
 impl PackageManager {
-    pub fn new(config: Config) -> Self {
-        Self {
-            config,
-            search_query: String::new(),
-            packages: Vec::new(),
-            loading: false,
-            message: None,
-            error: None,
+    pub fn new(config: Config) -> (Self, Command<Message>) {
+        // polkit prompts on its own, so only sudo needs the password check
+        let startup = if config.applications.package_manager.privilege_helper == "pkexec" {
+            Command::none()
+        } else {
+            Command::perform(check_passwordless_sudo(), Message::SudoChecked)
+        };
+
+        (
+            Self {
+                backend: backend_for(&config.applications.package_manager.backend),
+                config,
+                search_query: String::new(),
+                packages: Vec::new(),
+                page: 0,
+                loading: false,
+                message: None,
+                error: None,
+                running: None,
+                next_job_id: 0,
+                pending: None,
+                preview: None,
+                passwordless_sudo: None,
+                sudo_password: String::new(),
+                details: None,
+                rescanning: false,
+            },
+            startup,
+        )
+    }
+
+    fn page_count(&self) -> usize {
+        (self.packages.len() + PAGE_SIZE - 1) / PAGE_SIZE
+    }
+
+    fn uses_pkexec(&self) -> bool {
+        self.config.applications.package_manager.privilege_helper == "pkexec"
+    }
+
+    fn elevation(&self) -> Elevation {
+        if self.uses_pkexec() {
+            Elevation::Pkexec
+        } else {
+            Elevation::Sudo((!self.sudo_password.is_empty()).then(|| self.sudo_password.clone()))
         }
     }
 
+    /// Replaces the config after settings are saved.
+    pub fn set_config(&mut self, config: Config) {
+        self.backend = backend_for(&config.applications.package_manager.backend);
+        self.config = config;
+    }
+
     pub fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::Search(query) => {
                 self.search_query = query.clone();
+                self.page = 0;
                 if !query.is_empty() {
                     self.loading = true;
                     self.error = None;
-                    Command::perform(search_packages(query), |result| {
-                        match result {
-                            Ok(packages) => Message::SearchResults(packages),
-                            Err(e) => Message::OperationError(e),
-                        }
-                    })
+                    self.search(query)
                 } else {
                     self.packages.clear();
                     Command::none()
@@ -68,85 +158,283 @@ impl PackageManager {
             }
             Message::SearchResults(packages) => {
                 self.packages = packages;
-                self.loading = false;
+                // Results refreshed after an operation keep the current page
+                self.page = self.page.min(self.page_count().saturating_sub(1));
+                // A search typed during an operation leaves its spinner up
+                if !self.is_busy() {
+                    self.loading = false;
+                }
+                Command::none()
+            }
+            Message::SearchFailed(error) => {
+                if !self.is_busy() {
+                    self.loading = false;
+                }
+                self.error = Some(error);
+                Command::none()
+            }
+            Message::NextPage => {
+                if self.page + 1 < self.page_count() {
+                    self.page += 1;
+                }
+                Command::none()
+            }
+            Message::PreviousPage => {
+                self.page = self.page.saturating_sub(1);
                 Command::none()
             }
             Message::Install(package_name) => {
-                self.loading = true;
-                self.error = None;
-                self.message = Some(format!("Installing {}...", package_name));
-                Command::perform(install_package(package_name.clone()), |result| {
-                    match result {
-                        Ok(msg) => Message::OperationComplete(msg),
-                        Err(e) => Message::OperationError(e),
-                    }
-                })
+                // Starting another operation would drop, and so kill, the running one
+                if self.is_busy() {
+                    return Command::none();
+                }
+                if let Err(e) = validate_package_name(&package_name) {
+                    self.error = Some(e);
+                    return Command::none();
+                }
+                self.pending = Some(Operation::Install(package_name.clone()));
+                self.preview = None;
+                let preview = self.backend.preview_install(package_name.clone());
+                Command::perform(preview, move |result| Message::InstallPreviewed(package_name, result))
+            }
+            Message::InstallPreviewed(package_name, result) => {
+                // A preview for a prompt that has since changed is dropped
+                if self.pending == Some(Operation::Install(package_name)) {
+                    self.preview = Some(result);
+                }
+                Command::none()
+            }
+            Message::InstallProgress(line) => {
+                self.message = Some(line);
+                Command::none()
             }
             Message::Remove(package_name) => {
-                self.loading = true;
-                self.error = None;
-                self.message = Some(format!("Removing {}...", package_name));
-                Command::perform(remove_package(package_name.clone()), |result| {
-                    match result {
-                        Ok(msg) => Message::OperationComplete(msg),
-                        Err(e) => Message::OperationError(e),
-                    }
-                })
+                if self.is_busy() {
+                    return Command::none();
+                }
+                if let Err(e) = validate_package_name(&package_name) {
+                    self.error = Some(e);
+                    return Command::none();
+                }
+                self.pending = Some(Operation::Remove(package_name));
+                Command::none()
+            }
+            Message::ConfirmPending => {
+                if self.is_busy() {
+                    return Command::none();
+                }
+                self.preview = None;
+                if let Some(operation) = self.pending.take() {
+                    self.loading = true;
+                    self.error = None;
+                    self.message = Some(match &operation {
+                        Operation::Install(name) => format!("Installing {}...", name),
+                        Operation::Remove(name) => format!("Removing {}...", name),
+                        Operation::UpdateCache => "Updating package cache...".to_string(),
+                    });
+                    self.start(operation);
+                }
+                Command::none()
+            }
+            Message::CancelPending => {
+                self.pending = None;
+                self.preview = None;
+                Command::none()
             }
             Message::UpdateCache => {
+                if self.is_busy() {
+                    return Command::none();
+                }
                 self.loading = true;
                 self.error = None;
                 self.message = Some("Updating package cache...".to_string());
-                Command::perform(update_cache(), |result| {
-                    match result {
-                        Ok(msg) => Message::OperationComplete(msg),
-                        Err(e) => Message::OperationError(e),
+                self.start(Operation::UpdateCache);
+                Command::none()
+            }
+            Message::Rescan => {
+                self.rescanning = true;
+                self.error = None;
+                self.message = Some("Rescanning installed packages...".to_string());
+                Command::perform(self.backend.list_installed(), Message::Rescanned)
+            }
+            Message::Rescanned(result) => {
+                self.rescanning = false;
+                match result {
+                    // Results keep their order and page, so only the flags change
+                    Ok(installed) => {
+                        let installed = installed_names(Ok(installed));
+                        for package in &mut self.packages {
+                            package.installed = installed.contains(&package.name);
+                        }
+                        self.message = Some(format!("{} packages installed", installed.len()));
                     }
-                })
+                    Err(e) => {
+                        self.message = None;
+                        self.error = Some(e);
+                    }
+                }
+                Command::none()
             }
             Message::OperationComplete(msg) => {
+                if let Some((_, operation)) = &self.running {
+                    let summary = match operation {
+                        Operation::Install(name) => format!("Installed {}", name),
+                        Operation::Remove(name) => format!("Removed {}", name),
+                        Operation::UpdateCache => "Package cache updated".to_string(),
+                    };
+                    self.notify(summary, msg.clone());
+                }
                 self.loading = false;
+                self.running = None;
                 self.message = Some(msg);
                 self.error = None;
                 // Refresh search after operation
                 if !self.search_query.is_empty() {
-                    Command::perform(search_packages(self.search_query.clone()), |result| {
-                        match result {
-                            Ok(packages) => Message::SearchResults(packages),
-                            Err(e) => Message::OperationError(e),
-                        }
-                    })
+                    self.search(self.search_query.clone())
                 } else {
                     Command::none()
                 }
             }
             Message::OperationError(error) => {
+                if let Some((_, operation)) = &self.running {
+                    let summary = match operation {
+                        Operation::Install(name) => format!("Failed to install {}", name),
+                        Operation::Remove(name) => format!("Failed to remove {}", name),
+                        Operation::UpdateCache => "Failed to update package cache".to_string(),
+                    };
+                    self.notify(summary, error.clone());
+                }
                 self.loading = false;
+                self.running = None;
                 self.error = Some(error);
                 self.message = None;
                 Command::none()
             }
+            Message::SudoChecked(passwordless) => {
+                self.passwordless_sudo = Some(passwordless);
+                Command::none()
+            }
+            Message::UpdateSudoPassword(password) => {
+                self.sudo_password = password;
+                Command::none()
+            }
+            Message::ShowPackageDetails(package_name) => {
+                self.loading = true;
+                self.error = None;
+                Command::perform(self.backend.details(package_name), Message::PackageDetailsLoaded)
+            }
+            Message::PackageDetailsLoaded(result) => {
+                self.loading = false;
+                match result {
+                    Ok(details) => self.details = Some(details),
+                    Err(e) => self.error = Some(e),
+                }
+                Command::none()
+            }
+            Message::HidePackageDetails => {
+                self.details = None;
+                Command::none()
+            }
+            Message::CancelOperation => {
+                if let Some((_, operation)) = self.running.take() {
+                    self.loading = false;
+                    self.error = None;
+                    self.message = Some(match operation {
+                        Operation::Install(name) => format!("Cancelled installing {}", name),
+                        Operation::Remove(name) => format!("Cancelled removing {}", name),
+                        Operation::UpdateCache => "Cancelled cache update".to_string(),
+                    });
+                }
+                Command::none()
+            }
             Message::Close => {
                 Command::none()
             }
         }
     }
 
+    fn search(&self, query: String) -> Command<Message> {
+        Command::perform(
+            search_with(self.backend.as_ref(), query),
+            |result| match result {
+                Ok(packages) => Message::SearchResults(packages),
+                Err(e) => Message::SearchFailed(e),
+            },
+        )
+    }
+
+    /// Shows a desktop notification if they're enabled. Sending one talks
+    /// to D-Bus, so it happens off the UI thread.
+    fn notify(&self, summary: String, body: String) {
+        if !self.config.applications.package_manager.notifications {
+            return;
+        }
+        std::thread::spawn(move || {
+            if let Err(e) = notify_rust::Notification::new()
+                .appname("MinDesk")
+                .summary(&summary)
+                .body(&body)
+                .show()
+            {
+                log::warn!("Failed to show notification: {}", e);
+            }
+        });
+    }
+
+    /// Whether a search, lookup or operation is in progress.
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    /// Whether an install, removal or cache update is running.
+    pub fn is_busy(&self) -> bool {
+        self.running.is_some()
+    }
+
+    fn start(&mut self, operation: Operation) {
+        self.running = Some((self.next_job_id, operation));
+        self.next_job_id += 1;
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        match &self.running {
+            Some((id, operation)) => run_operation(*id, operation.clone(), self.elevation(), self.backend.clone()),
+            None => Subscription::none(),
+        }
+    }
+
+    /// Enter and Escape answer the confirmation prompt while it's open.
+    pub fn shortcuts(&self) -> Subscription<Message> {
+        if self.pending.is_none() {
+            return Subscription::none();
+        }
+
+        keyboard::on_key_press(|key, _| match key.as_ref() {
+            keyboard::Key::Named(keyboard::key::Named::Enter) => Some(Message::ConfirmPending),
+            keyboard::Key::Named(keyboard::key::Named::Escape) => Some(Message::CancelPending),
+            _ => None,
+        })
+    }
+
     pub fn view(&self) -> Element<Message> {
         let header = self.create_header();
         let search_bar = self.create_search_bar();
         let content = self.create_content();
         let status_bar = self.create_status_bar();
 
-        let main_content = column![
+        let mut main_content = column![
             header,
             search_bar,
-            content,
-            status_bar,
         ]
         .spacing(10)
         .padding(10);
 
+        if let Some(prompt) = self.create_confirmation_prompt() {
+            main_content = main_content.push(prompt);
+        }
+
+        let main_content = main_content.push(content).push(status_bar);
+
         container(main_content)
             .width(Length::Fill)
             .height(Length::Fill)
@@ -182,18 +470,100 @@ impl PackageManager {
             });
 
         let update_btn = button(text("🔄 Update Cache"))
-            .on_press(Message::UpdateCache)
+            .on_press_maybe((!self.is_busy()).then_some(Message::UpdateCache))
             .padding(8);
 
-        row![
+        // Picks up packages installed or removed outside MinDesk
+        let rescan_btn = button(text("🔍 Rescan"))
+            .on_press_maybe((!self.rescanning && !self.is_busy()).then_some(Message::Rescan))
+            .padding(8);
+
+        let mut header = row![
             text("📦 Package Manager").size(18),
             row![].width(Length::Fill),
-            update_btn,
-            close_btn,
         ]
         .spacing(10)
-        .align_items(alignment::Alignment::Center)
-        .into()
+        .align_items(alignment::Alignment::Center);
+
+        // Without passwordless sudo, collect the password here and pipe it to `sudo -S`
+        if !self.uses_pkexec() && self.passwordless_sudo == Some(false) {
+            header = header
+                .push(
+                    text("⚠ sudo needs a password")
+                        .size(12)
+                        .style(theme::Text::Color(iced::Color::from_rgb(1.0, 0.7, 0.3))),
+                )
+                .push(
+                    text_input("sudo password", &self.sudo_password)
+                        .on_input(Message::UpdateSudoPassword)
+                        .secure(true)
+                        .padding(5)
+                        .width(Length::Fixed(160.0)),
+                );
+        }
+
+        header.push(rescan_btn).push(update_btn).push(close_btn).into()
+    }
+
+    fn create_confirmation_prompt(&self) -> Option<Element<Message>> {
+        let (question, action) = match self.pending.as_ref()? {
+            Operation::Install(name) => (format!("Install {}?", name), "Install"),
+            Operation::Remove(name) => (format!("Remove {}?", name), "Remove"),
+            Operation::UpdateCache => ("Update the package cache?".to_string(), "Update"),
+        };
+
+        let prompt = row![
+            text(question).size(14),
+            row![].width(Length::Fill),
+            button(text("Cancel").size(12))
+                .on_press(Message::CancelPending)
+                .padding(5),
+            button(text(action).size(12))
+                .on_press(Message::ConfirmPending)
+                .padding(5)
+                .style(|theme: &iced::Theme, _| {
+                    button::Appearance {
+                        background: Some(iced::Background::Color(
+                            theme.palette().primary
+                        )),
+                        border: iced::Border::with_radius(4),
+                        text_color: theme.extended_palette().primary.base.text,
+                        ..Default::default()
+                    }
+                }),
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center);
+
+        let mut prompt = column![prompt].spacing(6);
+        if matches!(self.pending, Some(Operation::Install(_))) {
+            let details = match &self.preview {
+                None => vec!["Checking what will be installed...".to_string()],
+                Some(Err(e)) => vec![format!("Couldn't check what will be installed: {}", e)],
+                Some(Ok(preview)) => preview_lines(preview),
+            };
+            for line in details {
+                prompt = prompt.push(
+                    text(line)
+                        .size(12)
+                        .style(theme::Text::Color(iced::Color::from_rgb(0.7, 0.7, 0.7))),
+                );
+            }
+        }
+
+        Some(
+            container(prompt)
+                .padding(10)
+                .style(|theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    container::Appearance {
+                        background: Some(iced::Background::Color(palette.background.strong.color)),
+                        border: iced::Border::with_radius(4),
+                        ..Default::default()
+                    }
+                })
+                .into(),
+        )
     }
 
     fn create_search_bar(&self) -> Element<Message> {
@@ -213,9 +583,20 @@ impl PackageManager {
 
     fn create_content(&self) -> Element<Message> {
         if self.loading {
-            return container(
-                text("Loading...").size(16)
-            )
+            let spinner = row![widgets::spinner().size(16), text("Loading...").size(16)].spacing(8);
+            let mut loading = column![spinner]
+                .spacing(10)
+                .align_items(alignment::Alignment::Center);
+
+            if self.running.is_some() {
+                loading = loading.push(
+                    button(text("Cancel").size(14))
+                        .on_press(Message::CancelOperation)
+                        .padding(8),
+                );
+            }
+
+            return container(loading)
             .width(Length::Fill)
             .height(Length::Fill)
             .center_x()
@@ -223,13 +604,18 @@ impl PackageManager {
             .into();
         }
 
-        let mut packages_column = column![].spacing(5);
+        if let Some(details) = &self.details {
+            return self.create_details(details);
+        }
+
+        let density = Density::from_config(&self.config.desktop.density);
+        let mut packages_column = column![].spacing(density.padding(5));
 
-        for package in &self.packages {
+        for package in self.packages.iter().skip(self.page * PAGE_SIZE).take(PAGE_SIZE) {
             let install_btn = if package.installed {
-                button(text("Remove").size(12))
+                button(text("Remove").size(density.text_size(12)))
                     .on_press(Message::Remove(package.name.clone()))
-                    .padding(5)
+                    .padding(density.padding(5))
                     .style(|theme: &iced::Theme, _| {
                         button::Appearance {
                             background: Some(iced::Background::Color(
@@ -241,16 +627,16 @@ impl PackageManager {
                         }
                     })
             } else {
-                button(text("Install").size(12))
+                button(text("Install").size(density.text_size(12)))
                     .on_press(Message::Install(package.name.clone()))
-                    .padding(5)
+                    .padding(density.padding(5))
                     .style(|theme: &iced::Theme, _| {
                         button::Appearance {
                             background: Some(iced::Background::Color(
-                                iced::Color::from_rgb(0.2, 0.6, 0.2)
+                                theme.palette().primary
                             )),
                             border: iced::Border::with_radius(4),
-                            text_color: iced::Color::WHITE,
+                            text_color: theme.extended_palette().primary.base.text,
                             ..Default::default()
                         }
                     })
@@ -264,22 +650,39 @@ impl PackageManager {
 
             let package_row = container(
                 row![
-                    status_indicator.width(Length::Fixed(20.0)),
+                    status_indicator.width(Length::FillPortion(STATUS_PORTION)),
                     column![
-                        text(&package.name).size(14),
+                        button(text(&package.name).size(density.text_size(14)))
+                            .on_press(Message::ShowPackageDetails(package.name.clone()))
+                            .padding(0)
+                            .style(|theme: &iced::Theme, status| {
+                                let palette = theme.extended_palette();
+                                button::Appearance {
+                                    background: None,
+                                    text_color: if matches!(status, button::Status::Hovered) {
+                                        palette.primary.base.color
+                                    } else {
+                                        palette.background.base.text
+                                    },
+                                    ..Default::default()
+                                }
+                            }),
                         text(&package.description)
-                            .size(12)
+                            .size(density.text_size(12))
                             .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
-                    ].width(Length::Fill),
+                    ].width(Length::FillPortion(NAME_PORTION)),
                     text(&package.version)
-                        .size(12)
-                        .style(theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5))),
-                    install_btn,
+                        .size(density.text_size(12))
+                        .style(theme::Text::Color(iced::Color::from_rgb(0.5, 0.5, 0.5)))
+                        .width(Length::FillPortion(VERSION_PORTION)),
+                    container(install_btn)
+                        .width(Length::FillPortion(ACTION_PORTION))
+                        .align_x(alignment::Horizontal::Right),
                 ]
                 .spacing(10)
                 .align_items(alignment::Alignment::Center)
             )
-            .padding(10)
+            .padding(density.padding(10))
             .style(|theme: &iced::Theme| {
                 let palette = theme.extended_palette();
                 container::Appearance {
@@ -308,12 +711,87 @@ impl PackageManager {
             .width(Length::Fill)
             .height(Length::Fill);
 
-        container(scrollable_content)
+        let mut content = column![scrollable_content].spacing(5);
+
+        if self.page_count() > 1 {
+            let pager = row![
+                button(text("◀ Prev").size(12))
+                    .on_press_maybe((self.page > 0).then_some(Message::PreviousPage))
+                    .padding(5),
+                text(format!("Page {} of {}", self.page + 1, self.page_count())).size(12),
+                button(text("Next ▶").size(12))
+                    .on_press_maybe((self.page + 1 < self.page_count()).then_some(Message::NextPage))
+                    .padding(5),
+            ]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center);
+
+            content = content.push(container(pager).width(Length::Fill).center_x());
+        }
+
+        container(content)
             .width(Length::Fill)
             .height(Length::Fill)
             .into()
     }
 
+    fn create_details(&self, details: &PackageDetails) -> Element<Message> {
+        let back_btn = button(text("◀ Back").size(14))
+            .on_press(Message::HidePackageDetails)
+            .padding(8);
+
+        let installed = self.packages.iter().any(|p| p.name == details.name && p.installed);
+        let action_btn = if installed {
+            button(text("Remove").size(14)).on_press(Message::Remove(details.name.clone()))
+        } else {
+            button(text("Install").size(14)).on_press(Message::Install(details.name.clone()))
+        }
+        .padding(8);
+
+        let field = |label: &str, value: &str| {
+            row![
+                text(label.to_string())
+                    .size(13)
+                    .width(Length::Fixed(120.0))
+                    .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
+                text(if value.is_empty() { "—".to_string() } else { value.to_string() }).size(13),
+            ]
+            .spacing(10)
+        };
+
+        let dependencies = if details.dependencies.is_empty() {
+            String::new()
+        } else {
+            details.dependencies.join(", ")
+        };
+
+        let panel = column![
+            row![back_btn, row![].width(Length::Fill), action_btn]
+                .align_items(alignment::Alignment::Center),
+            text(&details.name).size(22),
+            text(&details.description).size(14),
+            field("Version", &details.version),
+            field("Installed size", &details.size),
+            field("Depends on", &dependencies),
+            field("Project URL", &details.url),
+        ]
+        .spacing(12)
+        .padding(15);
+
+        container(scrollable(panel))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(|theme: &iced::Theme| {
+                let palette = theme.extended_palette();
+                container::Appearance {
+                    background: Some(iced::Background::Color(palette.background.weak.color)),
+                    border: iced::Border::with_radius(4),
+                    ..Default::default()
+                }
+            })
+            .into()
+    }
+
     fn create_status_bar(&self) -> Element<Message> {
         let status_text = if let Some(error) = &self.error {
             text(error).style(theme::Text::Color(iced::Color::from_rgb(1.0, 0.4, 0.4)))
@@ -331,89 +809,59 @@ impl PackageManager {
     }
 }
 
-async fn search_packages(query: String) -> Result<Vec<Package>, String> {
-    let output = TokioCommand::new("apk")
-        .args(&["search", "-v", &query])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to search packages: {}", e))?;
-
-    if !output.status.success() {
-        return Err(String::from_utf8_lossy(&output.stderr).to_string());
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut packages = Vec::new();
-
-    for line in stdout.lines() {
-        if let Some((name_version, _)) = line.split_once(" - ") {
-            let (name, version) = if let Some(pos) = name_version.rfind('-') {
-                (&name_version[..pos], &name_version[pos + 1..])
-            } else {
-                (name_version, "")
-            };
-
-            // Check if installed
-            let installed = check_if_installed(name);
-
-            packages.push(Package {
-                name: name.to_string(),
-                version: version.to_string(),
-                description: line.split(" - ").nth(1).unwrap_or("").to_string(),
-                installed,
-            });
-        }
-    }
-
-    Ok(packages)
-}
-
-async fn install_package(package_name: String) -> Result<String, String> {
-    let output = TokioCommand::new("sudo")
-        .args(&["apk", "add", &package_name])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to install package: {}", e))?;
-
-    if output.status.success() {
-        Ok(format!("Successfully installed {}", package_name))
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
-}
-
-async fn remove_package(package_name: String) -> Result<String, String> {
-    let output = TokioCommand::new("sudo")
-        .args(&["apk", "del", &package_name])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to remove package: {}", e))?;
-
-    if output.status.success() {
-        Ok(format!("Successfully removed {}", package_name))
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
+/// The confirmation prompt's summary of an install's dry run.
+fn preview_lines(preview: &InstallPreview) -> Vec<String> {
+    let mut lines = Vec::new();
+    match preview.packages.len() {
+        0 => lines.push("Nothing new to install".to_string()),
+        1 => lines.push(format!("Installs {}", preview.packages[0])),
+        count => lines.push(format!("Installs {} packages: {}", count, preview.packages.join(", "))),
     }
+    lines.extend(preview.download.clone());
+    lines.extend(preview.disk.clone());
+    lines
 }
 
-async fn update_cache() -> Result<String, String> {
-    let output = TokioCommand::new("sudo")
-        .args(&["apk", "update"])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to update cache: {}", e))?;
-
-    if output.status.success() {
-        Ok("Package cache updated successfully".to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
-}
+/// Runs a privileged operation and reports its result. Installs stream each
+/// line of the backend's output back as `InstallProgress` along the way.
+fn run_operation(
+    id: u64,
+    operation: Operation,
+    elevation: Elevation,
+    backend: Arc<dyn PackageBackend>,
+) -> Subscription<Message> {
+    subscription::channel(id, 100, move |mut output| async move {
+        let result = match operation {
+            Operation::Install(package_name) => {
+                let (progress, mut lines) = tokio::sync::mpsc::unbounded_channel();
+                let install = tokio::time::timeout(
+                    SUDO_TIMEOUT,
+                    backend.install(package_name, elevation, progress),
+                );
+                // Lines are passed on as they arrive, until the install is
+                // done and drops its end of the channel
+                let mut forward = output.clone();
+                let forward = async move {
+                    while let Some(line) = lines.recv().await {
+                        let _ = forward.send(Message::InstallProgress(line)).await;
+                    }
+                };
+                let (result, ()) = tokio::join!(install, forward);
+                result.unwrap_or_else(|_| Err("Timed out waiting for sudo".to_string()))
+            }
+            Operation::Remove(package_name) => backend.remove(package_name, elevation).await,
+            Operation::UpdateCache => backend.update(elevation).await,
+        };
+        let _ = output
+            .send(match result {
+                Ok(msg) => Message::OperationComplete(msg),
+                Err(e) => Message::OperationError(e),
+            })
+            .await;
 
-fn check_if_installed(package_name: &str) -> bool {
-    ProcessCommand::new("apk")
-        .args(&["info", "-e", package_name])
-        .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false)
+        // The subscription is dropped once the result is handled
+        loop {
+            iced::futures::future::pending::<()>().await;
+        }
+    })
 }
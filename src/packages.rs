@@ -0,0 +1,1149 @@
+//! What the package manager knows about packages, the backends that run
+//! apk and apt to find out, and the parsers that read their output, without
+//! any UI.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::process::{Output, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command as TokioCommand};
+use tokio::sync::mpsc;
+
+/// A boxed future, as `PackageBackend` methods return.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub installed: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PackageDetails {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub size: String,
+    pub url: String,
+    pub dependencies: Vec<String>,
+}
+
+/// What an install would change, from a dry run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstallPreview {
+    /// Packages that would be installed or upgraded, dependencies included.
+    pub packages: Vec<String>,
+    /// The backend's note on the download, e.g. "Need to get 1,024 kB of
+    /// archives", when it gives one.
+    pub download: Option<String>,
+    /// The backend's note on disk use, e.g. "After this operation, 5 MiB of
+    /// additional disk space will be used", when it gives one.
+    pub disk: Option<String>,
+}
+
+/// Checks a name against the characters apk and apt allow in package names
+/// before it is handed to a privileged command. A leading `-` would be read
+/// as an option, so names must start with a letter or digit.
+pub fn validate_package_name(name: &str) -> Result<(), String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid package name: {:?}", name))
+    }
+}
+
+/// Orders search results with an exact name match first, then installed
+/// packages, then everything else, each group alphabetically.
+pub fn sort_results(packages: &mut [Package], query: &str) {
+    let query = query.trim();
+    packages.sort_by(|a, b| {
+        (a.name != query)
+            .cmp(&(b.name != query))
+            .then_with(|| b.installed.cmp(&a.installed))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+/// Splits a line of `apk search -v` output into name, version and
+/// description, e.g. `py3-requests-2.31.0-r0 - HTTP library for Python`.
+///
+/// Names may contain hyphens, so the version is located by its shape: the
+/// last two hyphen-separated parts are `<version>-r<N>` with a version that
+/// starts with a digit.
+pub fn parse_search_line(line: &str) -> Option<(&str, &str, &str)> {
+    let (name_version, description) = line.split_once(" - ")?;
+    let name_version = name_version.trim();
+
+    let mut parts = name_version.rsplitn(3, '-');
+    let release = parts.next()?;
+    let version = parts.next();
+    let name = parts.next();
+
+    let is_release = release
+        .strip_prefix('r')
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+
+    match (name, version) {
+        (Some(name), Some(version))
+            if is_release && version.starts_with(|c: char| c.is_ascii_digit()) =>
+        {
+            let version_start = name.len() + 1;
+            Some((name, &name_version[version_start..], description.trim()))
+        }
+        // Not the usual shape; fall back to splitting on the last hyphen
+        _ => match name_version.rsplit_once('-') {
+            Some((name, version)) => Some((name, version, description.trim())),
+            None => Some((name_version, "", description.trim())),
+        },
+    }
+}
+
+/// Parses `apk info` output, which is a series of sections headed by
+/// `<name>-<version> <field>:` lines and separated by blank lines.
+pub fn parse_apk_info(package_name: &str, output: &str) -> PackageDetails {
+    let mut details = PackageDetails {
+        name: package_name.to_string(),
+        ..Default::default()
+    };
+    let mut field = "";
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_suffix(':') {
+            if let Some((package, name)) = header.split_once(' ') {
+                if let Some(version) = package.strip_prefix(&format!("{}-", package_name)) {
+                    details.version = version.to_string();
+                }
+                field = match name {
+                    "description" => "description",
+                    "webpage" => "url",
+                    "installed size" => "size",
+                    "depends on" => "dependencies",
+                    _ => "",
+                };
+                continue;
+            }
+        }
+
+        match field {
+            "description" => details.description = line.to_string(),
+            "url" => details.url = line.to_string(),
+            "size" => details.size = line.to_string(),
+            "dependencies" => details.dependencies.push(line.to_string()),
+            _ => {}
+        }
+    }
+
+    details
+}
+
+/// Parses one record of `apt-cache show` output, a series of `Field: value`
+/// lines where indented lines continue the previous field.
+pub fn parse_apt_record(record: &str) -> PackageDetails {
+    let mut details = PackageDetails::default();
+
+    for line in record.lines() {
+        if line.starts_with([' ', '\t']) {
+            // Only the summary line of the description is shown
+            continue;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name {
+            "Package" => details.name = value.to_string(),
+            "Version" => details.version = value.to_string(),
+            "Description" | "Description-en" => details.description = value.to_string(),
+            "Homepage" => details.url = value.to_string(),
+            "Installed-Size" => details.size = format!("{} KiB", value),
+            "Depends" => {
+                details.dependencies = value
+                    .split(',')
+                    .map(|dependency| dependency.trim().to_string())
+                    .filter(|dependency| !dependency.is_empty())
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+
+    details
+}
+
+/// Groups apk's progress lines, such as `(1/3) Installing libbar (1.0-r0)`
+/// or `(1/1) Purging foo (1.2-r0)`, into `Installed: libbar; Removed: foo`.
+pub fn summarize_changes(apk_output: &str) -> String {
+    const ACTIONS: &[(&str, &str)] = &[
+        ("Installing", "Installed"),
+        ("Upgrading", "Upgraded"),
+        ("Downgrading", "Downgraded"),
+        ("Reinstalling", "Reinstalled"),
+        ("Replacing", "Replaced"),
+        ("Purging", "Removed"),
+        ("Deinstalling", "Removed"),
+    ];
+    let mut changes: Vec<(&str, Vec<&str>)> = Vec::new();
+
+    for line in apk_output.lines() {
+        let Some((counter, rest)) = line.trim().split_once(' ') else {
+            continue;
+        };
+        if !(counter.starts_with('(') && counter.ends_with(')')) {
+            continue;
+        }
+        let mut words = rest.split_whitespace();
+        let (Some(verb), Some(package)) = (words.next(), words.next()) else {
+            continue;
+        };
+        let Some(&(_, label)) = ACTIONS.iter().find(|(action, _)| *action == verb) else {
+            continue;
+        };
+
+        match changes.iter_mut().find(|(existing, _)| *existing == label) {
+            Some((_, packages)) => packages.push(package),
+            None => changes.push((label, vec![package])),
+        }
+    }
+
+    changes
+        .iter()
+        .map(|(label, packages)| format!("{}: {}", label, packages.join(", ")))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Parses the dry-run output of `apk add --simulate -v` or `apt-get install
+/// --simulate`. Packages come from apk's `(1/3) Installing libbar (1.0-r0)`
+/// and apt's `Inst libbar (1.0 ...)` lines, and the sizes from the
+/// "Need to get" and "After this operation" lines either may print.
+pub fn parse_install_preview(output: &str) -> InstallPreview {
+    let mut preview = InstallPreview::default();
+
+    for line in output.lines() {
+        let line = line.trim();
+        let mut words = line.split_whitespace();
+
+        let package = match words.next() {
+            Some("Inst") => words.next(),
+            Some(counter) if counter.starts_with('(') && counter.ends_with(')') => {
+                match words.next() {
+                    Some("Installing" | "Upgrading") => words.next(),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        if let Some(package) = package {
+            preview.packages.push(package.to_string());
+        } else if line.starts_with("Need to get ") {
+            preview.download = Some(line.trim_end_matches('.').to_string());
+        } else if line.starts_with("After this operation, ") {
+            preview.disk = Some(line.trim_end_matches('.').to_string());
+        }
+    }
+
+    preview
+}
+
+/// How privileged commands gain root.
+#[derive(Debug, Clone)]
+pub enum Elevation {
+    /// `sudo`, optionally fed a password on stdin.
+    Sudo(Option<String>),
+    /// `pkexec`, which shows the polkit authentication dialog itself.
+    Pkexec,
+}
+
+/// How long a privileged operation may run before it is abandoned.
+pub const SUDO_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A package system the package manager can drive. The futures are
+/// `'static` so the UI can run them without borrowing the backend.
+pub trait PackageBackend: Send + Sync {
+    /// Packages matching `query`. Whether each is installed is filled in
+    /// from `list_installed`.
+    fn search(&self, query: String) -> BoxFuture<'static, Result<Vec<Package>, String>>;
+    fn details(&self, package_name: String) -> BoxFuture<'static, Result<PackageDetails, String>>;
+    /// Installs a package, sending each line of output to `progress`.
+    fn install(
+        &self,
+        package_name: String,
+        elevation: Elevation,
+        progress: mpsc::UnboundedSender<String>,
+    ) -> BoxFuture<'static, Result<String, String>>;
+    fn remove(&self, package_name: String, elevation: Elevation) -> BoxFuture<'static, Result<String, String>>;
+    /// Refreshes the package index.
+    fn update(&self, elevation: Elevation) -> BoxFuture<'static, Result<String, String>>;
+    fn list_installed(&self) -> BoxFuture<'static, Result<Vec<Package>, String>>;
+    /// Dry-runs an install, which needs no privileges.
+    fn preview_install(&self, package_name: String) -> BoxFuture<'static, Result<InstallPreview, String>>;
+}
+
+/// Searches the backend named `backend` (see `backend_for`) for `query`,
+/// with results marked installed and ordered as the package manager lists
+/// them. Also behind `min-desk --search`.
+pub async fn search_packages(backend: &str, query: String) -> Result<Vec<Package>, String> {
+    search_with(backend_for(backend).as_ref(), query).await
+}
+
+/// Searches `backend`, marking which results are installed.
+pub fn search_with(
+    backend: &dyn PackageBackend,
+    query: String,
+) -> impl Future<Output = Result<Vec<Package>, String>> + 'static {
+    let search = backend.search(query.clone());
+    let installed = backend.list_installed();
+
+    async move {
+        let mut packages = search.await?;
+        let installed = installed_names(installed.await);
+        for package in &mut packages {
+            package.installed = installed.contains(&package.name);
+        }
+        sort_results(&mut packages, &query);
+        Ok(packages)
+    }
+}
+
+/// Picks the backend named in the config, falling back to apk for names
+/// that aren't supported.
+pub fn backend_for(name: &str) -> Arc<dyn PackageBackend> {
+    match name {
+        "apk" => Arc::new(Apk),
+        "apt" => Arc::new(Apt),
+        _ => {
+            log::warn!("Unknown package backend {:?}, using apk", name);
+            Arc::new(Apk)
+        }
+    }
+}
+
+/// Alpine's apk.
+struct Apk;
+
+impl PackageBackend for Apk {
+    fn search(&self, query: String) -> BoxFuture<'static, Result<Vec<Package>, String>> {
+        Box::pin(async move {
+            let output = TokioCommand::new("apk")
+                .args(["search", "-v", &query])
+                .output()
+                .await
+                .map_err(|e| format!("Failed to search packages: {}", e))?;
+
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+            }
+
+            Ok(String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(parse_search_line)
+                .map(|(name, version, description)| Package {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    description: description.to_string(),
+                    installed: false,
+                })
+                .collect())
+        })
+    }
+
+    fn details(&self, package_name: String) -> BoxFuture<'static, Result<PackageDetails, String>> {
+        Box::pin(async move {
+            let output = TokioCommand::new("apk")
+                .args(["info", "-d", "-w", "-s", "-R", &package_name])
+                .output()
+                .await
+                .map_err(|e| format!("Failed to query package: {}", e))?;
+
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+            }
+
+            Ok(parse_apk_info(&package_name, &String::from_utf8_lossy(&output.stdout)))
+        })
+    }
+
+    fn install(
+        &self,
+        package_name: String,
+        elevation: Elevation,
+        progress: mpsc::UnboundedSender<String>,
+    ) -> BoxFuture<'static, Result<String, String>> {
+        Box::pin(async move {
+            let (result, log) = stream_privileged(&["apk", "add", &package_name], elevation, &progress)
+                .await
+                .map_err(|e| format!("Failed to install package: {}", e))?;
+
+            if result.status.success() {
+                Ok(with_changes(format!("Successfully installed {}", package_name), &log))
+            } else {
+                Err(sudo_error(&result))
+            }
+        })
+    }
+
+    fn remove(&self, package_name: String, elevation: Elevation) -> BoxFuture<'static, Result<String, String>> {
+        Box::pin(async move {
+            let output = run_privileged(&["apk", "del", &package_name], elevation)
+                .await
+                .map_err(|e| format!("Failed to remove package: {}", e))?;
+
+            if output.status.success() {
+                Ok(with_changes(
+                    format!("Successfully removed {}", package_name),
+                    &String::from_utf8_lossy(&output.stdout),
+                ))
+            } else {
+                Err(sudo_error(&output))
+            }
+        })
+    }
+
+    fn update(&self, elevation: Elevation) -> BoxFuture<'static, Result<String, String>> {
+        Box::pin(privileged_update(&["apk", "update"], elevation))
+    }
+
+    fn list_installed(&self) -> BoxFuture<'static, Result<Vec<Package>, String>> {
+        Box::pin(async {
+            // -vv prints one `<name>-<version> - <description>` line each
+            let output = TokioCommand::new("apk")
+                .args(["info", "-vv"])
+                .output()
+                .await
+                .map_err(|e| format!("Failed to list installed packages: {}", e))?;
+
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+            }
+
+            Ok(String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(parse_search_line)
+                .map(|(name, version, description)| Package {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    description: description.to_string(),
+                    installed: true,
+                })
+                .collect())
+        })
+    }
+
+    fn preview_install(&self, package_name: String) -> BoxFuture<'static, Result<InstallPreview, String>> {
+        Box::pin(async move {
+            // -v adds the "After this operation" disk use line
+            let output = TokioCommand::new("apk")
+                .args(["add", "--simulate", "-v", &package_name])
+                .output()
+                .await
+                .map_err(|e| format!("Failed to simulate install: {}", e))?;
+
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+            }
+
+            Ok(parse_install_preview(&String::from_utf8_lossy(&output.stdout)))
+        })
+    }
+}
+
+/// Debian's apt, driven through `apt-cache`, `apt-get` and `dpkg-query`.
+struct Apt;
+
+impl PackageBackend for Apt {
+    fn search(&self, query: String) -> BoxFuture<'static, Result<Vec<Package>, String>> {
+        Box::pin(async move {
+            // --full prints whole records, which include the version
+            let output = TokioCommand::new("apt-cache")
+                .args(["search", "--full", &query])
+                .output()
+                .await
+                .map_err(|e| format!("Failed to search packages: {}", e))?;
+
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+            }
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let mut packages: Vec<Package> = Vec::new();
+            for record in stdout.split("\n\n") {
+                let details = parse_apt_record(record);
+                // Every available version gets its own record; keep the first
+                if details.name.is_empty() || packages.iter().any(|package| package.name == details.name) {
+                    continue;
+                }
+                packages.push(Package {
+                    name: details.name,
+                    version: details.version,
+                    description: details.description,
+                    installed: false,
+                });
+            }
+
+            Ok(packages)
+        })
+    }
+
+    fn details(&self, package_name: String) -> BoxFuture<'static, Result<PackageDetails, String>> {
+        Box::pin(async move {
+            let output = TokioCommand::new("apt-cache")
+                .args(["show", &package_name])
+                .output()
+                .await
+                .map_err(|e| format!("Failed to query package: {}", e))?;
+
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+            }
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Ok(parse_apt_record(stdout.split("\n\n").next().unwrap_or("")))
+        })
+    }
+
+    fn install(
+        &self,
+        package_name: String,
+        elevation: Elevation,
+        progress: mpsc::UnboundedSender<String>,
+    ) -> BoxFuture<'static, Result<String, String>> {
+        Box::pin(async move {
+            let (result, _) = stream_privileged(
+                &["apt-get", "install", "-y", &package_name],
+                elevation,
+                &progress,
+            )
+            .await
+            .map_err(|e| format!("Failed to install package: {}", e))?;
+
+            if result.status.success() {
+                Ok(format!("Successfully installed {}", package_name))
+            } else {
+                Err(sudo_error(&result))
+            }
+        })
+    }
+
+    fn remove(&self, package_name: String, elevation: Elevation) -> BoxFuture<'static, Result<String, String>> {
+        Box::pin(async move {
+            let output = run_privileged(&["apt-get", "remove", "-y", &package_name], elevation)
+                .await
+                .map_err(|e| format!("Failed to remove package: {}", e))?;
+
+            if output.status.success() {
+                Ok(format!("Successfully removed {}", package_name))
+            } else {
+                Err(sudo_error(&output))
+            }
+        })
+    }
+
+    fn update(&self, elevation: Elevation) -> BoxFuture<'static, Result<String, String>> {
+        Box::pin(privileged_update(&["apt-get", "update"], elevation))
+    }
+
+    fn list_installed(&self) -> BoxFuture<'static, Result<Vec<Package>, String>> {
+        Box::pin(async {
+            // Packages removed with their config files left behind are listed
+            // too, so only `ii` (installed) entries count
+            let output = TokioCommand::new("dpkg-query")
+                .args(["-W", "-f", "${db:Status-Abbrev}\t${Package}\t${Version}\t${binary:Summary}\n"])
+                .output()
+                .await
+                .map_err(|e| format!("Failed to list installed packages: {}", e))?;
+
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+            }
+
+            Ok(String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.split('\t');
+                    let status = fields.next()?;
+                    let name = fields.next()?;
+                    let version = fields.next()?;
+                    let description = fields.next().unwrap_or("");
+                    status.starts_with("ii").then(|| Package {
+                        name: name.to_string(),
+                        version: version.to_string(),
+                        description: description.to_string(),
+                        installed: true,
+                    })
+                })
+                .collect())
+        })
+    }
+
+    fn preview_install(&self, package_name: String) -> BoxFuture<'static, Result<InstallPreview, String>> {
+        Box::pin(async move {
+            let output = TokioCommand::new("apt-get")
+                .args(["install", "--simulate", &package_name])
+                .output()
+                .await
+                .map_err(|e| format!("Failed to simulate install: {}", e))?;
+
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+            }
+
+            Ok(parse_install_preview(&String::from_utf8_lossy(&output.stdout)))
+        })
+    }
+}
+
+/// Names from a backend's installed list. Search results still show if the
+/// list can't be read; they just aren't marked installed.
+pub fn installed_names(installed: Result<Vec<Package>, String>) -> HashSet<String> {
+    installed
+        .unwrap_or_default()
+        .into_iter()
+        .map(|package| package.name)
+        .collect()
+}
+
+/// Appends the packages apk reports changing, e.g.
+/// `Successfully installed foo - Installed: foo, libbar`, so dependencies
+/// pulled in or removed along the way are visible.
+fn with_changes(message: String, apk_output: &str) -> String {
+    let changes = summarize_changes(apk_output);
+    if changes.is_empty() {
+        message
+    } else {
+        format!("{} - {}", message, changes)
+    }
+}
+
+/// Runs a backend's index refresh as root.
+async fn privileged_update(args: &[&str], elevation: Elevation) -> Result<String, String> {
+    let output = run_privileged(args, elevation)
+        .await
+        .map_err(|e| format!("Failed to update cache: {}", e))?;
+
+    if output.status.success() {
+        Ok("Package cache updated successfully".to_string())
+    } else {
+        Err(sudo_error(&output))
+    }
+}
+
+/// Runs `args` as root, sending each line of stdout to `progress`.
+/// Returns the finished process along with everything it printed.
+async fn stream_privileged(
+    args: &[&str],
+    elevation: Elevation,
+    progress: &mpsc::UnboundedSender<String>,
+) -> Result<(Output, String), String> {
+    let mut child = spawn_privileged(args, elevation).await?;
+
+    let mut log = String::new();
+    if let Some(stdout) = child.stdout.take() {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            log.push_str(&line);
+            log.push('\n');
+            let _ = progress.send(line);
+        }
+    }
+
+    let output = child.wait_with_output().await.map_err(|e| e.to_string())?;
+    Ok((output, log))
+}
+
+/// Whether `sudo` can run without prompting for a password.
+pub async fn check_passwordless_sudo() -> bool {
+    TokioCommand::new("sudo")
+        .args(["-n", "true"])
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Spawns `args` as root. For sudo with a password it is written to sudo's
+/// stdin (`-S`); without one sudo runs non-interactively (`-n`) so it fails
+/// fast instead of blocking on a prompt nobody can answer. pkexec handles
+/// authentication through the graphical polkit agent.
+async fn spawn_privileged(args: &[&str], elevation: Elevation) -> Result<Child, String> {
+    let (mut command, password) = match elevation {
+        Elevation::Sudo(password) => {
+            let mut command = TokioCommand::new("sudo");
+            if password.is_some() {
+                command.args(["-S", "-p", ""]);
+            } else {
+                command.arg("-n");
+            }
+            (command, password)
+        }
+        Elevation::Pkexec => (TokioCommand::new("pkexec"), None),
+    };
+
+    let mut child = command
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // Dropping stdin after writing closes it, so a wrong password can't hang
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Some(password) = password {
+            stdin
+                .write_all(format!("{}\n", password).as_bytes())
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(child)
+}
+
+async fn run_privileged(args: &[&str], elevation: Elevation) -> Result<Output, String> {
+    let child = spawn_privileged(args, elevation).await?;
+    tokio::time::timeout(SUDO_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| "Timed out waiting for sudo".to_string())?
+        .map_err(|e| e.to_string())
+}
+
+fn sudo_error(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if stderr.contains("Request dismissed") || stderr.contains("Not authorized") {
+        "Authorization was cancelled or denied".to_string()
+    } else if stderr.contains("password is required") {
+        "sudo requires a password - enter it in the header and retry".to_string()
+    } else if stderr.contains("incorrect password") || stderr.contains("Sorry, try again") {
+        "Incorrect sudo password".to_string()
+    } else {
+        stderr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines of `apk search -v` output from Alpine 3.19.
+    const APK_SEARCH: &str = "\
+busybox-1.36.1-r15 - Size optimized toolbox of many common UNIX utilities
+py3-requests-2.31.0-r1 - HTTP request for Python
+xf86-video-intel-2.99.917_git20221028-r5 - Xorg driver for Intel graphics
+perl-xml-libxml-2.0209-r0 - Perl Binding for libxml2
+gtk+3.0-3.24.41-r0 - The GTK+ Toolkit (v3)
+font-noto-cjk-20230817-r0 - Noto CJK fonts - Sans and Serif
+";
+
+    #[test]
+    fn apk_search_lines_split_at_the_version() {
+        let parsed: Vec<_> = APK_SEARCH.lines().filter_map(parse_search_line).collect();
+        assert_eq!(
+            parsed,
+            [
+                ("busybox", "1.36.1-r15", "Size optimized toolbox of many common UNIX utilities"),
+                ("py3-requests", "2.31.0-r1", "HTTP request for Python"),
+                ("xf86-video-intel", "2.99.917_git20221028-r5", "Xorg driver for Intel graphics"),
+                ("perl-xml-libxml", "2.0209-r0", "Perl Binding for libxml2"),
+                ("gtk+3.0", "3.24.41-r0", "The GTK+ Toolkit (v3)"),
+                // Only the first " - " separates the description
+                ("font-noto-cjk", "20230817-r0", "Noto CJK fonts - Sans and Serif"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unusual_search_lines_fall_back_to_the_last_hyphen() {
+        assert_eq!(parse_search_line("foo-bar - Odd"), Some(("foo", "bar", "Odd")));
+        // A name ending in a number isn't mistaken for the version
+        assert_eq!(parse_search_line("lib2-r0 - Odd"), Some(("lib2", "r0", "Odd")));
+        assert_eq!(parse_search_line("plain - No version"), Some(("plain", "", "No version")));
+        assert_eq!(parse_search_line("WARNING: opening /var/cache: No such file"), None);
+    }
+
+    fn package(name: &str, installed: bool) -> Package {
+        Package {
+            name: name.to_string(),
+            version: "1.0-r0".to_string(),
+            description: String::new(),
+            installed,
+        }
+    }
+
+    fn sorted(mut packages: Vec<Package>, query: &str) -> Vec<String> {
+        sort_results(&mut packages, query);
+        packages.into_iter().map(|package| package.name).collect()
+    }
+
+    #[test]
+    fn exact_match_comes_first_then_installed() {
+        let packages = vec![
+            package("vim-doc", false),
+            package("neovim", true),
+            package("gvim", false),
+            package("vim", false),
+            package("vim-airline", true),
+        ];
+        assert_eq!(sorted(packages, "vim"), ["vim", "neovim", "vim-airline", "gvim", "vim-doc"]);
+    }
+
+    #[test]
+    fn installed_exact_match_and_no_match() {
+        let packages = vec![package("b", false), package("curl", true), package("a", true)];
+        // Surrounding spaces in the query don't stop the exact match
+        assert_eq!(sorted(packages.clone(), " curl "), ["curl", "a", "b"]);
+        assert_eq!(sorted(packages, "zzz"), ["a", "curl", "b"]);
+        assert!(sorted(Vec::new(), "vim").is_empty());
+    }
+
+    #[test]
+    fn apk_info_sections_fill_in_the_details() {
+        let output = "\
+curl-8.5.0-r0 description:
+URL retrieval utility and library
+
+curl-8.5.0-r0 webpage:
+https://curl.se/
+
+curl-8.5.0-r0 installed size:
+256 KiB
+
+curl-8.5.0-r0 depends on:
+ca-certificates
+so:libc.musl-x86_64.so.1
+so:libcurl.so.4
+
+";
+        let details = parse_apk_info("curl", output);
+        assert_eq!(details.name, "curl");
+        assert_eq!(details.version, "8.5.0-r0");
+        assert_eq!(details.description, "URL retrieval utility and library");
+        assert_eq!(details.url, "https://curl.se/");
+        assert_eq!(details.size, "256 KiB");
+        assert_eq!(
+            details.dependencies,
+            ["ca-certificates", "so:libc.musl-x86_64.so.1", "so:libcurl.so.4"]
+        );
+    }
+
+    #[test]
+    fn apk_info_of_a_hyphenated_name() {
+        let output = "py3-requests-2.31.0-r1 description:\nHTTP request for Python\n";
+        let details = parse_apk_info("py3-requests", output);
+        assert_eq!(details.version, "2.31.0-r1");
+        assert_eq!(details.description, "HTTP request for Python");
+        assert!(details.dependencies.is_empty());
+    }
+
+    #[test]
+    fn apt_record_fields_and_continuations() {
+        let record = "\
+Package: curl
+Version: 7.88.1-10+deb12u5
+Installed-Size: 500
+Depends: libc6 (>= 2.34), libcurl4 (= 7.88.1-10+deb12u5), zlib1g (>= 1:1.1.4)
+Homepage: https://curl.se/
+Description-en: command line tool for transferring data with URL syntax
+ curl is a command line tool for transferring data with URL syntax,
+ supporting DICT, FILE, FTP, FTPS, GOPHER, HTTP and more.
+Description-md5: 0f0f6e8a9a3b6b05e1e9e1b0e8b5a0e1
+";
+        let details = parse_apt_record(record);
+        assert_eq!(details.name, "curl");
+        assert_eq!(details.version, "7.88.1-10+deb12u5");
+        assert_eq!(details.size, "500 KiB");
+        assert_eq!(details.url, "https://curl.se/");
+        assert_eq!(details.description, "command line tool for transferring data with URL syntax");
+        assert_eq!(
+            details.dependencies,
+            ["libc6 (>= 2.34)", "libcurl4 (= 7.88.1-10+deb12u5)", "zlib1g (>= 1:1.1.4)"]
+        );
+    }
+
+    #[test]
+    fn apk_changes_are_grouped_by_action() {
+        let install = "\
+fetch https://dl-cdn.alpinelinux.org/alpine/v3.19/main/x86_64/APKINDEX.tar.gz
+(1/3) Installing ca-certificates (20240226-r0)
+(2/3) Installing libcurl (8.5.0-r0)
+(3/3) Installing curl (8.5.0-r0)
+Executing busybox-1.36.1-r15.trigger
+OK: 12 MiB in 18 packages
+";
+        assert_eq!(summarize_changes(install), "Installed: ca-certificates, libcurl, curl");
+
+        let mixed = "\
+(1/4) Upgrading musl (1.2.4_git20230717-r4 -> 1.2.4_git20230717-r5)
+(2/4) Purging curl (8.5.0-r0)
+(3/4) Installing wget (1.21.4-r0)
+(4/4) Deinstalling libcurl (8.5.0-r0)
+";
+        assert_eq!(
+            summarize_changes(mixed),
+            "Upgraded: musl; Removed: curl, libcurl; Installed: wget"
+        );
+        assert_eq!(summarize_changes("OK: 12 MiB in 18 packages"), "");
+    }
+
+    #[test]
+    fn apk_preview_lists_new_and_upgraded_packages() {
+        // `apk add --simulate -v htop` on Alpine 3.19
+        let output = "\
+fetch https://dl-cdn.alpinelinux.org/alpine/v3.19/main/x86_64/APKINDEX.tar.gz
+The following NEW packages will be installed:
+  libncursesw ncurses-terminfo-base htop
+The following packages will be upgraded:
+  musl
+After this operation, 580 KiB of additional disk space will be used.
+(1/4) Upgrading musl (1.2.4_git20230717-r4 -> 1.2.4_git20230717-r5)
+(2/4) Installing ncurses-terminfo-base (6.4_p20231125-r0)
+(3/4) Installing libncursesw (6.4_p20231125-r0)
+(4/4) Installing htop (3.2.2-r1)
+Executing busybox-1.36.1-r15.trigger
+OK: 9 MiB in 19 packages
+";
+        assert_eq!(
+            parse_install_preview(output),
+            InstallPreview {
+                packages: vec![
+                    "musl".to_string(),
+                    "ncurses-terminfo-base".to_string(),
+                    "libncursesw".to_string(),
+                    "htop".to_string(),
+                ],
+                download: None,
+                disk: Some(
+                    "After this operation, 580 KiB of additional disk space will be used".to_string()
+                ),
+            }
+        );
+    }
+
+    #[test]
+    fn apt_preview_lists_inst_lines() {
+        // `apt-get install --simulate htop` on Debian 12 as a normal user
+        let output = "\
+NOTE: This is only a simulation!
+      apt-get needs root privileges for real execution.
+      Keep also in mind that locking is deactivated,
+      so don't depend on the relevance to the real current situation!
+Reading package lists...
+Building dependency tree...
+Reading state information...
+The following additional packages will be installed:
+  libnl-3-200 libnl-genl-3-200
+Suggested packages:
+  lm-sensors lsof strace
+The following NEW packages will be installed:
+  htop libnl-3-200 libnl-genl-3-200
+The following packages will be upgraded:
+  libc6
+1 upgraded, 3 newly installed, 0 to remove and 12 not upgraded.
+Inst libc6 [2.36-9+deb12u3] (2.36-9+deb12u4 Debian:12.5/stable [amd64])
+Inst libnl-3-200 (3.7.0-0.2+b1 Debian:12.5/stable [amd64])
+Inst libnl-genl-3-200 (3.7.0-0.2+b1 Debian:12.5/stable [amd64])
+Inst htop (3.2.2-2 Debian:12.5/stable [amd64])
+Conf libc6 (2.36-9+deb12u4 Debian:12.5/stable [amd64])
+Conf libnl-3-200 (3.7.0-0.2+b1 Debian:12.5/stable [amd64])
+Conf libnl-genl-3-200 (3.7.0-0.2+b1 Debian:12.5/stable [amd64])
+Conf htop (3.2.2-2 Debian:12.5/stable [amd64])
+";
+        let preview = parse_install_preview(output);
+        assert_eq!(preview.packages, ["libc6", "libnl-3-200", "libnl-genl-3-200", "htop"]);
+        // The simulation stops before apt works out sizes
+        assert_eq!(preview.download, None);
+        assert_eq!(preview.disk, None);
+    }
+
+    #[test]
+    fn preview_sizes_and_nothing_to_do() {
+        let output = "\
+Need to get 1,024 kB of archives.
+After this operation, 3,072 kB of additional disk space will be used.
+";
+        let preview = parse_install_preview(output);
+        assert!(preview.packages.is_empty());
+        assert_eq!(preview.download.as_deref(), Some("Need to get 1,024 kB of archives"));
+        assert_eq!(
+            preview.disk.as_deref(),
+            Some("After this operation, 3,072 kB of additional disk space will be used")
+        );
+
+        // An already installed package: apk just reports the world as is
+        assert_eq!(parse_install_preview("OK: 9 MiB in 19 packages\n"), InstallPreview::default());
+    }
+
+    #[test]
+    fn package_names_that_could_be_options_or_shell_are_rejected() {
+        let names = [
+            "",
+            // Would be read as options
+            "-",
+            "-rf",
+            "--force-broken-world",
+            // Must start with a letter or digit
+            ".hidden",
+            "+x",
+            // Shell metacharacters and paths
+            "foo bar",
+            "foo;rm -rf /",
+            "$(reboot)",
+            "`id`",
+            "a|b",
+            "a&b",
+            "a>b",
+            "foo/bar",
+            "../etc",
+            "foo\n",
+            "naïve",
+        ];
+        for name in names {
+            assert!(validate_package_name(name).is_err(), "{:?} was accepted", name);
+        }
+        assert_eq!(validate_package_name("-rf"), Err("Invalid package name: \"-rf\"".to_string()));
+    }
+
+    #[test]
+    fn real_package_names_are_accepted() {
+        let names = ["py3-foo_bar+1.2", "busybox", "gtk+3.0", "g++", "libstdc++6", "7zip", "xf86-video-intel"];
+        for name in names {
+            assert_eq!(validate_package_name(name), Ok(()), "{:?} was rejected", name);
+        }
+    }
+
+    /// A backend with a fixed catalogue, for testing what's built on the
+    /// trait without apk or apt.
+    struct MockBackend {
+        available: Vec<Package>,
+        installed: Result<Vec<Package>, String>,
+    }
+
+    impl PackageBackend for MockBackend {
+        fn search(&self, query: String) -> BoxFuture<'static, Result<Vec<Package>, String>> {
+            let matches = self
+                .available
+                .iter()
+                .filter(|package| package.name.contains(&query))
+                .cloned()
+                .collect();
+            Box::pin(async move { Ok(matches) })
+        }
+
+        fn details(
+            &self,
+            package_name: String,
+        ) -> BoxFuture<'static, Result<PackageDetails, String>> {
+            Box::pin(async move { Err(format!("no details for {}", package_name)) })
+        }
+
+        fn install(
+            &self,
+            package_name: String,
+            _elevation: Elevation,
+            progress: mpsc::UnboundedSender<String>,
+        ) -> BoxFuture<'static, Result<String, String>> {
+            Box::pin(async move {
+                let _ = progress.send(format!("(1/1) Installing {} (1.0-r0)", package_name));
+                Ok(format!("Successfully installed {}", package_name))
+            })
+        }
+
+        fn remove(
+            &self,
+            package_name: String,
+            _elevation: Elevation,
+        ) -> BoxFuture<'static, Result<String, String>> {
+            Box::pin(async move { Ok(format!("Successfully removed {}", package_name)) })
+        }
+
+        fn update(&self, _elevation: Elevation) -> BoxFuture<'static, Result<String, String>> {
+            Box::pin(async { Ok("Package cache updated successfully".to_string()) })
+        }
+
+        fn list_installed(&self) -> BoxFuture<'static, Result<Vec<Package>, String>> {
+            let installed = self.installed.clone();
+            Box::pin(async move { installed })
+        }
+
+        fn preview_install(
+            &self,
+            _package_name: String,
+        ) -> BoxFuture<'static, Result<InstallPreview, String>> {
+            Box::pin(async { Ok(InstallPreview::default()) })
+        }
+    }
+
+    fn names_and_flags(packages: &[Package]) -> Vec<(&str, bool)> {
+        packages
+            .iter()
+            .map(|package| (package.name.as_str(), package.installed))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn search_marks_installed_packages_and_orders_them() {
+        let backend = MockBackend {
+            available: ["vim-doc", "neovim", "gvim", "vim", "vim-airline", "nano"]
+                .into_iter()
+                .map(|name| package(name, false))
+                .collect(),
+            // Installed packages that don't match the query aren't added
+            installed: Ok(vec![
+                package("neovim", true),
+                package("vim-airline", true),
+                package("nano", true),
+            ]),
+        };
+
+        let packages = search_with(&backend, "vim".to_string()).await.unwrap();
+        assert_eq!(
+            names_and_flags(&packages),
+            [
+                ("vim", false),
+                ("neovim", true),
+                ("vim-airline", true),
+                ("gvim", false),
+                ("vim-doc", false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn search_still_lists_results_when_the_installed_list_fails() {
+        let backend = MockBackend {
+            available: vec![package("curl", false), package("libcurl", false)],
+            installed: Err("apk: database locked".to_string()),
+        };
+
+        let packages = search_with(&backend, "curl".to_string()).await.unwrap();
+        assert_eq!(names_and_flags(&packages), [("curl", false), ("libcurl", false)]);
+    }
+
+    #[tokio::test]
+    async fn search_results_take_the_installed_flag_from_the_list_only() {
+        // A backend whose search already claims a package is installed
+        let backend = MockBackend {
+            available: vec![package("curl", true), package("wget", false)],
+            installed: Ok(vec![package("wget", true)]),
+        };
+
+        let packages = search_with(&backend, "".to_string()).await.unwrap();
+        assert_eq!(names_and_flags(&packages), [("wget", true), ("curl", false)]);
+    }
+}
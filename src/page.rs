@@ -0,0 +1,1259 @@
+//! Fetching web pages and extracting their readable text.
+//!
+//! This is the browser's page pipeline without any UI, so it can be reused
+//! by other tools.
+
+use html5ever::parse_document;
+use html5ever::tendril::TendrilSink;
+use markup5ever_rcdom::{Handle, Node, NodeData, RcDom};
+use crate::fs_ops;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// What a fetch produced: a page to render, or a file to offer for
+/// download.
+#[derive(Debug, Clone)]
+pub enum Fetched {
+    Page(Page),
+    Offer(DownloadOffer),
+}
+
+/// A response that isn't a page, described from its headers. Nothing of
+/// its body has been read; pass it to `download` to save it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadOffer {
+    pub url: String,
+    /// The `Content-Type` header, lowercased.
+    pub content_type: String,
+    /// The `Content-Length` header, if the server sent one.
+    pub size: Option<u64>,
+}
+
+/// A decoded HTML document and the response it came from.
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub html: String,
+    /// `None` for local files, which have no HTTP response.
+    pub info: Option<ResponseInfo>,
+}
+
+/// Details of the response a page was served with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseInfo {
+    pub status: u16,
+    /// The `Content-Type` header, lowercased.
+    pub content_type: String,
+    /// Where the page was actually served from, after redirects.
+    pub final_url: String,
+    /// Size of the response body in bytes.
+    pub size: usize,
+}
+
+/// A block of readable content extracted from a page.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentBlock {
+    Heading(u8, String),
+    Paragraph(String),
+    ListItem(String),
+    /// An `<img>`, with `src` exactly as written in the page.
+    Image { src: String, alt: String },
+}
+
+/// Text extracted from an HTML document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageContent {
+    /// Contents of the `<title>` element, if any.
+    pub title: Option<String>,
+    pub blocks: Vec<ContentBlock>,
+}
+
+/// An anchor found on a page, with its href resolved to an absolute URL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub text: String,
+    pub url: String,
+}
+
+/// A `<meta http-equiv="refresh">` redirect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaRefresh {
+    /// Seconds to wait before redirecting.
+    pub delay: u64,
+    /// The target, resolved to an absolute URL.
+    pub url: String,
+}
+
+/// Proxies to send requests through, picked by the scheme of the URL being
+/// fetched. A scheme without one is fetched directly.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Proxies {
+    pub http: Option<String>,
+    pub https: Option<String>,
+}
+
+impl Proxies {
+    /// Fills in any proxy left unset from the `http_proxy` and
+    /// `https_proxy` environment variables, or their uppercase forms.
+    pub fn or_from_env(self) -> Self {
+        let set = |proxy: &String| !proxy.trim().is_empty();
+        Self {
+            http: self.http.filter(set).or_else(|| proxy_from_env("http_proxy")),
+            https: self.https.filter(set).or_else(|| proxy_from_env("https_proxy")),
+        }
+    }
+
+    /// The proxy a request for `url` goes through, if any.
+    fn for_url(&self, url: &str) -> Option<&str> {
+        let proxy = match reqwest::Url::parse(url).ok()?.scheme() {
+            "http" => self.http.as_deref(),
+            "https" => self.https.as_deref(),
+            _ => None,
+        };
+        proxy.filter(|proxy| !proxy.trim().is_empty())
+    }
+}
+
+fn proxy_from_env(name: &str) -> Option<String> {
+    [name.to_string(), name.to_uppercase()]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+}
+
+/// A proxy's host and port for messages, leaving out any credentials.
+fn proxy_host(proxy: &str) -> String {
+    let with_scheme = if proxy.contains("://") {
+        proxy.to_string()
+    } else {
+        format!("http://{}", proxy)
+    };
+    reqwest::Url::parse(&with_scheme)
+        .ok()
+        .and_then(|url| {
+            let host = url.host_str()?.to_string();
+            Some(match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host,
+            })
+        })
+        .unwrap_or_else(|| "(invalid address)".to_string())
+}
+
+/// Describes a failed request, blaming the proxy when the connection to it
+/// is what failed rather than the site behind it.
+fn request_error(action: &str, url: &str, proxies: &Proxies, error: reqwest::Error) -> String {
+    match proxies.for_url(url) {
+        Some(proxy) if error.is_connect() => {
+            format!("Failed to connect through proxy {}: {}", proxy_host(proxy), error)
+        }
+        _ => format!("Failed to {}: {}", action, error),
+    }
+}
+
+/// How long a request may take to connect and to get the response's
+/// headers. Bodies aren't bound by it, so large downloads can finish.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest a response body may go without sending anything before it's
+/// given up on.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An HTTP client sending requests through `Proxies`. Build one and share
+/// it between requests, which then reuse its connections and TLS sessions.
+#[derive(Debug)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    proxies: Proxies,
+}
+
+impl HttpClient {
+    /// Fails if one of the proxies isn't a valid address.
+    pub fn new(proxies: Proxies) -> Result<Self, String> {
+        // Minimal settings for Alpine compatibility. Proxies only come from
+        // `proxies`, which has already looked at the environment.
+        let mut builder = reqwest::Client::builder()
+            .user_agent("MinDesk/1.0")
+            .connect_timeout(REQUEST_TIMEOUT)
+            .no_proxy();
+
+        // Credentials in a proxy URL are sent as basic auth
+        let http = proxies.http.as_deref().map(str::trim).filter(|proxy| !proxy.is_empty());
+        if let Some(proxy) = http {
+            let proxy = reqwest::Proxy::http(proxy)
+                .map_err(|e| format!("Invalid proxy {}: {}", proxy_host(proxy), e))?;
+            builder = builder.proxy(proxy.no_proxy(reqwest::NoProxy::from_env()));
+        }
+        let https = proxies.https.as_deref().map(str::trim).filter(|proxy| !proxy.is_empty());
+        if let Some(proxy) = https {
+            let proxy = reqwest::Proxy::https(proxy)
+                .map_err(|e| format!("Invalid proxy {}: {}", proxy_host(proxy), e))?;
+            builder = builder.proxy(proxy.no_proxy(reqwest::NoProxy::from_env()));
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(Self { client, proxies })
+    }
+
+    /// The proxies the client was built for.
+    pub fn proxies(&self) -> &Proxies {
+        &self.proxies
+    }
+}
+
+/// Fetches `url`, decoding text documents to a string. Anything else, or
+/// any document over `MAX_PAGE_BYTES`, comes back as an offer without its
+/// body being read. `file://` URLs are read straight from disk.
+pub async fn fetch_page(url: String, client: Arc<HttpClient>) -> Result<Fetched, String> {
+    fetch_page_with_progress(url, client, |_| {}).await
+}
+
+/// Like `fetch_page`, calling `on_progress` with the number of bytes of the
+/// page received so far each time more of its body arrives.
+pub async fn fetch_page_with_progress(
+    url: String,
+    client: Arc<HttpClient>,
+    on_progress: impl FnMut(usize),
+) -> Result<Fetched, String> {
+    let local_path = reqwest::Url::parse(&url)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok());
+    if let Some(path) = local_path {
+        return read_local_page(path).await;
+    }
+
+    let proxies = &client.proxies;
+    let client = &client.client;
+
+    // Ask first so large or binary files aren't requested as pages. Servers
+    // that reject HEAD fall through to the GET, which is checked the same
+    // way before its body is read.
+    let head = client.head(&url).timeout(REQUEST_TIMEOUT).send().await.ok();
+    if let Some(head) = head.filter(|head| head.status().is_success()) {
+        let content_type = header_content_type(&head);
+        let size = header_content_length(&head);
+        if !is_page(&content_type, size) {
+            return Ok(Fetched::Offer(DownloadOffer { url, content_type, size }));
+        }
+    }
+
+    let response = tokio::time::timeout(REQUEST_TIMEOUT, client.get(&url).send())
+        .await
+        .map_err(|_| format!("Failed to fetch page: no answer in {}s", REQUEST_TIMEOUT.as_secs()))?
+        .map_err(|e| request_error("fetch page", &url, proxies, e))?;
+
+    // A plain HTTP proxy answers for the site, so its refusal arrives as a
+    // response rather than a connection error
+    if response.status() == reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+        if let Some(proxy) = proxies.for_url(&url) {
+            return Err(format!("Proxy {} rejected the credentials", proxy_host(proxy)));
+        }
+    }
+
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+
+    let status = response.status().as_u16();
+    let final_url = response.url().to_string();
+    let content_type = header_content_type(&response);
+
+    // Anything that isn't a reasonably sized text document is offered for
+    // download instead of rendered. Dropping the response abandons its body.
+    if !is_page(&content_type, response.content_length()) {
+        return Ok(Fetched::Offer(DownloadOffer {
+            url,
+            content_type,
+            size: response.content_length(),
+        }));
+    }
+
+    let bytes = read_page_body(response, on_progress).await?;
+
+    Ok(Fetched::Page(Page {
+        html: decode_html(&bytes, &content_type),
+        info: Some(ResponseInfo {
+            status,
+            content_type,
+            final_url,
+            size: bytes.len(),
+        }),
+    }))
+}
+
+/// Most of an error response's body that is read for its explanation.
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// Longest excerpt of an error response shown after its status.
+const MAX_ERROR_SNIPPET_CHARS: usize = 300;
+
+/// "HTTP 404: Not Found", followed on the next line by the start of the
+/// text of the server's error page or message, if it sent one.
+async fn status_error(mut response: reqwest::Response) -> String {
+    let status = response.status();
+    let mut error = format!(
+        "HTTP {}: {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("Unknown error")
+    );
+
+    let content_type = header_content_type(&response);
+    let mut bytes = Vec::new();
+    while let Ok(Some(chunk)) = next_chunk(&mut response).await {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() >= MAX_ERROR_BODY_BYTES {
+            break;
+        }
+    }
+    bytes.truncate(MAX_ERROR_BODY_BYTES);
+
+    let body = decode_html(&bytes, &content_type);
+    // API errors are usually JSON or plain text, shown as they are
+    let text = if content_type.contains("html") || body.trim_start().starts_with('<') {
+        extract_text_from_html(&body)
+            .blocks
+            .into_iter()
+            .filter_map(|block| match block {
+                ContentBlock::Heading(_, text)
+                | ContentBlock::Paragraph(text)
+                | ContentBlock::ListItem(text) => Some(text),
+                ContentBlock::Image { .. } => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    } else {
+        collapse_whitespace(&body)
+    };
+
+    if !text.is_empty() {
+        error.push('\n');
+        if text.chars().count() > MAX_ERROR_SNIPPET_CHARS {
+            error.extend(text.chars().take(MAX_ERROR_SNIPPET_CHARS));
+            error.push('…');
+        } else {
+            error.push_str(&text);
+        }
+    }
+    error
+}
+
+/// Largest response `fetch_page` will render; anything bigger is downloaded.
+pub const MAX_PAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// The lowercased `Content-Type` header, or an empty string if there is none.
+fn header_content_type(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// The `Content-Length` header. Unlike `Response::content_length` this is
+/// also right for HEAD responses, which reqwest reports as empty.
+fn header_content_length(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Whether a response should be rendered rather than downloaded. A missing
+/// type or length is given the benefit of the doubt.
+fn is_page(content_type: &str, content_length: Option<u64>) -> bool {
+    let is_text = content_type.is_empty()
+        || content_type.starts_with("text/")
+        || content_type.contains("xhtml");
+    is_text && content_length.unwrap_or(0) <= MAX_PAGE_BYTES as u64
+}
+
+/// Reads a page body, giving up once it passes `MAX_PAGE_BYTES` for
+/// servers that don't send a length up front.
+async fn read_page_body(
+    mut response: reqwest::Response,
+    mut on_progress: impl FnMut(usize),
+) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = next_chunk(&mut response)
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?
+    {
+        bytes.extend_from_slice(&chunk);
+        on_progress(bytes.len());
+        if bytes.len() > MAX_PAGE_BYTES {
+            return Err(format!(
+                "Page is larger than {} MB",
+                MAX_PAGE_BYTES / (1024 * 1024)
+            ));
+        }
+    }
+    Ok(bytes)
+}
+
+/// The next piece of a response body, failing if none arrives within
+/// `READ_TIMEOUT`.
+async fn next_chunk(
+    response: &mut reqwest::Response,
+) -> Result<Option<impl std::ops::Deref<Target = [u8]>>, String> {
+    match tokio::time::timeout(READ_TIMEOUT, response.chunk()).await {
+        Ok(chunk) => chunk.map_err(|e| e.to_string()),
+        Err(_) => Err(format!("nothing received for {}s", READ_TIMEOUT.as_secs())),
+    }
+}
+
+async fn read_local_page(path: PathBuf) -> Result<Fetched, String> {
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+
+    Ok(Fetched::Page(Page {
+        html: decode_html(&bytes, ""),
+        info: None,
+    }))
+}
+
+/// Largest image `fetch_image` will accept.
+pub const MAX_IMAGE_BYTES: usize = 2 * 1024 * 1024;
+
+/// Fetches an image's bytes, refusing anything that isn't an image or is
+/// larger than `MAX_IMAGE_BYTES`.
+pub async fn fetch_image(url: String, client: Arc<HttpClient>) -> Result<Vec<u8>, String> {
+    let mut response = client
+        .client
+        .get(&url)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| request_error("fetch image", &url, &client.proxies, e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status().as_u16()));
+    }
+
+    let is_image = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.to_lowercase().starts_with("image/"));
+    if !is_image {
+        return Err("Not an image".to_string());
+    }
+    if response.content_length().is_some_and(|length| length > MAX_IMAGE_BYTES as u64) {
+        return Err("Image too large".to_string());
+    }
+
+    // Content-Length can be missing or wrong, so the cap is checked as the
+    // body arrives too
+    let mut bytes = Vec::new();
+    while let Some(chunk) = next_chunk(&mut response)
+        .await
+        .map_err(|e| format!("Failed to read image: {}", e))?
+    {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > MAX_IMAGE_BYTES {
+            return Err("Image too large".to_string());
+        }
+    }
+    Ok(bytes)
+}
+
+/// How far into a page to look for a `<meta charset>`, matching the
+/// prescan length browsers use.
+const CHARSET_PRESCAN_LENGTH: usize = 1024;
+
+/// Decodes a page using the charset from the `Content-Type` header, then a
+/// `<meta charset>` tag, falling back to lossy UTF-8. A byte order mark
+/// overrides both.
+fn decode_html(bytes: &[u8], content_type: &str) -> String {
+    let encoding = charset_from_content_type(content_type)
+        .or_else(|| charset_from_meta(bytes))
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+fn charset_from_content_type(content_type: &str) -> Option<String> {
+    content_type
+        .split(';')
+        .filter_map(|param| param.trim().strip_prefix("charset="))
+        .map(|charset| charset.trim_matches(['"', '\'']).to_string())
+        .find(|charset| !charset.is_empty())
+}
+
+/// Finds a charset declared by either `<meta charset="...">` or
+/// `<meta http-equiv="Content-Type" content="...; charset=...">`.
+fn charset_from_meta(bytes: &[u8]) -> Option<String> {
+    let head = &bytes[..bytes.len().min(CHARSET_PRESCAN_LENGTH)];
+    let head = String::from_utf8_lossy(head).to_lowercase();
+
+    head.match_indices("<meta").find_map(|(start, _)| {
+        let tag = &head[start..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        let value = &tag[tag.find("charset=")? + "charset=".len()..];
+        let charset: String = value
+            .trim_start_matches(['"', '\''])
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+            .collect();
+        (!charset.is_empty()).then_some(charset)
+    })
+}
+
+/// Where a download to `path` is written until it's complete.
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Fetches an offered file and streams it into the downloads directory
+/// chunk by chunk so large files never sit in memory. It's written under a
+/// `.part` name that only becomes the real one once the whole body has
+/// arrived.
+pub async fn download(offer: DownloadOffer, client: Arc<HttpClient>) -> Result<PathBuf, String> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut response = tokio::time::timeout(REQUEST_TIMEOUT, client.client.get(&offer.url).send())
+        .await
+        .map_err(|_| format!("Download failed: no answer in {}s", REQUEST_TIMEOUT.as_secs()))?
+        .map_err(|e| request_error("download", &offer.url, &client.proxies, e))?;
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+
+    let dir = dirs::download_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join("Downloads")))
+        .ok_or("No download directory available")?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    // Named after where the file was actually served from, so a link
+    // like `download?id=…` that redirects to the file keeps its name
+    let name = response
+        .url()
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("download")
+        .to_string();
+
+    // Never overwrite an earlier download of the same name, or one still
+    // in progress
+    let is_free = |path: &Path| !path.exists() && !part_path(path).exists();
+    let mut path = dir.join(&name);
+    if !is_free(&path) {
+        path = fs_ops::unique_name_where(&path, is_free);
+    }
+    let part = part_path(&path);
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&part)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", part.display(), e))?;
+
+    let written = async {
+        while let Some(chunk) = next_chunk(&mut response)
+            .await
+            .map_err(|e| format!("Download failed: {}", e))?
+        {
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+        }
+        file.flush()
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+        drop(file);
+        tokio::fs::rename(&part, &path)
+            .await
+            .map_err(|e| format!("Failed to rename {}: {}", part.display(), e))
+    }
+    .await;
+
+    // Don't leave a truncated file behind
+    if let Err(e) = written {
+        let _ = tokio::fs::remove_file(&part).await;
+        return Err(e);
+    }
+    Ok(path)
+}
+
+/// Deepest nesting kept by `parse_html`. The walks over the DOM below
+/// recurse, so anything nested further is flattened to its text rather
+/// than risk overflowing the stack.
+const MAX_DOM_DEPTH: usize = 256;
+
+/// Parses a document the way a browser would, so any input gives a tree.
+fn parse_html(html: &str) -> RcDom {
+    let dom = parse_document(RcDom::default(), Default::default()).one(html);
+    flatten_below(&dom.document, MAX_DOM_DEPTH);
+    dom
+}
+
+/// Replaces the children of every node `depth` levels down with a single
+/// text node holding the text beneath it. Works without recursion, since
+/// it runs on trees too deep to recurse over.
+fn flatten_below(root: &Handle, depth: usize) {
+    let mut pending = vec![(root.clone(), 0)];
+    while let Some((handle, level)) = pending.pop() {
+        if level < depth {
+            let children = handle.children.borrow();
+            pending.extend(children.iter().map(|child| (child.clone(), level + 1)));
+            continue;
+        }
+        if handle.children.borrow().is_empty() {
+            continue;
+        }
+
+        let mut text = String::new();
+        let mut descendants: Vec<Handle> = handle.children.borrow().iter().rev().cloned().collect();
+        while let Some(node) = descendants.pop() {
+            match node.data {
+                NodeData::Text { ref contents } => text.push_str(&contents.borrow()),
+                NodeData::Element { ref name, .. }
+                    if matches!(name.local.as_ref(), "script" | "style") => continue,
+                // Keeps the words of neighbouring elements apart
+                NodeData::Element { .. } => text.push(' '),
+                _ => {}
+            }
+            descendants.extend(node.children.borrow().iter().rev().cloned());
+        }
+
+        let flattened = Node::new(NodeData::Text {
+            contents: RefCell::new(text.into()),
+        });
+        flattened.parent.set(Some(Rc::downgrade(&handle)));
+        *handle.children.borrow_mut() = vec![flattened];
+    }
+}
+
+/// Converts an HTML document to its title and a list of text blocks.
+/// Scripts, styles and the document `<head>` are skipped.
+///
+/// ```
+/// use min_desk::page::{extract_text_from_html, ContentBlock};
+///
+/// let page = extract_text_from_html(
+///     "<html><head><title>Notes</title></head>\
+///      <body><h1>Shopping</h1><ul><li>Eggs</li><li>Milk</li></ul>\
+///      <p><b>Fish</b> &amp; chips, it&#x27;s&nbsp;late</p></body></html>",
+/// );
+///
+/// assert_eq!(page.title.as_deref(), Some("Notes"));
+/// assert_eq!(page.blocks, vec![
+///     ContentBlock::Heading(1, "Shopping".to_string()),
+///     ContentBlock::ListItem("Eggs".to_string()),
+///     ContentBlock::ListItem("Milk".to_string()),
+///     ContentBlock::Paragraph("Fish & chips, it's late".to_string()),
+/// ]);
+/// ```
+///
+/// Broken or absurdly nested markup still gives its text rather than
+/// failing:
+///
+/// ```
+/// use min_desk::page::{extract_text_from_html, ContentBlock};
+///
+/// let page = extract_text_from_html("<p>Unclosed <b><i>tags</p></div>&bogus;<");
+/// assert_eq!(page.blocks, vec![
+///     ContentBlock::Paragraph("Unclosed tags".to_string()),
+///     ContentBlock::Paragraph("&bogus;<".to_string()),
+/// ]);
+///
+/// let deep = format!("{}bottom", "<div>".repeat(2_000));
+/// let page = extract_text_from_html(&deep);
+/// assert_eq!(page.blocks, vec![ContentBlock::Paragraph("bottom".to_string())]);
+/// ```
+pub fn extract_text_from_html(html: &str) -> PageContent {
+    let dom = parse_html(html);
+
+    let mut blocks = Vec::new();
+    let mut inline = String::new();
+    extract_text_from_node(&dom.document, &mut blocks, &mut inline);
+    flush_paragraph(&mut blocks, &mut inline);
+
+    PageContent {
+        title: find_tag(&dom.document, "title")
+            .map(|title| collect_text(&title))
+            .filter(|title| !title.is_empty()),
+        blocks,
+    }
+}
+
+/// Elements that start a new block, ending any paragraph in progress.
+const BLOCK_TAGS: &[&str] = &[
+    "html", "body", "p", "div", "section", "article", "main", "header", "footer", "nav",
+    "aside", "ul", "ol", "dl", "dt", "dd", "table", "tr", "td", "th", "blockquote", "pre",
+    "br", "hr", "form", "figure", "figcaption",
+];
+
+/// Walks the DOM emitting blocks. Inline text accumulates in `inline` until
+/// the next block boundary turns it into a paragraph.
+fn extract_text_from_node(handle: &Handle, blocks: &mut Vec<ContentBlock>, inline: &mut String) {
+    match handle.data {
+        // Entities are already decoded by the parser. Text is appended as-is
+        // so inline markup like `<b>Hello</b>, world` doesn't gain spaces
+        NodeData::Text { ref contents } => inline.push_str(&contents.borrow()),
+        NodeData::Element { ref name, .. } => match name.local.as_ref() {
+            // Skip script and style elements, and the title, which is
+            // reported separately
+            "head" | "script" | "style" | "noscript" => {}
+            "pre" => {
+                flush_paragraph(blocks, inline);
+                let text = collect_preformatted(handle);
+                if !text.is_empty() {
+                    blocks.push(ContentBlock::Paragraph(text));
+                }
+            }
+            tag @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                flush_paragraph(blocks, inline);
+                let heading = collect_text(handle);
+                if !heading.is_empty() {
+                    blocks.push(ContentBlock::Heading(tag[1..].parse().unwrap_or(6), heading));
+                }
+            }
+            "li" => {
+                flush_paragraph(blocks, inline);
+                let item = collect_text(handle);
+                if !item.is_empty() {
+                    blocks.push(ContentBlock::ListItem(item));
+                }
+            }
+            "img" => {
+                if let Some(image) = image_block(handle) {
+                    flush_paragraph(blocks, inline);
+                    blocks.push(image);
+                }
+            }
+            tag if BLOCK_TAGS.contains(&tag) => {
+                flush_paragraph(blocks, inline);
+                for child in handle.children.borrow().iter() {
+                    extract_text_from_node(child, blocks, inline);
+                }
+                flush_paragraph(blocks, inline);
+            }
+            _ => {
+                for child in handle.children.borrow().iter() {
+                    extract_text_from_node(child, blocks, inline);
+                }
+            }
+        },
+        _ => {
+            for child in handle.children.borrow().iter() {
+                extract_text_from_node(child, blocks, inline);
+            }
+        }
+    }
+}
+
+fn flush_paragraph(blocks: &mut Vec<ContentBlock>, inline: &mut String) {
+    let paragraph = collapse_whitespace(inline);
+    if !paragraph.is_empty() {
+        blocks.push(ContentBlock::Paragraph(paragraph));
+    }
+    inline.clear();
+}
+
+/// Elements that never carry article content.
+const BOILERPLATE_TAGS: &[&str] = &[
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form",
+];
+
+/// An `Image` block for an `<img>` element, if it has a `src`.
+fn image_block(handle: &Handle) -> Option<ContentBlock> {
+    let NodeData::Element { ref attrs, .. } = handle.data else {
+        return None;
+    };
+    let attrs = attrs.borrow();
+    let attr = |name: &str| {
+        attrs
+            .iter()
+            .find(|attr| attr.name.local.as_ref() == name)
+            .map(|attr| attr.value.trim().to_string())
+    };
+
+    let src = attr("src").filter(|src| !src.is_empty())?;
+    Some(ContentBlock::Image {
+        src,
+        alt: collapse_whitespace(&attr("alt").unwrap_or_default()),
+    })
+}
+
+fn element_name(handle: &Handle) -> Option<String> {
+    match handle.data {
+        NodeData::Element { ref name, .. } => Some(name.local.as_ref().to_string()),
+        _ => None,
+    }
+}
+
+/// Extracts the main article of a page as headings and paragraphs, dropping
+/// navigation and other boilerplate.
+pub fn extract_readable(html: &str) -> Vec<ContentBlock> {
+    let dom = parse_html(html);
+    let root = find_tag(&dom.document, "article")
+        .or_else(|| find_tag(&dom.document, "main"))
+        .or_else(|| densest_paragraph_container(&dom.document).map(|(handle, _)| handle))
+        .unwrap_or_else(|| dom.document.clone());
+
+    let mut blocks = Vec::new();
+    collect_readable_blocks(&root, &mut blocks);
+    blocks
+}
+
+/// Collects every anchor on the page, resolving hrefs against `base_url`.
+/// Empty and `javascript:` hrefs are skipped, as are repeat targets. Links
+/// to local files are only kept on pages that are local files themselves.
+pub fn extract_links(html: &str, base_url: &str) -> Vec<Link> {
+    fn walk(handle: &Handle, base: &reqwest::Url, links: &mut Vec<Link>) {
+        if let NodeData::Element { ref name, ref attrs, .. } = handle.data {
+            if name.local.as_ref() == "a" {
+                let href = attrs
+                    .borrow()
+                    .iter()
+                    .find(|attr| attr.name.local.as_ref() == "href")
+                    .map(|attr| attr.value.trim().to_string());
+
+                let url = href
+                    .filter(|href| !href.is_empty() && !href.to_lowercase().starts_with("javascript:"))
+                    .and_then(|href| base.join(&href).ok())
+                    .filter(|url| match url.scheme() {
+                        "http" | "https" => true,
+                        "file" => base.scheme() == "file",
+                        _ => false,
+                    });
+
+                if let Some(url) = url {
+                    let url = url.to_string();
+                    if !links.iter().any(|link| link.url == url) {
+                        links.push(Link { text: collect_text(handle), url });
+                    }
+                }
+                return;
+            }
+        }
+
+        for child in handle.children.borrow().iter() {
+            walk(child, base, links);
+        }
+    }
+
+    let Ok(base) = reqwest::Url::parse(base_url) else {
+        return Vec::new();
+    };
+    let dom = parse_html(html);
+    let mut links = Vec::new();
+    walk(&dom.document, &base, &mut links);
+    links
+}
+
+/// Finds a `<meta http-equiv="refresh" content="<delay>; url=<target>">`
+/// redirect, resolving the target against `base_url`. A refresh without a
+/// URL only reloads the page, so it isn't reported.
+pub fn extract_meta_refresh(html: &str, base_url: &str) -> Option<MetaRefresh> {
+    fn find_refresh(handle: &Handle) -> Option<String> {
+        if let NodeData::Element { ref name, ref attrs, .. } = handle.data {
+            if name.local.as_ref() == "meta" {
+                let attrs = attrs.borrow();
+                let attr = |key: &str| {
+                    attrs
+                        .iter()
+                        .find(|attr| attr.name.local.as_ref() == key)
+                        .map(|attr| attr.value.to_string())
+                };
+                if attr("http-equiv").is_some_and(|equiv| equiv.trim().eq_ignore_ascii_case("refresh")) {
+                    return attr("content");
+                }
+            }
+        }
+        handle.children.borrow().iter().find_map(find_refresh)
+    }
+
+    let content = find_refresh(&parse_html(html).document)?;
+    let (delay, target) = content.split_once([';', ',']).unwrap_or((&content, ""));
+    let delay = delay.trim().parse::<f64>().ok().filter(|delay| *delay >= 0.0)? as u64;
+
+    let target = target.trim();
+    let target = match target.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+            target[3..].trim_start().strip_prefix('=').unwrap_or(&target[3..])
+        }
+        _ => target,
+    };
+    let target = target.trim().trim_matches(['"', '\'']);
+    if target.is_empty() {
+        return None;
+    }
+
+    let base = reqwest::Url::parse(base_url).ok()?;
+    let url = base.join(target).ok().filter(|url| match url.scheme() {
+        "http" | "https" => true,
+        "file" => base.scheme() == "file",
+        _ => false,
+    })?;
+    Some(MetaRefresh { delay, url: url.to_string() })
+}
+
+fn find_tag(handle: &Handle, tag: &str) -> Option<Handle> {
+    if element_name(handle).as_deref() == Some(tag) {
+        return Some(handle.clone());
+    }
+    handle
+        .children
+        .borrow()
+        .iter()
+        .find_map(|child| find_tag(child, tag))
+}
+
+/// Finds the element whose direct `<p>` children hold the most text.
+fn densest_paragraph_container(handle: &Handle) -> Option<(Handle, usize)> {
+    if let Some(name) = element_name(handle) {
+        if BOILERPLATE_TAGS.contains(&name.as_str()) {
+            return None;
+        }
+    }
+
+    let children = handle.children.borrow();
+    let score: usize = children
+        .iter()
+        .filter(|child| element_name(child).as_deref() == Some("p"))
+        .map(|child| collect_text(child).len())
+        .sum();
+
+    let mut best = (score > 0).then(|| (handle.clone(), score));
+    for child in children.iter() {
+        if let Some(candidate) = densest_paragraph_container(child) {
+            let better = match &best {
+                Some((_, score)) => candidate.1 > *score,
+                None => true,
+            };
+            if better {
+                best = Some(candidate);
+            }
+        }
+    }
+    best
+}
+
+fn collect_readable_blocks(handle: &Handle, blocks: &mut Vec<ContentBlock>) {
+    match element_name(handle).as_deref() {
+        Some(name) if BOILERPLATE_TAGS.contains(&name) => {}
+        Some(name @ ("h1" | "h2" | "h3")) => {
+            let heading = collect_text(handle);
+            if !heading.is_empty() {
+                let level = name[1..].parse().unwrap_or(3);
+                blocks.push(ContentBlock::Heading(level, heading));
+            }
+        }
+        Some("li") => {
+            let item = collect_text(handle);
+            if !item.is_empty() {
+                blocks.push(ContentBlock::ListItem(item));
+            }
+        }
+        Some("pre") => {
+            let text = collect_preformatted(handle);
+            if !text.is_empty() {
+                blocks.push(ContentBlock::Paragraph(text));
+            }
+        }
+        Some("img") => blocks.extend(image_block(handle)),
+        Some("p" | "blockquote" | "h4" | "h5" | "h6") => {
+            let paragraph = collect_text(handle);
+            if !paragraph.is_empty() {
+                blocks.push(ContentBlock::Paragraph(paragraph));
+            }
+        }
+        _ => {
+            for child in handle.children.borrow().iter() {
+                if let NodeData::Text { ref contents } = child.data {
+                    let text = collapse_whitespace(&contents.borrow());
+                    if !text.is_empty() {
+                        blocks.push(ContentBlock::Paragraph(text));
+                    }
+                } else {
+                    collect_readable_blocks(child, blocks);
+                }
+            }
+        }
+    }
+}
+
+/// Concatenates all text beneath `handle` with whitespace collapsed.
+fn collect_text(handle: &Handle) -> String {
+    fn walk(handle: &Handle, output: &mut String) {
+        match handle.data {
+            NodeData::Text { ref contents } => output.push_str(&contents.borrow()),
+            NodeData::Element { ref name, .. }
+                if matches!(name.local.as_ref(), "script" | "style") => {}
+            // Nested blocks are separate words even without whitespace
+            // between them in the source
+            NodeData::Element { ref name, .. } if is_block(name.local.as_ref()) => {
+                output.push(' ');
+                for child in handle.children.borrow().iter() {
+                    walk(child, output);
+                }
+                output.push(' ');
+            }
+            _ => {
+                for child in handle.children.borrow().iter() {
+                    walk(child, output);
+                }
+            }
+        }
+    }
+
+    let mut output = String::new();
+    walk(handle, &mut output);
+    collapse_whitespace(&output)
+}
+
+fn is_block(tag: &str) -> bool {
+    BLOCK_TAGS.contains(&tag) || matches!(tag, "li" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+}
+
+/// Text beneath a `<pre>`, keeping its line breaks and indentation.
+fn collect_preformatted(handle: &Handle) -> String {
+    fn walk(handle: &Handle, output: &mut String) {
+        match handle.data {
+            NodeData::Text { ref contents } => output.push_str(&contents.borrow()),
+            _ => {
+                for child in handle.children.borrow().iter() {
+                    walk(child, output);
+                }
+            }
+        }
+    }
+
+    let mut output = String::new();
+    walk(handle, &mut output);
+    output.trim_matches('\n').trim_end().to_string()
+}
+
+/// Cuts every match of `patterns` out of the text of `blocks`, dropping
+/// blocks left without any text. Images are kept as they are.
+pub fn remove_text(blocks: &mut Vec<ContentBlock>, patterns: &[regex::Regex]) {
+    if patterns.is_empty() {
+        return;
+    }
+
+    blocks.retain_mut(|block| {
+        let text = match block {
+            ContentBlock::Heading(_, text)
+            | ContentBlock::Paragraph(text)
+            | ContentBlock::ListItem(text) => text,
+            ContentBlock::Image { .. } => return true,
+        };
+        let mut changed = false;
+        for pattern in patterns {
+            if let std::borrow::Cow::Owned(removed) = pattern.replace_all(text, "") {
+                *text = removed;
+                changed = true;
+            }
+        }
+        // Only tidied when something was removed, which keeps <pre> intact
+        if changed {
+            *text = text.trim().to_string();
+        }
+        !text.is_empty()
+    });
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pieces of markup that `malformed_html` strings together at random.
+    const FRAGMENTS: &[&str] = &[
+        "<", ">", "</", "/>", "<div", "<p>", "</p>", "<li>", "<h2>", "</h2>", "<pre>", "<b>",
+        "<a href=\"", "\"", "'", "=", "<!--", "-->", "<![CDATA[", "]]>", "<script>",
+        "</script>", "<style>", "<table><tr><td>", "<title>", "<img src=", "alt=",
+        "<meta http-equiv=refresh content=\"0;url=", "&amp", "&#x", "&#99999999;", ";",
+        "javascript:", "text", " ", "\n", "\0", "é", "\u{202e}", "<template>",
+        "<svg><foreignObject>",
+    ];
+
+    /// A reproducible jumble of `length` fragments.
+    fn malformed_html(seed: u64, length: usize) -> String {
+        let mut state = seed;
+        (0..length)
+            .map(|_| {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                FRAGMENTS[state as usize % FRAGMENTS.len()]
+            })
+            .collect()
+    }
+
+    /// Deepest element nesting under `handle`, found without recursing.
+    fn depth(handle: &Handle) -> usize {
+        let mut deepest = 0;
+        let mut pending = vec![(handle.clone(), 0)];
+        while let Some((handle, level)) = pending.pop() {
+            deepest = deepest.max(level);
+            pending.extend(handle.children.borrow().iter().map(|child| (child.clone(), level + 1)));
+        }
+        deepest
+    }
+
+    #[test]
+    fn malformed_html_never_panics() {
+        for seed in 1..=300 {
+            let html = malformed_html(seed, 200);
+            extract_text_from_html(&html);
+            extract_readable(&html);
+            extract_links(&html, "https://example.com/page");
+            extract_meta_refresh(&html, "https://example.com/page");
+        }
+    }
+
+    #[test]
+    fn unterminated_markup_keeps_its_text() {
+        let page = extract_text_from_html("<p>one<p>two</div></span><li>three<!-- open comment");
+        assert_eq!(page.blocks, vec![
+            ContentBlock::Paragraph("one".to_string()),
+            ContentBlock::Paragraph("two".to_string()),
+            ContentBlock::ListItem("three".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn deep_nesting_is_flattened() {
+        let nesting = "<div>".repeat(MAX_DOM_DEPTH * 4);
+        let html = format!("{}<b>deep</b> <script>x()</script>text", nesting);
+
+        assert!(depth(&parse_html(&html).document) <= MAX_DOM_DEPTH + 1);
+        assert_eq!(
+            extract_text_from_html(&html).blocks,
+            vec![ContentBlock::Paragraph("deep text".to_string())]
+        );
+    }
+
+    #[test]
+    fn remove_text_cuts_matches_and_drops_emptied_blocks() {
+        let mut blocks = vec![
+            ContentBlock::Heading(1, "News".to_string()),
+            ContentBlock::Paragraph("We use cookies. Accept?".to_string()),
+            ContentBlock::Paragraph("Story text. We use cookies.".to_string()),
+            ContentBlock::Image { src: "a.png".to_string(), alt: String::new() },
+            ContentBlock::Paragraph("  indented\n    code".to_string()),
+        ];
+        let patterns = [
+            regex::Regex::new(&regex::escape("We use cookies.")).unwrap(),
+            regex::Regex::new(r"^\s*Accept\?$").unwrap(),
+        ];
+
+        remove_text(&mut blocks, &patterns);
+
+        assert_eq!(blocks, vec![
+            ContentBlock::Heading(1, "News".to_string()),
+            ContentBlock::Paragraph("Story text.".to_string()),
+            ContentBlock::Image { src: "a.png".to_string(), alt: String::new() },
+            ContentBlock::Paragraph("  indented\n    code".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn shallow_documents_are_left_alone() {
+        let html = "<div><p>kept <b>as</b> is</p></div>";
+        let dom = parse_html(html);
+        assert!(depth(&dom.document) < MAX_DOM_DEPTH);
+        assert_eq!(
+            find_tag(&dom.document, "b").map(|b| collect_text(&b)),
+            Some("as".to_string())
+        );
+    }
+
+    fn paragraph(text: &str) -> ContentBlock {
+        ContentBlock::Paragraph(text.to_string())
+    }
+
+    #[test]
+    fn nested_elements_become_blocks() {
+        let page = extract_text_from_html(
+            "<html><head><title> The  Title </title></head><body>\
+             <div><p>One <b>bold <i>nested</i></b> word</p><div><section><p>Two</p></section></div></div>\
+             <h2>Heading <em>two</em></h2>\
+             <ul><li>First <a href=\"/x\">link</a></li><li><p>Second</p></li></ul>\
+             </body></html>",
+        );
+        assert_eq!(page.title.as_deref(), Some("The Title"));
+        assert_eq!(
+            page.blocks,
+            [
+                paragraph("One bold nested word"),
+                paragraph("Two"),
+                ContentBlock::Heading(2, "Heading two".to_string()),
+                ContentBlock::ListItem("First link".to_string()),
+                ContentBlock::ListItem("Second".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn scripts_and_styles_are_skipped() {
+        let page = extract_text_from_html(
+            "<p>Before</p>\
+             <script>document.write('<p>injected</p>')</script>\
+             <style>p { color: red }</style>\
+             <noscript>Enable JavaScript</noscript>\
+             <p>After <script>var x = 1;</script>this</p>",
+        );
+        assert_eq!(page.blocks, [paragraph("Before"), paragraph("After this")]);
+    }
+
+    #[test]
+    fn named_entities_are_decoded() {
+        let page = extract_text_from_html("<p>&lt;b&gt; is bold &amp; &eacute;l&eacute;gant</p>");
+        assert_eq!(page.blocks, [paragraph("<b> is bold & élégant")]);
+    }
+
+    #[test]
+    fn numeric_entities_and_escaped_ampersands_are_decoded() {
+        let page = extract_text_from_html(
+            "<p>Tom &amp; Jerry &lt;3 &#x27;quoted&#x27; &#39;too&#39; &copy; &eacute;l&egrave;ve</p>\
+             <p>&amp;amp; stays &amp;amp;</p>",
+        );
+        assert_eq!(
+            page.blocks,
+            [paragraph("Tom & Jerry <3 'quoted' 'too' © élève"), paragraph("&amp; stays &amp;")]
+        );
+    }
+
+    #[test]
+    fn whitespace_collapses_within_blocks_only() {
+        let page = extract_text_from_html(
+            "<p>  lots \n\n of\t  space&nbsp;&nbsp;here </p>\
+             <p>One</p><p>Two</p>line<br>break\
+             <pre>keep\n  this   layout</pre>",
+        );
+        assert_eq!(
+            page.blocks,
+            [
+                paragraph("lots of space here"),
+                paragraph("One"),
+                paragraph("Two"),
+                paragraph("line"),
+                paragraph("break"),
+                paragraph("keep\n  this   layout"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use iced::{
+    alignment, theme, widget::{button, checkbox, column, container, pick_list, row, scrollable, text, text_input},
+    Command, Element, Length,
+};
+
+use crate::config::Config;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ThemeSelected(&'static str),
+    DensitySelected(&'static str),
+    UpdateFontSize(String),
+    UpdateHomepage(String),
+    UpdateSearchEngine(String),
+    UpdateHttpProxy(String),
+    UpdateHttpsProxy(String),
+    UpdateDefaultPath(String),
+    UpdateBackend(String),
+    ToggleMinimalMode(bool),
+    ToggleRestoreSession(bool),
+    ToggleLoadImages(bool),
+    ToggleMonospace(bool),
+    ToggleNotifications(bool),
+    ToggleRestoreLastPath(bool),
+    SortSelected(&'static str),
+    SortOrderSelected(&'static str),
+    ToggleCompositor(bool),
+    ToggleRememberWindow(bool),
+    Save,
+    Revert,
+    Close,
+}
+
+const THEMES: &[&str] = &["dark", "light", "system"];
+
+const DENSITIES: &[&str] = &["compact", "comfortable", "spacious"];
+
+const SORT_KEYS: &[&str] = &["name", "size", "modified"];
+
+const SORT_ORDERS: &[&str] = &["asc", "desc"];
+
+const LABEL_WIDTH: f32 = 200.0;
+
+pub struct SettingsPanel {
+    /// Edited copy of the config, written out on save.
+    draft: Config,
+    /// Kept as text so partially typed values don't get rejected mid-edit.
+    font_size_input: String,
+    error_message: Option<String>,
+    status_message: Option<String>,
+}
+
+impl SettingsPanel {
+    pub fn new(config: Config) -> Self {
+        Self {
+            font_size_input: config.desktop.font_size.to_string(),
+            draft: config,
+            error_message: None,
+            status_message: None,
+        }
+    }
+
+    /// Starts editing from the config on disk, which may include changes
+    /// saved by the other apps since this screen was last opened.
+    pub fn open(&mut self) {
+        *self = Self::new(Config::load(None).unwrap_or_else(|_| self.draft.clone()));
+    }
+
+    pub fn update(&mut self, message: Message) -> Command<Message> {
+        self.status_message = None;
+
+        match message {
+            Message::ThemeSelected(theme) => {
+                self.draft.desktop.theme = theme.to_string();
+            }
+            Message::DensitySelected(density) => {
+                self.draft.desktop.density = density.to_string();
+            }
+            Message::UpdateFontSize(size) => {
+                self.font_size_input = size;
+            }
+            Message::UpdateHomepage(homepage) => {
+                self.draft.applications.browser.homepage = homepage;
+            }
+            Message::UpdateSearchEngine(template) => {
+                self.draft.applications.browser.search_engine = template;
+            }
+            Message::UpdateHttpProxy(proxy) => {
+                self.draft.applications.browser.http_proxy = (!proxy.is_empty()).then_some(proxy);
+            }
+            Message::UpdateHttpsProxy(proxy) => {
+                self.draft.applications.browser.https_proxy = (!proxy.is_empty()).then_some(proxy);
+            }
+            Message::UpdateDefaultPath(path) => {
+                self.draft.applications.file_manager.default_path = path;
+            }
+            Message::UpdateBackend(backend) => {
+                self.draft.applications.package_manager.backend = backend;
+            }
+            Message::ToggleMinimalMode(enabled) => {
+                self.draft.applications.browser.minimal_mode = enabled;
+            }
+            Message::ToggleRestoreSession(enabled) => {
+                self.draft.applications.browser.restore_session = enabled;
+            }
+            Message::ToggleLoadImages(enabled) => {
+                self.draft.applications.browser.load_images = enabled;
+            }
+            Message::ToggleMonospace(enabled) => {
+                self.draft.applications.browser.monospace = enabled;
+            }
+            Message::ToggleNotifications(enabled) => {
+                self.draft.applications.package_manager.notifications = enabled;
+            }
+            Message::ToggleRestoreLastPath(enabled) => {
+                self.draft.applications.file_manager.restore_last_path = enabled;
+            }
+            Message::SortSelected(sort) => {
+                self.draft.applications.file_manager.default_sort = sort.to_string();
+            }
+            Message::SortOrderSelected(order) => {
+                self.draft.applications.file_manager.default_sort_order = order.to_string();
+            }
+            Message::ToggleCompositor(enabled) => {
+                self.draft.system.compositor = enabled;
+            }
+            Message::ToggleRememberWindow(enabled) => {
+                self.draft.system.remember_window = enabled;
+            }
+            Message::Revert => {
+                self.open();
+            }
+            // Saving is handled by the desktop, which applies the result
+            Message::Save | Message::Close => {}
+        }
+        Command::none()
+    }
+
+    /// Validates and writes the draft, returning it so the caller can apply
+    /// it to the running apps.
+    pub fn save(&mut self) -> Option<Config> {
+        let font_size = match self.font_size_input.trim().parse::<u16>() {
+            Ok(size) if (6..=48).contains(&size) => size,
+            _ => {
+                self.error_message = Some("Error: Font size must be a number from 6 to 48".to_string());
+                return None;
+            }
+        };
+        self.draft.desktop.font_size = font_size;
+
+        match self.draft.save() {
+            Ok(()) => {
+                self.error_message = None;
+                self.status_message = Some("Settings saved".to_string());
+                Some(self.draft.clone())
+            }
+            Err(e) => {
+                self.error_message = Some(format!("Error: Failed to save settings: {}", e));
+                None
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<Message> {
+        let header = self.create_header();
+        let form = self.create_form();
+        let status_bar = self.create_status_bar();
+
+        let main_content = column![
+            header,
+            form,
+            status_bar,
+        ]
+        .spacing(5)
+        .padding(10);
+
+        container(main_content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(|theme: &iced::Theme| {
+                let palette = theme.extended_palette();
+                container::Appearance {
+                    background: Some(iced::Background::Color(palette.background.base.color)),
+                    border: iced::Border::with_radius(0),
+                    ..Default::default()
+                }
+            })
+            .into()
+    }
+
+    fn create_header(&self) -> Element<Message> {
+        let close_btn = button(text("✕").size(20))
+            .on_press(Message::Close)
+            .padding(5)
+            .style(|theme: &iced::Theme, status| {
+                let palette = theme.extended_palette();
+                button::Appearance {
+                    background: Some(iced::Background::Color(
+                        if matches!(status, button::Status::Hovered) {
+                            iced::Color::from_rgba8(255, 100, 100, 0.8)
+                        } else {
+                            iced::Color::TRANSPARENT
+                        }
+                    )),
+                    border: iced::Border::with_radius(4),
+                    text_color: palette.background.base.text,
+                    ..Default::default()
+                }
+            });
+
+        let revert_btn = button(text("Revert").size(14))
+            .on_press(Message::Revert)
+            .padding(8);
+
+        let save_btn = button(text("Save").size(14))
+            .on_press(Message::Save)
+            .padding(8)
+            .style(|theme: &iced::Theme, _| {
+                button::Appearance {
+                    background: Some(iced::Background::Color(
+                        theme.palette().primary
+                    )),
+                    border: iced::Border::with_radius(4),
+                    text_color: theme.extended_palette().primary.base.text,
+                    ..Default::default()
+                }
+            });
+
+        row![
+            text("⚙ Settings").size(18),
+            row![].width(Length::Fill),
+            revert_btn,
+            save_btn,
+            close_btn,
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center)
+        .into()
+    }
+
+    fn create_form(&self) -> Element<Message> {
+        let selected_theme = THEMES
+            .iter()
+            .find(|theme| **theme == self.draft.desktop.theme)
+            .copied();
+        let selected_density = DENSITIES
+            .iter()
+            .find(|density| **density == self.draft.desktop.density)
+            .copied();
+        let file_manager = &self.draft.applications.file_manager;
+        let selected_sort = SORT_KEYS.iter().find(|sort| **sort == file_manager.default_sort).copied();
+        let selected_order = SORT_ORDERS
+            .iter()
+            .find(|order| **order == file_manager.default_sort_order)
+            .copied();
+
+        let form = column![
+            text("Desktop").size(16),
+            field(
+                "Theme",
+                pick_list(THEMES, selected_theme, Message::ThemeSelected),
+            ),
+            field(
+                "List density",
+                pick_list(DENSITIES, selected_density, Message::DensitySelected),
+            ),
+            field(
+                "Font size (restart)",
+                text_input("12", &self.font_size_input)
+                    .on_input(Message::UpdateFontSize)
+                    .padding(5)
+                    .width(Length::Fixed(80.0)),
+            ),
+            field(
+                "Use compositor (restart)",
+                checkbox("", self.draft.system.compositor)
+                    .on_toggle(Message::ToggleCompositor),
+            ),
+            field(
+                "Remember window size",
+                checkbox("", self.draft.system.remember_window)
+                    .on_toggle(Message::ToggleRememberWindow),
+            ),
+            text("Files").size(16),
+            field(
+                "Default folder",
+                text_input("/home", &self.draft.applications.file_manager.default_path)
+                    .on_input(Message::UpdateDefaultPath)
+                    .padding(5),
+            ),
+            field(
+                "Reopen last folder",
+                checkbox("", self.draft.applications.file_manager.restore_last_path)
+                    .on_toggle(Message::ToggleRestoreLastPath),
+            ),
+            field(
+                "Sort by",
+                row![
+                    pick_list(SORT_KEYS, selected_sort, Message::SortSelected),
+                    pick_list(SORT_ORDERS, selected_order, Message::SortOrderSelected),
+                ]
+                .spacing(10),
+            ),
+            text("Packages").size(16),
+            field(
+                "Backend",
+                text_input("apk", &self.draft.applications.package_manager.backend)
+                    .on_input(Message::UpdateBackend)
+                    .padding(5)
+                    .width(Length::Fixed(200.0)),
+            ),
+            field(
+                "Notify when done",
+                checkbox("", self.draft.applications.package_manager.notifications)
+                    .on_toggle(Message::ToggleNotifications),
+            ),
+            text("Browser").size(16),
+            field(
+                "Homepage",
+                text_input("https://", &self.draft.applications.browser.homepage)
+                    .on_input(Message::UpdateHomepage)
+                    .padding(5),
+            ),
+            field(
+                "Search URL ({} is the query)",
+                text_input("https://", &self.draft.applications.browser.search_engine)
+                    .on_input(Message::UpdateSearchEngine)
+                    .padding(5),
+            ),
+            field(
+                "HTTP proxy",
+                text_input(
+                    "from http_proxy",
+                    self.draft.applications.browser.http_proxy.as_deref().unwrap_or(""),
+                )
+                .on_input(Message::UpdateHttpProxy)
+                .padding(5),
+            ),
+            field(
+                "HTTPS proxy",
+                text_input(
+                    "from https_proxy",
+                    self.draft.applications.browser.https_proxy.as_deref().unwrap_or(""),
+                )
+                .on_input(Message::UpdateHttpsProxy)
+                .padding(5),
+            ),
+            field(
+                "Minimal mode",
+                checkbox("", self.draft.applications.browser.minimal_mode)
+                    .on_toggle(Message::ToggleMinimalMode),
+            ),
+            field(
+                "Reopen tabs on startup",
+                checkbox("", self.draft.applications.browser.restore_session)
+                    .on_toggle(Message::ToggleRestoreSession),
+            ),
+            field(
+                "Load images",
+                checkbox("", self.draft.applications.browser.load_images)
+                    .on_toggle(Message::ToggleLoadImages),
+            ),
+            field(
+                "Monospace text",
+                checkbox("", self.draft.applications.browser.monospace)
+                    .on_toggle(Message::ToggleMonospace),
+            ),
+        ]
+        .spacing(12)
+        .max_width(700);
+
+        container(scrollable(container(form).padding(20)))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(5)
+            .style(|theme: &iced::Theme| {
+                let palette = theme.extended_palette();
+                container::Appearance {
+                    background: Some(iced::Background::Color(palette.background.weak.color)),
+                    border: iced::Border {
+                        color: palette.background.strong.color,
+                        width: 1.0,
+                        radius: 4.0.into(),
+                    },
+                    ..Default::default()
+                }
+            })
+            .into()
+    }
+
+    fn create_status_bar(&self) -> Element<Message> {
+        let status_text = if let Some(error) = &self.error_message {
+            text(error).style(theme::Text::Color(iced::Color::from_rgb(1.0, 0.4, 0.4)))
+        } else if let Some(message) = &self.status_message {
+            text(message).style(theme::Text::Color(iced::Color::from_rgb(0.4, 0.8, 0.4)))
+        } else {
+            text("Changes apply when saved")
+                .style(theme::Text::Color(iced::Color::from_rgb(0.7, 0.7, 0.7)))
+        };
+
+        container(status_text.size(12))
+            .padding(5)
+            .width(Length::Fill)
+            .into()
+    }
+}
+
+/// A labelled row in the settings form.
+fn field<'a>(label: &str, control: impl Into<Element<'a, Message>>) -> Element<'a, Message> {
+    row![
+        text(label.to_string()).size(14).width(Length::Fixed(LABEL_WIDTH)),
+        control.into(),
+    ]
+    .spacing(10)
+    .align_items(alignment::Alignment::Center)
+    .into()
+}
@@ -0,0 +1,58 @@
+//! Small widgets shared by the apps.
+
+use iced::widget::{text, Text};
+use std::time::{Duration, SystemTime};
+
+/// How often the spinner advances. The desktop redraws at this rate while
+/// any app is busy.
+pub const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// How tightly the file and package lists are packed, from the `density`
+/// setting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Density {
+    Compact,
+    Comfortable,
+    Spacious,
+}
+
+impl Density {
+    /// Parses the config's `density`, falling back to comfortable.
+    pub fn from_config(value: &str) -> Self {
+        match value {
+            "compact" => Density::Compact,
+            "spacious" => Density::Spacious,
+            _ => Density::Comfortable,
+        }
+    }
+
+    /// Scales a row's padding or spacing.
+    pub fn padding(self, base: u16) -> u16 {
+        match self {
+            Density::Compact => base / 2,
+            Density::Comfortable => base,
+            Density::Spacious => base * 3 / 2,
+        }
+    }
+
+    /// Scales a text size, more gently than padding so text stays readable.
+    pub fn text_size(self, base: u16) -> u16 {
+        match self {
+            Density::Compact => base.saturating_sub(1),
+            Density::Comfortable => base,
+            Density::Spacious => base + 2,
+        }
+    }
+}
+
+/// A braille spinner. The frame comes from the clock, so every spinner on
+/// screen moves in step without the apps tracking any state for it.
+pub fn spinner() -> Text<'static> {
+    let elapsed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let frame = (elapsed.as_millis() / SPINNER_INTERVAL.as_millis()) as usize;
+    text(SPINNER_FRAMES[frame % SPINNER_FRAMES.len()])
+}